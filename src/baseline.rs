@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::errors::{AnsibleSecError, Result};
+use crate::model::absolute;
+use crate::scanner::{Finding, ScanFindings, Summary};
+
+const BASELINE_VERSION: u32 = 1;
+
+/// Findings accepted when a project adopted ansiblesec. Entries are matched
+/// by fingerprint, so they survive edits that only move code around. Files
+/// are recorded relative to the directory of the baseline file, so the
+/// baseline matches whichever directory the scan runs from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Baseline {
+    pub version: u32,
+    pub findings: Vec<BaselineEntry>,
+}
+
+/// A recorded finding. `line` and `message` are kept for readers; only the
+/// fingerprint is matched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BaselineEntry {
+    pub fingerprint: String,
+    pub rule_id: String,
+    pub file: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl Baseline {
+    /// Record every finding that is not suppressed, with paths relative to
+    /// `dir`, the directory the baseline file is saved in.
+    pub fn create(findings: &ScanFindings, dir: &Path) -> Self {
+        let mut entries = Vec::new();
+
+        for file_finding in findings.files() {
+            let lines = source_lines(&file_finding.file_path);
+            let file = relative_to(&file_finding.file_path, dir);
+
+            for finding in file_finding.findings.iter().filter(|f| !f.is_suppressed()) {
+                entries.push(BaselineEntry {
+                    fingerprint: fingerprint(&file, finding, &lines),
+                    rule_id: finding.rule_id.clone(),
+                    file: file.clone(),
+                    line: finding.line,
+                    message: finding.message.clone(),
+                });
+            }
+        }
+
+        entries.sort_by(|a, b| (&a.file, a.line, &a.rule_id).cmp(&(&b.file, b.line, &b.rule_id)));
+
+        Self {
+            version: BASELINE_VERSION,
+            findings: entries,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let baseline: Self = serde_json::from_str(&content)?;
+        if baseline.version != BASELINE_VERSION {
+            return Err(AnsibleSecError::BaselineError(format!(
+                "{}: unsupported baseline version {}",
+                path.display(),
+                baseline.version
+            )));
+        }
+        Ok(baseline)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Mark findings already in the baseline, recount the summary and list
+    /// the baseline entries that were not found again as fixed. `dir` is the
+    /// directory of the baseline file.
+    pub fn apply(&self, findings: &mut ScanFindings, dir: &Path) {
+        let mut remaining: HashMap<&str, Vec<&BaselineEntry>> = HashMap::new();
+        for entry in &self.findings {
+            remaining
+                .entry(entry.fingerprint.as_str())
+                .or_default()
+                .push(entry);
+        }
+
+        // A file may be listed under several categories; decide once per file
+        let mut baselined: HashMap<_, Vec<bool>> = HashMap::new();
+        for file_finding in findings.files() {
+            let lines = source_lines(&file_finding.file_path);
+            let file = relative_to(&file_finding.file_path, dir);
+
+            let flags = file_finding
+                .findings
+                .iter()
+                .map(|finding| {
+                    !finding.is_suppressed()
+                        && remaining
+                            .get_mut(fingerprint(&file, finding, &lines).as_str())
+                            .and_then(|entries| entries.pop())
+                            .is_some()
+                })
+                .collect();
+            baselined.insert(file_finding.file_path.clone(), flags);
+        }
+
+        for file_finding in findings.files_mut() {
+            if let Some(flags) = baselined.get(&file_finding.file_path) {
+                for (finding, &flag) in file_finding.findings.iter_mut().zip(flags) {
+                    finding.baselined = flag;
+                }
+            }
+        }
+
+        let mut fixed: Vec<BaselineEntry> = remaining.into_values().flatten().cloned().collect();
+        fixed.sort_by(|a, b| (&a.file, a.line, &a.rule_id).cmp(&(&b.file, b.line, &b.rule_id)));
+
+        findings.summary = Summary::count(findings.files());
+        findings.fixed = fixed;
+    }
+}
+
+/// Hash of the rule, the file and the finding's source line with
+/// whitespace collapsed, so unrelated edits elsewhere in the file do not
+/// change it.
+fn fingerprint(file: &str, finding: &Finding, lines: &[String]) -> String {
+    let snippet = finding
+        .line
+        .checked_sub(1)
+        .and_then(|idx| lines.get(idx))
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .unwrap_or_default();
+
+    let mut hasher = blake3::Hasher::new();
+    for part in [finding.rule_id.as_str(), file, snippet.as_str()] {
+        hasher.update(part.as_bytes());
+        hasher.update(&[0]);
+    }
+    hasher.finalize().to_hex()[..32].to_string()
+}
+
+/// `path` relative to `dir` with `/` separators, going up with `..` when it
+/// is outside `dir`. Relative paths on either side are taken from the working
+/// directory.
+fn relative_to(path: &Path, dir: &Path) -> String {
+    let (path, dir) = (absolute(path), absolute(dir));
+    let path: Vec<Component> = path.components().collect();
+    let dir: Vec<Component> = dir.components().collect();
+    let common = path.iter().zip(&dir).take_while(|(a, b)| a == b).count();
+
+    let relative: PathBuf = dir[common..]
+        .iter()
+        .map(|_| Component::ParentDir)
+        .chain(path[common..].iter().copied())
+        .collect();
+    relative.to_string_lossy().replace('\\', "/")
+}
+
+fn source_lines(path: &Path) -> Vec<String> {
+    fs::read_to_string(path)
+        .map(|content| content.lines().map(str::to_string).collect())
+        .unwrap_or_default()
+}
@@ -3,10 +3,13 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::config::Config;
 use crate::scanner::FileFinding;
 
 pub struct Cache {
     cache_dir: PathBuf,
+    /// Identifies the settings findings depend on
+    key: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -17,7 +20,30 @@ struct CacheEntry {
 }
 
 impl Cache {
-    pub fn new(cache_dir: Option<PathBuf>) -> Self {
+    /// The cache key for a configuration: the settings, the contents of the
+    /// rules files they name and the ansiblesec version, so editing a rules
+    /// file or upgrading does not reuse old findings.
+    pub fn key(config: &Config) -> crate::errors::Result<String> {
+        let mut key = format!(
+            "{}\n{}",
+            env!("CARGO_PKG_VERSION"),
+            serde_yaml::to_string(config)?
+        );
+        let rules_files = [&config.policies.rules_file, &config.secrets.rules_file];
+        // A missing default rules file means the built-in rules, covered by
+        // the version
+        for rules_file in rules_files.into_iter().flatten() {
+            if let Ok(content) = fs::read(rules_file) {
+                key.push('\n');
+                key.push_str(&blake3::hash(&content).to_hex());
+            }
+        }
+        Ok(key)
+    }
+
+    /// A cache whose entries only hit under the same `key`, so findings are
+    /// not reused after the configuration changes.
+    pub fn new(cache_dir: Option<PathBuf>, key: &str) -> Self {
         let dir = cache_dir.unwrap_or_else(|| {
             let mut path = dirs::cache_dir().unwrap_or_else(|| PathBuf::from("."));
             path.push("ansiblesec");
@@ -27,7 +53,10 @@ impl Cache {
         // Create cache directory if it doesn't exist
         fs::create_dir_all(&dir).ok();
 
-        Self { cache_dir: dir }
+        Self {
+            cache_dir: dir,
+            key: key.to_string(),
+        }
     }
 
     pub fn get(&self, file_path: &Path) -> crate::errors::Result<FileFinding> {
@@ -85,6 +114,7 @@ impl Cache {
     fn hash_file(&self, file_path: &Path) -> crate::errors::Result<String> {
         let content = fs::read(file_path)?;
         let mut hasher = Hasher::new();
+        hasher.update(self.key.as_bytes());
         hasher.update(&content);
         Ok(hasher.finalize().to_hex().to_string())
     }
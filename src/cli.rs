@@ -1,13 +1,24 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use log::info;
-use std::path::PathBuf;
-
-use crate::config::Config;
+use std::path::{Path, PathBuf};
+
+use crate::baseline::Baseline;
+use crate::config::ConfigLoader;
+use crate::errors::AnsibleSecError;
+use crate::linter::Linter;
+use crate::model::FileKind;
+use crate::profiles;
+use crate::references::ReferenceChecker;
 use crate::reporting::{OutputFormat, Reporter};
-use crate::rules::RulesEngine;
+use crate::roles::RoleChecker;
+use crate::rules::{self, RulesEngine};
 use crate::sbom::SbomGenerator;
 use crate::scanner::Scanner;
+use crate::schema::{self, SchemaKind};
+use crate::secrets::SecretDetector;
+use crate::selftest;
+use crate::validation;
 
 #[derive(Parser)]
 #[command(name = "ansiblesec")]
@@ -57,7 +68,27 @@ enum Commands {
         #[arg(long)]
         fail_on_findings: bool,
 
-        /// Number of threads for scanning
+        /// Report inline suppressions that do not give a reason
+        #[arg(long)]
+        require_suppression_reason: bool,
+
+        /// Rule profile: minimal, default, strict, production or one from the config
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+
+        /// Only count findings that are not in this baseline file
+        #[arg(long, value_name = "FILE", conflicts_with = "baseline_create")]
+        baseline: Option<PathBuf>,
+
+        /// Record the current findings as a baseline file
+        #[arg(long, value_name = "FILE")]
+        baseline_create: Option<PathBuf>,
+
+        /// Print the files that would be scanned and why others are skipped, then exit
+        #[arg(long)]
+        list_files: bool,
+
+        /// Number of threads for scanning; 0 uses `general.parallel_jobs`
         #[arg(short, long, default_value = "0")]
         threads: usize,
 
@@ -89,6 +120,17 @@ enum Commands {
         ci_mode: bool,
     },
 
+    /// Show which playbooks, task files and roles include which others
+    Graph {
+        /// Path to playbook or directory
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+
+        /// Path to configuration file
+        #[arg(short = 'c', long, value_name = "FILE")]
+        config: Option<PathBuf>,
+    },
+
     /// Generate Software Bill of Materials (SBOM)
     Sbom {
         /// Path to playbook or directory
@@ -113,22 +155,64 @@ enum Commands {
         #[command(subcommand)]
         command: RulesCommands,
     },
+
+    /// Inspect the effective configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+
+    /// Print the JSON Schema of a config or rules file format
+    Schema {
+        /// File format
+        #[arg(value_enum)]
+        kind: SchemaKind,
+
+        /// Output file path
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Print the effective configuration and where each value came from
+    Show {
+        /// Path whose project config is used
+        #[arg(value_name = "PATH", default_value = ".")]
+        path: PathBuf,
+
+        /// Path to configuration file
+        #[arg(short = 'c', long, value_name = "FILE")]
+        config: Option<PathBuf>,
+    },
 }
 
 #[derive(Subcommand)]
 enum RulesCommands {
-    /// Validate rules file syntax and structure
+    /// Check a policy or secrets rules file and report every problem
     Validate {
         /// Path to rules file
         #[arg(value_name = "FILE")]
         rules_file: PathBuf,
     },
 
-    /// List all available rules
-    List {
-        /// Path to rules file
+    /// Run each rule against the `examples` in its definition
+    Test {
+        /// Path to policy or secrets rules file
         #[arg(value_name = "FILE")]
+        rules_file: PathBuf,
+    },
+
+    /// List all available rules with the configured overrides applied
+    List {
+        /// Path to a policy rules file to list as written
+        #[arg(value_name = "FILE", conflicts_with = "profile")]
         rules_file: Option<PathBuf>,
+
+        /// Show the rules as resolved for this profile
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
     },
 }
 
@@ -145,24 +229,81 @@ impl Cli {
                 no_cache,
                 ci_mode,
                 fail_on_findings,
+                require_suppression_reason,
+                profile,
+                baseline,
+                baseline_create,
+                list_files,
                 threads,
                 verbose: _verbose,
             } => {
                 info!("Starting security scan on: {:?}", path);
 
-                let mut config = Config::load(config_file.clone())?;
+                let mut loader = ConfigLoader::new()
+                    .config_file(config_file.clone())
+                    .search_from(path);
 
-                // Override with command-line options if provided
+                // Command-line options override every config layer
                 if let Some(ref secrets_file) = secrets_rules {
-                    config.secrets.rules_file = Some(secrets_file.to_string_lossy().to_string());
+                    loader = loader.set(
+                        "secrets.rules_file",
+                        secrets_file.to_string_lossy().as_ref(),
+                    );
                 }
                 if let Some(ref policy_file) = policy_rules {
-                    config.policies.rules_file = Some(policy_file.to_string_lossy().to_string());
+                    loader = loader.set(
+                        "policies.rules_file",
+                        policy_file.to_string_lossy().as_ref(),
+                    );
+                }
+                if *require_suppression_reason {
+                    loader = loader.set("general.require_suppression_reason", true);
+                }
+                if *no_cache {
+                    loader = loader.set("general.cache_enabled", false);
+                }
+                if let Some(ref profile) = profile {
+                    loader = loader.set("general.profile", profile.as_str());
+                }
+                let resolved = loader.resolve()?;
+
+                let enable_cache = resolved.config.general.cache_enabled;
+                let scanner = Scanner::new(resolved, *threads, enable_cache)?;
+
+                if *list_files {
+                    for file in scanner.list_files(path)? {
+                        match file.skipped {
+                            None => match file.kind {
+                                Some(kind) if kind != FileKind::Unknown => {
+                                    println!("scan  {}  [{}]", file.path.display(), kind.as_str())
+                                }
+                                _ => println!("scan  {}", file.path.display()),
+                            },
+                            Some(reason) => {
+                                println!("skip  {}  ({})", file.path.display(), reason)
+                            }
+                        }
+                    }
+                    return Ok(());
                 }
 
-                let enable_cache = !no_cache && config.general.cache_enabled;
-                let scanner = Scanner::new(config, *threads, enable_cache);
-                let findings = scanner.scan(path)?;
+                let mut findings = scanner.scan(path)?;
+
+                if let Some(ref baseline_file) = baseline_create {
+                    let dir = baseline_file.parent().unwrap_or(Path::new("."));
+                    let created = Baseline::create(&findings, dir);
+                    created.save(baseline_file)?;
+                    eprintln!(
+                        "📌 Baseline with {} finding{} written to {}",
+                        created.findings.len(),
+                        if created.findings.len() == 1 { "" } else { "s" },
+                        baseline_file.display()
+                    );
+                    created.apply(&mut findings, dir);
+                } else if let Some(ref baseline_file) = baseline {
+                    let dir = baseline_file.parent().unwrap_or(Path::new("."));
+                    Baseline::load(baseline_file)?.apply(&mut findings, dir);
+                }
 
                 let output_format = OutputFormat::from_str(format)?;
                 let reporter = Reporter::new(output_format);
@@ -198,8 +339,11 @@ impl Cli {
             } => {
                 info!("Starting linting on: {:?}", path);
 
-                let config = Config::load(rules.clone())?;
-                let scanner = Scanner::new(config, 0, false);
+                let resolved = ConfigLoader::new()
+                    .config_file(rules.clone())
+                    .search_from(path)
+                    .resolve()?;
+                let scanner = Scanner::new(resolved, 0, false)?;
                 let findings = scanner.lint(path)?;
 
                 let output_format = OutputFormat::from_str(format)?;
@@ -214,6 +358,20 @@ impl Cli {
                 Ok(())
             }
 
+            Commands::Graph {
+                path,
+                config: config_file,
+            } => {
+                let resolved = ConfigLoader::new()
+                    .config_file(config_file.clone())
+                    .search_from(path)
+                    .resolve()?;
+                let scanner = Scanner::new(resolved, 0, false)?;
+                scanner.graph(path)?.print();
+
+                Ok(())
+            }
+
             Commands::Sbom {
                 path,
                 output,
@@ -239,24 +397,118 @@ impl Cli {
                 RulesCommands::Validate { rules_file } => {
                     info!("Validating rules file: {:?}", rules_file);
 
-                    let rules_engine = RulesEngine::load(rules_file)?;
-                    rules_engine.validate()?;
+                    let diagnostics = validation::validate_rules_file(rules_file)?;
+                    if diagnostics.is_empty() {
+                        println!("✓ Rules file is valid");
+                        return Ok(());
+                    }
+
+                    for diagnostic in &diagnostics {
+                        println!("{}:{}", rules_file.display(), diagnostic);
+                    }
+                    println!(
+                        "\n✗ {} problem(s) in {}",
+                        diagnostics.len(),
+                        rules_file.display()
+                    );
+                    std::process::exit(1);
+                }
+
+                RulesCommands::Test { rules_file } => {
+                    info!("Testing rules file: {:?}", rules_file);
+
+                    let results = selftest::test_rules_file(rules_file)?;
+                    if results.is_empty() {
+                        println!("No rules with examples in {}", rules_file.display());
+                        return Ok(());
+                    }
+
+                    for result in &results {
+                        if result.passed() {
+                            println!("✓ {} ({} examples)", result.rule_id, result.examples);
+                        } else {
+                            println!("✗ {}", result.rule_id);
+                            for failure in &result.failures {
+                                println!("    {}", failure);
+                            }
+                        }
+                    }
 
-                    println!("✓ Rules file is valid");
+                    let failed = results.iter().filter(|r| !r.passed()).count();
+                    println!(
+                        "\n{} rules tested, {} passed, {} failed",
+                        results.len(),
+                        results.len() - failed,
+                        failed
+                    );
+
+                    if failed > 0 {
+                        std::process::exit(1);
+                    }
                     Ok(())
                 }
 
-                RulesCommands::List { rules_file } => {
-                    let rules_engine = if let Some(file) = rules_file {
-                        RulesEngine::load(file)?
-                    } else {
-                        RulesEngine::default()
-                    };
+                RulesCommands::List {
+                    rules_file,
+                    profile,
+                } => {
+                    if let Some(file) = rules_file {
+                        RulesEngine::load(file)?.list_rules();
+                        return Ok(());
+                    }
 
-                    rules_engine.list_rules();
+                    let mut loader = ConfigLoader::new().search_from(Path::new("."));
+                    if let Some(ref profile) = profile {
+                        loader = loader.set("general.profile", profile.as_str());
+                    }
+                    let config = loader.load()?;
+                    let chain = profiles::chain(&config.general.profile, &config.profiles)
+                        .map_err(AnsibleSecError::ConfigError)?;
+                    let names: Vec<&str> = chain.iter().map(|(name, _)| name.as_str()).collect();
+                    println!("Profile: {}\n", names.join(" extends "));
+
+                    RulesEngine::from_config(&config)?.list_rules();
+                    rules::print_rules("Secrets Rules:", &SecretDetector::listings(&config)?);
+                    rules::print_rules(
+                        "Reference Rules:",
+                        &ReferenceChecker::new(&config)?.listings(),
+                    );
+                    rules::print_rules("Role Rules:", &RoleChecker::new(&config)?.listings());
+                    rules::print_rules("Lint Rules:", &Linter::new(config)?.listings());
                     Ok(())
                 }
             },
+
+            Commands::Config { command } => match command {
+                ConfigCommands::Show {
+                    path,
+                    config: config_file,
+                } => {
+                    let resolved = ConfigLoader::new()
+                        .config_file(config_file.clone())
+                        .search_from(path)
+                        .resolve()?;
+
+                    print!("{}", resolved.annotated()?);
+                    for warning in resolved.warnings() {
+                        eprintln!("Warning: {}", warning);
+                    }
+                    Ok(())
+                }
+            },
+
+            Commands::Schema { kind, output } => {
+                let schema = schema::render(*kind)?;
+
+                if let Some(output_path) = output {
+                    std::fs::write(output_path, schema)?;
+                    println!("Schema written to: {:?}", output_path);
+                } else {
+                    println!("{}", schema);
+                }
+
+                Ok(())
+            }
         }
     }
 }
@@ -1,114 +1,711 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use crate::errors::Result;
+use crate::errors::{AnsibleSecError, Result};
+use crate::overrides::{RuleOverride, RuleOverrides};
+use crate::profiles::{self, Profile, DEFAULT_PROFILE};
+use crate::secrets::DEFAULT_MIN_ENTROPY_LENGTH;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Prefix of environment variables that override config keys, e.g.
+/// `ANSIBLESEC_SECRETS__ENTROPY_THRESHOLD=5.0`.
+pub const ENV_PREFIX: &str = "ANSIBLESEC";
+
+/// Project config file, searched for from the scan path upwards.
+pub const PROJECT_CONFIG: &str = ".ansiblesec.yml";
+
+/// Policy rules file used when none is configured.
+pub const DEFAULT_POLICY_RULES: &str = "rules/policies.yml";
+
+/// Secrets rules file used when none is configured.
+pub const DEFAULT_SECRETS_RULES: &str = "rules/secrets.yml";
+
+/// The `.ansiblesec.yml` configuration. Every section and key is optional;
+/// unknown keys are rejected.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
     pub secrets: SecretsConfig,
     pub policies: PoliciesConfig,
     pub linting: LintingConfig,
     pub general: GeneralConfig,
+    /// Severity, `enabled` and parameter overrides keyed by rule ID or glob
+    #[serde(skip_serializing_if = "RuleOverrides::is_empty")]
+    #[schemars(with = "BTreeMap<String, RuleOverride>")]
+    pub rules: RuleOverrides,
+    /// User-defined profiles by name; one named like a built-in replaces it
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
 pub struct SecretsConfig {
     pub enabled: bool,
+    /// Shannon entropy above which a string is reported as a possible secret
     pub entropy_threshold: f64,
+    /// Secrets rules file
     pub rules_file: Option<String>,
+    /// Shortest string checked for entropy
     pub min_entropy_length: usize,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
 pub struct PoliciesConfig {
     pub enabled: bool,
+    /// Policy rules file
     pub rules_file: Option<String>,
     pub disallow_modules: Vec<String>,
     pub require_vault: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
 pub struct LintingConfig {
     pub enabled: bool,
     pub max_line_length: usize,
+    /// Require every task to have a name
     pub require_name: bool,
     pub check_permissions: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
 pub struct GeneralConfig {
+    /// Deepest directory level scanned
     pub max_depth: usize,
+    /// Files larger than this many bytes are skipped
     pub max_file_size: usize,
+    /// Worker threads; 0 picks one per CPU
     pub parallel_jobs: usize,
     pub cache_enabled: bool,
     pub cache_dir: Option<PathBuf>,
+    /// Directory names skipped during discovery
     pub exclude_paths: Vec<String>,
+    /// `.gitignore`-style patterns of paths skipped during discovery
     pub exclude_patterns: Vec<String>,
+    /// Report inline suppressions that do not give a reason
+    pub require_suppression_reason: bool,
+    /// Rule profile: `minimal`, `default`, `strict`, `production` or one
+    /// defined under `profiles:`
+    pub profile: String,
+}
+
+impl Default for SecretsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            entropy_threshold: 4.5,
+            rules_file: Some(DEFAULT_SECRETS_RULES.to_string()),
+            min_entropy_length: DEFAULT_MIN_ENTROPY_LENGTH,
+        }
+    }
+}
+
+impl Default for PoliciesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rules_file: Some(DEFAULT_POLICY_RULES.to_string()),
+            disallow_modules: vec![
+                "shell".to_string(),
+                "command".to_string(),
+                "raw".to_string(),
+            ],
+            require_vault: true,
+        }
+    }
 }
 
-impl Default for Config {
+impl Default for LintingConfig {
     fn default() -> Self {
         Self {
-            secrets: SecretsConfig {
-                enabled: true,
-                entropy_threshold: 4.5,
-                rules_file: Some("rules/secrets.yml".to_string()),
-                min_entropy_length: 20,
-            },
-            policies: PoliciesConfig {
-                enabled: true,
-                rules_file: Some("rules/policies.yml".to_string()),
-                disallow_modules: vec![
-                    "shell".to_string(),
-                    "command".to_string(),
-                    "raw".to_string(),
-                ],
-                require_vault: true,
-            },
-            linting: LintingConfig {
-                enabled: true,
-                max_line_length: 120,
-                require_name: true,
-                check_permissions: true,
-            },
-            general: GeneralConfig {
-                max_depth: 10,
-                max_file_size: 10 * 1024 * 1024, // 10MB
-                parallel_jobs: 0,                // 0 = auto
-                cache_enabled: true,
-                cache_dir: Some(PathBuf::from(".ansiblesec_cache")),
-                exclude_paths: vec![
-                    ".git".to_string(),
-                    "venv".to_string(),
-                    "node_modules".to_string(),
-                    "vendor".to_string(),
-                ],
-                exclude_patterns: vec!["*.retry".to_string(), "*.swp".to_string()],
-            },
+            enabled: true,
+            max_line_length: 120,
+            require_name: true,
+            check_permissions: true,
+        }
+    }
+}
+
+impl Default for GeneralConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: 10,
+            max_file_size: 10 * 1024 * 1024, // 10MB
+            parallel_jobs: 0,                // 0 = auto
+            cache_enabled: true,
+            cache_dir: Some(PathBuf::from(".ansiblesec_cache")),
+            exclude_paths: vec![
+                ".git".to_string(),
+                "venv".to_string(),
+                "node_modules".to_string(),
+                "vendor".to_string(),
+            ],
+            exclude_patterns: vec!["*.retry".to_string(), "*.swp".to_string()],
+            require_suppression_reason: false,
+            profile: DEFAULT_PROFILE.to_string(),
         }
     }
 }
 
 impl Config {
-    pub fn load(config_file: Option<PathBuf>) -> Result<Self> {
-        let mut config = Self::default();
+    /// Parse a config file. Errors name the file and the line and column of
+    /// the offending key.
+    pub fn parse(path: &Path, content: &str) -> Result<Self> {
+        serde_yaml::from_str(content)
+            .map_err(|e| AnsibleSecError::ConfigError(format!("{}: {}", path.display(), e)))
+    }
+
+    /// The rule overrides in effect: those of `general.profile` and the
+    /// profiles it extends, then the `rules:` section.
+    pub fn effective_rules(&self) -> Result<RuleOverrides> {
+        let mut rules = profiles::resolve(&self.general.profile, &self.profiles)
+            .map_err(|e| AnsibleSecError::ConfigError(format!("general.profile: {}", e)))?;
+        rules.extend(self.rules.clone());
+        Ok(rules)
+    }
+}
+
+/// Where a configuration value came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    Default,
+    File(PathBuf),
+    /// An `ANSIBLESEC_*` environment variable
+    Env(String),
+    CommandLine,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::File(path) => write!(f, "{}", path.display()),
+            ConfigSource::Env(name) => write!(f, "env {}", name),
+            ConfigSource::CommandLine => write!(f, "command line"),
+        }
+    }
+}
+
+/// The effective configuration and where each of its values came from.
+#[derive(Debug, Clone)]
+pub struct ResolvedConfig {
+    pub config: Config,
+    /// Keyed by `section.key`, in declaration order
+    pub sources: Vec<(String, ConfigSource)>,
+    /// Config files applied, outermost first
+    pub files: Vec<PathBuf>,
+    /// `ANSIBLESEC_*` environment variables that name no config key
+    pub ignored_env: Vec<String>,
+}
+
+impl From<Config> for ResolvedConfig {
+    fn from(config: Config) -> Self {
+        let keys = serde_yaml::to_value(&config)
+            .map(|values| leaf_keys(&values))
+            .unwrap_or_default();
+        Self {
+            config,
+            sources: keys
+                .into_iter()
+                .filter(|key| !key.starts_with("rules.") && !key.starts_with("profiles."))
+                .map(|key| (key, ConfigSource::Default))
+                .collect(),
+            files: Vec::new(),
+            ignored_env: Vec::new(),
+        }
+    }
+}
+
+impl ResolvedConfig {
+    /// This configuration with a nested config file merged onto it, key by
+    /// key. Keys set by the environment or the command line are kept.
+    pub fn merge_file(&self, path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| AnsibleSecError::ConfigError(format!("{}: {}", path.display(), e)))?;
+        let values: serde_yaml::Value = serde_yaml::from_str(&content)
+            .map_err(|e| AnsibleSecError::ConfigError(format!("{}: {}", path.display(), e)))?;
+        if values.is_null() {
+            return Ok(self.clone());
+        }
+        let parsed = Config::parse(path, &content)?;
+
+        let mut merged = serde_yaml::to_value(&self.config)?;
+        let mut sources = self.sources.clone();
+        for key in leaf_keys(&values) {
+            if key.starts_with("rules.") || key.starts_with("profiles.") {
+                continue;
+            }
+            if matches!(
+                self.source(&key),
+                Some(ConfigSource::Env(_) | ConfigSource::CommandLine)
+            ) {
+                continue;
+            }
+            let (Some(value), Some(slot)) =
+                (yaml_get(&values, &key), yaml_get_mut(&mut merged, &key))
+            else {
+                continue;
+            };
+            *slot = value.clone();
+            if let Some((_, source)) = sources.iter_mut().find(|(k, _)| *k == key) {
+                *source = ConfigSource::File(path.to_path_buf());
+            }
+        }
+
+        let mut config: Config = serde_yaml::from_value(merged)
+            .map_err(|e| AnsibleSecError::ConfigError(format!("{}: {}", path.display(), e)))?;
+        set_rule_sources(&mut sources, &parsed, path);
+        config.rules.extend(parsed.rules);
+        config.profiles.extend(parsed.profiles);
+        config.effective_rules()?;
+
+        let mut files = self.files.clone();
+        files.push(path.to_path_buf());
+        Ok(Self {
+            config,
+            sources,
+            files,
+            ignored_env: self.ignored_env.clone(),
+        })
+    }
+
+    pub fn source(&self, key: &str) -> Option<&ConfigSource> {
+        self.sources
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, source)| source)
+    }
+
+    /// Directory of the config file that set `key`; `None` when the key
+    /// comes from the defaults, the environment or the command line.
+    pub fn file_dir(&self, key: &str) -> Option<&Path> {
+        match self.source(key)? {
+            ConfigSource::File(path) => path.parent(),
+            _ => None,
+        }
+    }
+
+    /// Problems with the configuration that do not stop a scan: ignored
+    /// environment variables and keys that have no effect.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings: Vec<String> = self
+            .ignored_env
+            .iter()
+            .map(|name| {
+                let key = name[ENV_PREFIX.len() + 1..]
+                    .to_lowercase()
+                    .replace("__", ".");
+                format!(
+                    "{} ignored: no config key `{}`; use {}_<SECTION>__<KEY>",
+                    name, key, ENV_PREFIX
+                )
+            })
+            .collect();
+        warnings.extend(self.ineffective_keys());
+        warnings
+    }
+
+    /// Keys set away from their defaults that have no effect because of
+    /// other settings, e.g. secrets options while secrets are disabled.
+    pub fn ineffective_keys(&self) -> Vec<String> {
+        let config = &self.config;
+        let mut disabled = Vec::new();
+        for (section, enabled) in [
+            ("secrets", config.secrets.enabled),
+            ("policies", config.policies.enabled),
+            ("linting", config.linting.enabled),
+        ] {
+            if !enabled {
+                disabled.push((
+                    format!("{}.", section),
+                    format!("{}.enabled is false", section),
+                ));
+            }
+        }
+        // `--no-cache` should not warn about a project's cache directory
+        if !config.general.cache_enabled
+            && self.source("general.cache_enabled") != Some(&ConfigSource::CommandLine)
+        {
+            disabled.push((
+                "general.cache_dir".to_string(),
+                "general.cache_enabled is false".to_string(),
+            ));
+        }
+
+        let mut warnings = Vec::new();
+        for (key, source) in &self.sources {
+            if *source == ConfigSource::Default || key.ends_with(".enabled") {
+                continue;
+            }
+            if let Some((_, reason)) = disabled.iter().find(|(prefix, _)| key.starts_with(prefix)) {
+                warnings.push(format!(
+                    "{} (from {}) has no effect: {}",
+                    key, source, reason
+                ));
+            }
+        }
+        warnings
+    }
+
+    /// The configuration as YAML, each value commented with its source.
+    pub fn annotated(&self) -> Result<String> {
+        let values = serde_yaml::to_value(&self.config)?;
+        let mut lines = Vec::new();
+        for (section, keys) in values.as_mapping().into_iter().flatten() {
+            let section = section.as_str().unwrap_or_default();
+            lines.push((format!("{}:", section), None));
+            for (key, value) in keys.as_mapping().into_iter().flatten() {
+                let key = key.as_str().unwrap_or_default();
+                let source = self.source(&format!("{}.{}", section, key));
+                lines.push((format!("  {}: {}", key, flow_yaml(value)?), source));
+            }
+        }
+
+        let width = lines.iter().map(|(line, _)| line.len()).max().unwrap_or(0);
+        let mut out = String::new();
+        for (line, source) in lines {
+            match source {
+                Some(source) => out.push_str(&format!("{:width$}  # {}\n", line, source)),
+                None => out.push_str(&format!("{}\n", line)),
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// A value on one line; JSON scalars and flow sequences are valid YAML.
+fn flow_yaml(value: &serde_yaml::Value) -> Result<String> {
+    match value {
+        serde_yaml::Value::Sequence(items) => {
+            let items: Vec<String> = items.iter().map(flow_yaml).collect::<Result<_>>()?;
+            Ok(format!("[{}]", items.join(", ")))
+        }
+        other => Ok(serde_json::to_string(other)?),
+    }
+}
+
+/// Resolves the configuration from its layers, later layers overriding
+/// earlier ones key by key: built-in defaults, the user config
+/// (`$XDG_CONFIG_HOME/ansiblesec/config.yml`), the project config, `ANSIBLESEC_*`
+/// environment variables and command-line flags.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigLoader {
+    user_file: Option<PathBuf>,
+    project_file: Option<PathBuf>,
+    /// Set when the project file was given explicitly and must exist
+    explicit: bool,
+    /// Variables `ANSIBLESEC_*` settings are read from
+    env: HashMap<String, String>,
+    overrides: Vec<(String, ::config::ValueKind)>,
+}
 
-        // Try to load from specified config file or default
-        if let Some(ref path) = config_file {
-            let content = fs::read_to_string(path)?;
-            config = serde_yaml::from_str(&content)?;
-        } else if let Ok(content) = fs::read_to_string(".ansiblesec.yml") {
-            config = serde_yaml::from_str(&content)?;
+impl ConfigLoader {
+    /// A loader reading the user config and the process environment.
+    pub fn new() -> Self {
+        Self::default()
+            .user_file(dirs::config_dir().map(|dir| dir.join("ansiblesec").join("config.yml")))
+            .env(std::env::vars().collect())
+    }
+
+    /// Use `path` as the project config instead of searching for one.
+    pub fn config_file(mut self, path: Option<PathBuf>) -> Self {
+        if let Some(path) = path {
+            self.project_file = Some(path);
+            self.explicit = true;
+        }
+        self
+    }
+
+    /// Use the nearest `.ansiblesec.yml` in `path` or one of its parents,
+    /// unless a config file was given.
+    pub fn search_from(mut self, path: &Path) -> Self {
+        if !self.explicit {
+            self.project_file = find_project_config(path);
         }
+        self
+    }
+
+    /// Use `path` as the user config; `None` reads no user config.
+    pub fn user_file(mut self, path: Option<PathBuf>) -> Self {
+        self.user_file = path;
+        self
+    }
 
-        Ok(config)
+    /// Read `ANSIBLESEC_*` variables from `env` instead of the process environment.
+    pub fn env(mut self, env: HashMap<String, String>) -> Self {
+        self.env = env;
+        self
     }
 
-    #[allow(dead_code)]
-    pub fn save(&self, path: &Path) -> Result<()> {
-        let yaml = serde_yaml::to_string(self)?;
-        fs::write(path, yaml)?;
-        Ok(())
+    /// Override `key` (`section.key`) from the command line.
+    pub fn set(mut self, key: &str, value: impl Into<::config::ValueKind>) -> Self {
+        self.overrides.push((key.to_string(), value.into()));
+        self
     }
+
+    /// The resolved configuration, warning about keys that have no effect.
+    pub fn load(&self) -> Result<Config> {
+        let resolved = self.resolve()?;
+        for warning in resolved.warnings() {
+            eprintln!("Warning: {}", warning);
+        }
+        Ok(resolved.config)
+    }
+
+    pub fn resolve(&self) -> Result<ResolvedConfig> {
+        let defaults = serde_yaml::to_value(Config::default())?;
+        let keys = leaf_keys(&defaults);
+
+        let mut builder = ::config::Config::builder().add_source(Layer::from_yaml(None, &defaults));
+        let mut rules = RuleOverrides::default();
+        let mut profiles = BTreeMap::new();
+        let mut rule_sources = Vec::new();
+
+        let user_file = self.user_file.as_ref().filter(|path| path.is_file());
+        let project_file = self
+            .project_file
+            .as_ref()
+            .filter(|path| self.explicit || path.is_file());
+        for path in user_file.into_iter().chain(project_file) {
+            let content = fs::read_to_string(path)
+                .map_err(|e| AnsibleSecError::ConfigError(format!("{}: {}", path.display(), e)))?;
+            let values: serde_yaml::Value = serde_yaml::from_str(&content)
+                .map_err(|e| AnsibleSecError::ConfigError(format!("{}: {}", path.display(), e)))?;
+            if values.is_null() {
+                continue;
+            }
+            // Parsed on its own first so unknown keys are reported with their position
+            let parsed = Config::parse(path, &content)?;
+            set_rule_sources(&mut rule_sources, &parsed, path);
+            rules.extend(parsed.rules);
+            profiles.extend(parsed.profiles);
+            // Rule overrides are keyed by globs, which are not config key
+            // paths; profiles are replaced whole rather than key by key
+            let mut values = values;
+            if let Some(mapping) = values.as_mapping_mut() {
+                mapping.remove("rules");
+                mapping.remove("profiles");
+            }
+            let origin = path.to_string_lossy().to_string();
+            builder = builder.add_source(Layer::from_yaml(Some(origin), &values));
+        }
+
+        let mut env = self.env.clone();
+        // Other tools may share the prefix, so unknown variables are skipped
+        let env_prefix = format!("{}_", ENV_PREFIX);
+        let mut ignored_env = Vec::new();
+        env.retain(|name, _| {
+            let Some(key) = name.strip_prefix(&env_prefix) else {
+                return true;
+            };
+            if keys.contains(&key.to_lowercase().replace("__", ".")) {
+                return true;
+            }
+            ignored_env.push(name.clone());
+            false
+        });
+        ignored_env.sort();
+        let mut environment = ::config::Environment::with_prefix(ENV_PREFIX)
+            .prefix_separator("_")
+            .separator("__")
+            .try_parsing(true)
+            .list_separator(",")
+            .source(Some(env.into_iter().collect()));
+        for key in keys
+            .iter()
+            .filter(|key| yaml_get(&defaults, key).is_some_and(|v| v.is_sequence()))
+        {
+            environment = environment.with_list_parse_key(key);
+        }
+        builder = builder.add_source(environment);
+
+        let mut command_line = Layer {
+            values: ::config::Map::new(),
+        };
+        for (key, value) in &self.overrides {
+            command_line.values.insert(
+                key.clone(),
+                ::config::Value::new(Some(&COMMAND_LINE.to_string()), value.clone()),
+            );
+        }
+        builder = builder.add_source(command_line);
+
+        let files = user_file.into_iter().chain(project_file).cloned().collect();
+        let built = builder.build().map_err(config_error)?;
+        let mut sources: Vec<(String, ConfigSource)> = keys
+            .iter()
+            .map(|key| {
+                let origin = config_value(&built.cache, key).and_then(|value| value.origin());
+                let source = match origin {
+                    None => ConfigSource::Default,
+                    Some(COMMAND_LINE) => ConfigSource::CommandLine,
+                    Some("the environment") => ConfigSource::Env(env_var(key)),
+                    Some(path) => ConfigSource::File(PathBuf::from(path)),
+                };
+                (key.clone(), source)
+            })
+            .collect();
+        sources.extend(rule_sources);
+        let mut config: Config = built.try_deserialize().map_err(config_error)?;
+        config.rules = rules;
+        config.profiles = profiles;
+        config.effective_rules()?;
+
+        Ok(ResolvedConfig {
+            config,
+            sources,
+            files,
+            ignored_env,
+        })
+    }
+}
+
+/// Record `path` as the source of each `rules.<pattern>` entry and
+/// `profiles.<name>` profile its config sets.
+fn set_rule_sources(sources: &mut Vec<(String, ConfigSource)>, config: &Config, path: &Path) {
+    let rules = config
+        .rules
+        .patterns()
+        .map(|pattern| format!("rules.{}", pattern));
+    let profiles = config
+        .profiles
+        .keys()
+        .map(|name| format!("profiles.{}", name));
+    for key in rules.chain(profiles) {
+        sources.retain(|(k, _)| *k != key);
+        sources.push((key, ConfigSource::File(path.to_path_buf())));
+    }
+}
+
+/// The nearest project config in `path` or one of its parents.
+pub fn find_project_config(path: &Path) -> Option<PathBuf> {
+    let path = path.canonicalize().ok()?;
+    let start = if path.is_file() {
+        path.parent()?
+    } else {
+        &path
+    };
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG))
+        .find(|candidate| candidate.is_file())
+}
+
+/// The environment variable that sets `key`, e.g.
+/// `ANSIBLESEC_SECRETS__ENTROPY_THRESHOLD` for `secrets.entropy_threshold`.
+pub fn env_var(key: &str) -> String {
+    format!("{}_{}", ENV_PREFIX, key.replace('.', "__").to_uppercase())
+}
+
+const COMMAND_LINE: &str = "command line";
+
+/// Configuration values from one layer, keyed by dotted path so that a
+/// layer only replaces the keys it sets.
+#[derive(Debug, Clone)]
+struct Layer {
+    values: ::config::Map<String, ::config::Value>,
+}
+
+impl Layer {
+    fn from_yaml(origin: Option<String>, values: &serde_yaml::Value) -> Self {
+        let values = leaf_keys(values)
+            .into_iter()
+            .filter_map(|key| {
+                let value = yaml_get(values, &key)?;
+                Some((key, to_config_value(origin.as_ref(), value)))
+            })
+            .collect();
+        Self { values }
+    }
+}
+
+impl ::config::Source for Layer {
+    fn clone_into_box(&self) -> Box<dyn ::config::Source + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn collect(
+        &self,
+    ) -> std::result::Result<::config::Map<String, ::config::Value>, ::config::ConfigError> {
+        Ok(self.values.clone())
+    }
+}
+
+/// Dotted paths of the non-mapping values in `value`.
+fn leaf_keys(value: &serde_yaml::Value) -> Vec<String> {
+    let mut keys = Vec::new();
+    for (key, child) in value.as_mapping().into_iter().flatten() {
+        let Some(key) = key.as_str() else { continue };
+        if child.is_mapping() {
+            keys.extend(
+                leaf_keys(child)
+                    .into_iter()
+                    .map(|child_key| format!("{}.{}", key, child_key)),
+            );
+        } else {
+            keys.push(key.to_string());
+        }
+    }
+    keys
+}
+
+fn yaml_get<'a>(value: &'a serde_yaml::Value, key: &str) -> Option<&'a serde_yaml::Value> {
+    key.split('.')
+        .try_fold(value, |value, part| value.get(part))
+}
+
+fn yaml_get_mut<'a>(
+    value: &'a mut serde_yaml::Value,
+    key: &str,
+) -> Option<&'a mut serde_yaml::Value> {
+    key.split('.')
+        .try_fold(value, |value, part| value.get_mut(part))
+}
+
+fn config_value<'a>(value: &'a ::config::Value, key: &str) -> Option<&'a ::config::Value> {
+    key.split('.')
+        .try_fold(value, |value, part| match value.kind {
+            ::config::ValueKind::Table(ref table) => table.get(part),
+            _ => None,
+        })
+}
+
+fn to_config_value(origin: Option<&String>, value: &serde_yaml::Value) -> ::config::Value {
+    use ::config::ValueKind;
+    use serde_yaml::Value;
+
+    let kind = match value {
+        Value::Null => ValueKind::Nil,
+        Value::Bool(b) => ValueKind::Boolean(*b),
+        Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(i), _) => ValueKind::I64(i),
+            (None, Some(u)) => ValueKind::U64(u),
+            _ => ValueKind::Float(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => ValueKind::String(s.clone()),
+        Value::Sequence(items) => ValueKind::Array(
+            items
+                .iter()
+                .map(|item| to_config_value(origin, item))
+                .collect(),
+        ),
+        Value::Mapping(mapping) => ValueKind::Table(
+            mapping
+                .iter()
+                .filter_map(|(k, v)| Some((k.as_str()?.to_string(), to_config_value(origin, v))))
+                .collect(),
+        ),
+        Value::Tagged(tagged) => return to_config_value(origin, &tagged.value),
+    };
+    ::config::Value::new(origin, kind)
+}
+
+fn config_error(error: ::config::ConfigError) -> AnsibleSecError {
+    AnsibleSecError::ConfigError(error.to_string())
 }
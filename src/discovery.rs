@@ -0,0 +1,324 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use lazy_static::lazy_static;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::config::GeneralConfig;
+use crate::errors::{AnsibleSecError, Result};
+use crate::model::FileKind;
+use crate::pattern::Globs;
+
+/// Ignore file read from every scanned directory, after `.gitignore`.
+const ANSIBLESEC_IGNORE: &str = ".ansiblesecignore";
+
+/// Ignore files read from every scanned directory, lowest precedence first.
+pub const IGNORE_FILES: [&str; 2] = [".gitignore", ANSIBLESEC_IGNORE];
+
+/// Directories whose YAML is CI or tooling configuration, not Ansible.
+const NON_ANSIBLE_DIRS: &[&str] = &[".github", ".circleci", ".gitea", ".woodpecker"];
+
+/// YAML file names of other tools, skipped unless named on the command line.
+const NON_ANSIBLE_FILES: &[&str] = &[
+    ".gitlab-ci.yml",
+    ".travis.yml",
+    ".pre-commit-config.y*ml",
+    ".pre-commit-hooks.y*ml",
+    ".yamllint*",
+    ".readthedocs.y*ml",
+    "azure-pipelines.y*ml",
+    "bitbucket-pipelines.yml",
+    "codecov.y*ml",
+    "compose.y*ml",
+    "docker-compose*.y*ml",
+    "mkdocs.y*ml",
+];
+
+/// File names of INI or YAML inventories outside an `inventory/` directory.
+const INVENTORY_FILES: &[&str] = &["hosts", "hosts.ini", "inventory", "inventory.ini"];
+
+lazy_static! {
+    static ref NON_ANSIBLE: Globs = Globs::new(
+        &NON_ANSIBLE_FILES
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>()
+    )
+    .expect("valid non-Ansible globs");
+}
+
+/// The kind of a file from its place in an Ansible project: role
+/// directories, `group_vars/`, `host_vars/`, inventories, templates and
+/// `ansible.cfg`. Other YAML files are `Unknown` and classified by content
+/// once parsed. `None` if the file is not Ansible content.
+pub fn file_kind(path: &Path) -> Option<FileKind> {
+    let name = path.file_name()?.to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase());
+    let yaml = matches!(extension.as_deref(), Some("yml" | "yaml"));
+
+    if name == "ansible.cfg" {
+        return Some(FileKind::AnsibleConfig);
+    }
+    if extension.as_deref() == Some("j2") {
+        return Some(FileKind::Template);
+    }
+
+    // The innermost directory with a meaning in Ansible decides
+    let dirs = path
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .map(|dir| dir.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>();
+    for dir in dirs.iter().rev() {
+        match dir.as_ref() {
+            "templates" => return Some(FileKind::Template),
+            "files" => return Some(FileKind::RoleFile),
+            "inventory" | "inventories" => return Some(FileKind::Inventory),
+            // Variable files may also be JSON or have no extension
+            "group_vars" | "host_vars"
+                if yaml || extension.is_none() || name.ends_with(".json") =>
+            {
+                return Some(FileKind::VarsFile)
+            }
+            "tasks" | "handlers" if yaml => return Some(FileKind::TaskList),
+            "defaults" | "vars" if yaml => return Some(FileKind::VarsFile),
+            "meta" if yaml => return Some(FileKind::RoleMeta),
+            _ => {}
+        }
+    }
+
+    if INVENTORY_FILES.contains(&name.as_ref()) {
+        Some(FileKind::Inventory)
+    } else if yaml {
+        Some(FileKind::Unknown)
+    } else {
+        None
+    }
+}
+
+/// Whether a YAML file belongs to another tool, such as a GitHub workflow
+/// or a docker-compose file.
+pub fn is_other_tool(path: &Path) -> bool {
+    path.components()
+        .any(|dir| NON_ANSIBLE_DIRS.iter().any(|name| dir.as_os_str() == *name))
+        || NON_ANSIBLE.is_match(path)
+}
+
+/// Why discovery skipped a file or directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    /// Matched an ignore pattern
+    Ignored {
+        pattern: String,
+        /// The ignore file or config key the pattern came from
+        source: String,
+    },
+    NotAnsible,
+    /// YAML of another tool, e.g. a GitHub workflow
+    OtherTool,
+    /// A policy or secrets rules file of ansiblesec itself
+    RulesFile,
+    /// Larger than `general.max_file_size`
+    TooLarge(u64),
+    /// A nested `.ansiblesec.yml` or an ignore file
+    ConfigFile,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::Ignored { pattern, source } => {
+                write!(f, "ignored by '{}' in {}", pattern, source)
+            }
+            SkipReason::NotAnsible => write!(f, "not an Ansible file"),
+            SkipReason::OtherTool => write!(f, "configuration of another tool"),
+            SkipReason::RulesFile => write!(f, "ansiblesec rules file"),
+            SkipReason::TooLarge(size) => {
+                write!(f, "{} bytes is over general.max_file_size", size)
+            }
+            SkipReason::ConfigFile => write!(f, "config or ignore file"),
+        }
+    }
+}
+
+/// A file or directory discovery looked at.
+#[derive(Debug, Clone)]
+pub struct DiscoveredFile {
+    pub path: PathBuf,
+    /// Kind from the project layout; `Unknown` until the content is parsed
+    pub kind: Option<FileKind>,
+    /// `None` when the file is scanned
+    pub skipped: Option<SkipReason>,
+}
+
+/// Gitignore-style rules for one directory: the `exclude_paths` and
+/// `exclude_patterns` of the configuration, then the `.gitignore` and
+/// `.ansiblesecignore` files of the directory and its parents up to the
+/// scanned path. Later rules win, so `!pattern` in an ignore file can bring
+/// back a path the configuration excludes.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    layers: Vec<Layer>,
+}
+
+#[derive(Debug, Clone)]
+struct Layer {
+    matcher: Gitignore,
+    /// Config key of the patterns; `None` for ignore files
+    key: Option<&'static str>,
+}
+
+impl IgnoreRules {
+    /// These rules with the patterns of a configuration added, anchored at
+    /// `root`.
+    pub fn with_config(&self, root: &Path, general: &GeneralConfig) -> Result<Self> {
+        // `exclude_paths` are directory names
+        let exclude_paths: Vec<String> = general
+            .exclude_paths
+            .iter()
+            .map(|name| format!("{}/", name.trim_end_matches('/')))
+            .collect();
+
+        let mut rules = self.clone();
+        for (key, patterns) in [
+            ("general.exclude_paths", &exclude_paths),
+            ("general.exclude_patterns", &general.exclude_patterns),
+        ] {
+            let mut builder = GitignoreBuilder::new(root);
+            builder.allow_unclosed_class(false);
+            for pattern in patterns {
+                builder
+                    .add_line(None, pattern)
+                    .map_err(|e| AnsibleSecError::ConfigError(format!("{}: {}", key, e)))?;
+            }
+            let matcher = builder
+                .build()
+                .map_err(|e| AnsibleSecError::ConfigError(format!("{}: {}", key, e)))?;
+            rules.layers.push(Layer {
+                matcher,
+                key: Some(key),
+            });
+        }
+        Ok(rules)
+    }
+
+    /// These rules with the ignore files of `dir` added; `None` if it has
+    /// none. Lines that are not valid globs are skipped with a warning.
+    pub fn with_dir(&self, dir: &Path) -> Option<Self> {
+        let files: Vec<PathBuf> = IGNORE_FILES
+            .iter()
+            .map(|name| dir.join(name))
+            .filter(|file| file.is_file())
+            .collect();
+        if files.is_empty() {
+            return None;
+        }
+
+        let mut rules = self.clone();
+        for file in files {
+            let mut builder = GitignoreBuilder::new(dir);
+            if let Some(e) = builder.add(&file) {
+                eprintln!("Warning: {}: {}", file.display(), e);
+            }
+            match builder.build() {
+                Ok(matcher) => rules.layers.push(Layer { matcher, key: None }),
+                Err(e) => eprintln!("Warning: {}: {}", file.display(), e),
+            }
+        }
+        Some(rules)
+    }
+
+    /// Why `path` is ignored, if it is.
+    pub fn matched(&self, path: &Path, is_dir: bool) -> Option<SkipReason> {
+        for layer in self.layers.iter().rev() {
+            match layer.matcher.matched(path, is_dir) {
+                Match::None => continue,
+                Match::Whitelist(_) => return None,
+                Match::Ignore(glob) => {
+                    let source = match (layer.key, glob.from()) {
+                        (Some(key), _) => key.to_string(),
+                        (None, Some(file)) => file.display().to_string(),
+                        (None, None) => "an ignore file".to_string(),
+                    };
+                    return Some(SkipReason::Ignored {
+                        pattern: glob.original().to_string(),
+                        source,
+                    });
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_kind() {
+        let kind = |path: &str| file_kind(Path::new(path));
+        assert_eq!(kind("roles/web/tasks/main.yml"), Some(FileKind::TaskList));
+        assert_eq!(
+            kind("roles/web/handlers/main.yaml"),
+            Some(FileKind::TaskList)
+        );
+        assert_eq!(
+            kind("roles/web/defaults/main.yml"),
+            Some(FileKind::VarsFile)
+        );
+        assert_eq!(kind("roles/web/meta/main.yml"), Some(FileKind::RoleMeta));
+        assert_eq!(
+            kind("roles/web/templates/app.conf"),
+            Some(FileKind::Template)
+        );
+        assert_eq!(
+            kind("roles/web/files/tasks/id_rsa"),
+            Some(FileKind::RoleFile)
+        );
+        assert_eq!(kind("nginx.conf.j2"), Some(FileKind::Template));
+        assert_eq!(kind("group_vars/all"), Some(FileKind::VarsFile));
+        assert_eq!(kind("host_vars/web1/vault.yml"), Some(FileKind::VarsFile));
+        assert_eq!(kind("inventory/production"), Some(FileKind::Inventory));
+        assert_eq!(kind("hosts.ini"), Some(FileKind::Inventory));
+        assert_eq!(kind("ansible.cfg"), Some(FileKind::AnsibleConfig));
+        assert_eq!(kind("playbooks/site.yml"), Some(FileKind::Unknown));
+        assert_eq!(kind("README.md"), None);
+        assert_eq!(kind("roles/web/tasks/notes.txt"), None);
+
+        assert!(is_other_tool(Path::new(".github/workflows/ci.yml")));
+        assert!(is_other_tool(Path::new("deploy/docker-compose.prod.yml")));
+        assert!(!is_other_tool(Path::new("playbooks/compose_stack.yml")));
+    }
+
+    #[test]
+    fn test_config_patterns() {
+        let general = GeneralConfig {
+            exclude_paths: vec!["test".to_string()],
+            exclude_patterns: vec!["*.retry".to_string(), "!keep.retry".to_string()],
+            ..GeneralConfig::default()
+        };
+        let rules = IgnoreRules::default()
+            .with_config(Path::new("root"), &general)
+            .unwrap();
+
+        assert!(rules.matched(Path::new("root/test"), true).is_some());
+        assert!(rules.matched(Path::new("root/latest_role"), true).is_none());
+        assert!(rules.matched(Path::new("root/test.yml"), false).is_none());
+        assert!(rules
+            .matched(Path::new("root/a/site.retry"), false)
+            .is_some());
+        assert!(rules.matched(Path::new("root/keep.retry"), false).is_none());
+
+        let general = GeneralConfig {
+            exclude_patterns: vec!["[".to_string()],
+            ..GeneralConfig::default()
+        };
+        assert!(IgnoreRules::default()
+            .with_config(Path::new("root"), &general)
+            .is_err());
+    }
+}
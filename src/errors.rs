@@ -33,7 +33,6 @@ pub enum AnsibleSecError {
     #[error("File not found: {0}")]
     FileNotFound(String),
 
-    #[allow(dead_code)]
     #[error("Invalid rule format: {0}")]
     InvalidRuleFormat(String),
 
@@ -42,6 +41,20 @@ pub enum AnsibleSecError {
 
     #[error("Cache error: {0}")]
     CacheError(String),
+
+    #[error("Baseline error: {0}")]
+    BaselineError(String),
+}
+
+impl AnsibleSecError {
+    /// An invalid rule format error naming the rule it was found in.
+    pub fn in_rule(self, rule_id: &str) -> Self {
+        let message = match self {
+            AnsibleSecError::InvalidRuleFormat(message) => message,
+            other => other.to_string(),
+        };
+        AnsibleSecError::InvalidRuleFormat(format!("rule {}: {}", rule_id, message))
+    }
 }
 
 pub type Result<T> = std::result::Result<T, AnsibleSecError>;
@@ -0,0 +1,697 @@
+use rayon::prelude::*;
+use serde_yaml::{Mapping, Value};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::discovery;
+use crate::model::{as_ansible_bool, Document, FileKind, Role};
+use crate::modules;
+use crate::yaml::{self, PathSegment, Span};
+
+/// Directories that make up a role; a file under one belongs to the role
+/// above it.
+const ROLE_DIRS: &[&str] = &[
+    "tasks",
+    "handlers",
+    "defaults",
+    "vars",
+    "meta",
+    "templates",
+    "files",
+];
+
+/// How one file refers to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefKind {
+    ImportPlaybook,
+    IncludeTasks,
+    ImportTasks,
+    IncludeRole,
+    ImportRole,
+    /// An entry of a play's `roles:`
+    Role,
+    /// An entry of `dependencies:` in a role's `meta/main.yml`
+    RoleDependency,
+    /// The `src` of a `template` task
+    Template,
+    /// The `src` of a `copy` task
+    Copy,
+}
+
+impl RefKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            RefKind::ImportPlaybook => "import_playbook",
+            RefKind::IncludeTasks => "include_tasks",
+            RefKind::ImportTasks => "import_tasks",
+            RefKind::IncludeRole => "include_role",
+            RefKind::ImportRole => "import_role",
+            RefKind::Role => "roles",
+            RefKind::RoleDependency => "dependencies",
+            RefKind::Template => "template",
+            RefKind::Copy => "copy",
+        }
+    }
+
+    pub fn is_role(&self) -> bool {
+        matches!(
+            self,
+            RefKind::IncludeRole | RefKind::ImportRole | RefKind::Role | RefKind::RoleDependency
+        )
+    }
+}
+
+/// A playbook, task file, role, template or file named in a file.
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub kind: RefKind,
+    /// The file or role name as written
+    pub target: String,
+    /// Task file a role reference starts from instead of `main`
+    pub tasks_from: Option<String>,
+    /// Location of the target in the referring file
+    pub span: Span,
+    /// The file, directory or role found, or a role's `tasks_from` file;
+    /// `None` if the target does not exist or is templated
+    pub resolved: Option<PathBuf>,
+    /// Files the reference runs: the task file or playbook, or a role's
+    /// tasks, handlers and meta. Empty for templates and copied files.
+    pub files: Vec<PathBuf>,
+    /// Parameters given with a `roles:` or `dependencies:` entry
+    pub params: Mapping,
+    /// Index of the play a `roles:` entry belongs to, counting the plays
+    /// of every document in the file
+    pub play: Option<usize>,
+}
+
+impl Reference {
+    /// Whether the target is a Jinja expression that cannot be resolved
+    /// without running Ansible.
+    pub fn is_templated(&self) -> bool {
+        let templated = |s: &str| s.contains("{{") || s.contains("{%");
+        templated(&self.target) || self.tasks_from.as_deref().is_some_and(templated)
+    }
+}
+
+/// Which files include, import or apply which others, built by following
+/// `import_playbook`, `include_tasks`/`import_tasks`,
+/// `include_role`/`import_role`, play `roles:` and role dependencies from the
+/// scanned files. Paths are normalized: no `.` components and `..` resolved
+/// where possible.
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    references: BTreeMap<PathBuf, Vec<Reference>>,
+    kinds: BTreeMap<PathBuf, FileKind>,
+    /// Directories searched for roles after the including playbook's
+    /// `roles/`, from `roles_path` in `ansible.cfg`
+    roles_path: Vec<PathBuf>,
+    /// `ansible_collections` directories searched for roles given by FQCN
+    collections_path: Vec<PathBuf>,
+    /// Chains from entry points, by the file they reach
+    chains: BTreeMap<PathBuf, Vec<Vec<PathBuf>>>,
+}
+
+impl DependencyGraph {
+    /// Build the graph for the files of a scan of `root`. Files reached
+    /// through references are followed even if they were not discovered,
+    /// e.g. roles under a `roles_path` outside the scanned directory.
+    pub fn build(root: &Path, files: &[(PathBuf, FileKind)]) -> Self {
+        let cfg_dir = if root.is_file() {
+            root.parent().unwrap_or(Path::new(""))
+        } else {
+            root
+        };
+        let (roles_path, collections_path) = read_ansible_cfg(cfg_dir);
+
+        let mut graph = Self {
+            roles_path,
+            collections_path,
+            ..Self::default()
+        };
+
+        let mut pending: Vec<(PathBuf, FileKind)> = files
+            .iter()
+            .map(|(path, kind)| (normalize(path), *kind))
+            .collect();
+        loop {
+            while !pending.is_empty() {
+                let parsed: Vec<_> = pending
+                    .par_iter()
+                    .map(|(path, kind)| {
+                        let doc = fs::read_to_string(path)
+                            .ok()
+                            .map(|content| Document::parse_as(path, &content, *kind));
+                        let kind = doc.as_ref().map_or(*kind, |doc| doc.kind);
+                        let references = doc.map(|doc| graph.references_in(&doc));
+                        (path.clone(), kind, references.unwrap_or_default())
+                    })
+                    .collect();
+
+                for (path, kind, references) in parsed {
+                    graph.kinds.insert(path.clone(), kind);
+                    graph.references.insert(path, references);
+                }
+                pending = graph.unparsed();
+            }
+
+            // Targets missing next to a file may be next to the playbooks
+            // that reach it, which are only known once the chains are
+            graph.find_chains();
+            if !graph.resolve_from_playbooks() {
+                break;
+            }
+            pending = graph.unparsed();
+        }
+
+        graph
+    }
+
+    /// Files references run that have not been parsed yet.
+    fn unparsed(&self) -> Vec<(PathBuf, FileKind)> {
+        self.references
+            .values()
+            .flatten()
+            .flat_map(|reference| &reference.files)
+            .filter(|file| !self.kinds.contains_key(*file))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|file| {
+                let kind = discovery::file_kind(file).unwrap_or_default();
+                (file.clone(), kind)
+            })
+            .collect()
+    }
+
+    /// Resolve references still missing against the directories of the
+    /// playbooks that reach their file. Whether any was found.
+    fn resolve_from_playbooks(&mut self) -> bool {
+        let mut found = Vec::new();
+        for (file, references) in &self.references {
+            let mut dirs: Vec<PathBuf> = Vec::new();
+            for playbook in self.chains(file).iter().flatten() {
+                if self.kinds.get(playbook) != Some(&FileKind::Playbook) {
+                    continue;
+                }
+                let dir = playbook.parent().unwrap_or(Path::new("")).to_path_buf();
+                if Some(dir.as_path()) != file.parent() && !dirs.contains(&dir) {
+                    dirs.push(dir);
+                }
+            }
+            if dirs.is_empty() {
+                continue;
+            }
+
+            for (idx, reference) in references.iter().enumerate() {
+                if reference.resolved.is_some() || reference.is_templated() {
+                    continue;
+                }
+                let mut reference = reference.clone();
+                self.resolve(file, &mut reference, &dirs);
+                if reference.resolved.is_some() {
+                    found.push((file.clone(), idx, reference));
+                }
+            }
+        }
+
+        let any = !found.is_empty();
+        for (file, idx, reference) in found {
+            if let Some(references) = self.references.get_mut(&file) {
+                references[idx] = reference;
+            }
+        }
+        any
+    }
+
+    /// The references made by `path`.
+    pub fn references(&self, path: &Path) -> &[Reference] {
+        self.references
+            .get(&normalize(path))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Every file in the graph with its kind.
+    pub fn files(&self) -> impl Iterator<Item = (&Path, FileKind)> {
+        self.kinds
+            .iter()
+            .map(|(path, kind)| (path.as_path(), *kind))
+    }
+
+    /// Playbooks no other file imports, where Ansible runs start.
+    pub fn entry_points(&self) -> Vec<&Path> {
+        let referenced: BTreeSet<&PathBuf> = self
+            .references
+            .values()
+            .flatten()
+            .flat_map(|reference| &reference.files)
+            .collect();
+        self.kinds
+            .iter()
+            .filter(|(path, kind)| **kind == FileKind::Playbook && !referenced.contains(path))
+            .map(|(path, _)| path.as_path())
+            .collect()
+    }
+
+    /// The shortest chain from each entry point that reaches `path`, entry
+    /// point first and `path` last. Empty for entry points themselves and
+    /// for files nothing reaches.
+    pub fn chains(&self, path: &Path) -> &[Vec<PathBuf>] {
+        self.chains
+            .get(&normalize(path))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Print the tree of files each entry point reaches.
+    pub fn print(&self) {
+        let entry_points = self.entry_points();
+        if entry_points.is_empty() {
+            println!("No entry-point playbooks found");
+        }
+        let mut shown = BTreeSet::new();
+        for entry in entry_points {
+            println!("{}", entry.display());
+            self.print_references(entry, 1, &mut vec![entry], &mut shown);
+        }
+    }
+
+    fn print_references<'a>(
+        &'a self,
+        file: &Path,
+        depth: usize,
+        stack: &mut Vec<&'a Path>,
+        shown: &mut BTreeSet<&'a Path>,
+    ) {
+        let indent = "  ".repeat(depth);
+        for reference in self.references(file) {
+            let label = format!(
+                "{}:{} {} {}",
+                file.display(),
+                reference.span.start.line,
+                reference.kind.as_str(),
+                reference.target
+            );
+            if reference.files.is_empty() {
+                match reference.resolved {
+                    Some(ref found) => println!("{}{} -> {}", indent, label, found.display()),
+                    None if reference.is_templated() => println!("{}{} (templated)", indent, label),
+                    None => println!("{}{} (not found)", indent, label),
+                }
+                continue;
+            }
+            for target in &reference.files {
+                if stack.contains(&target.as_path()) {
+                    println!("{}{} -> {} (cycle)", indent, label, target.display());
+                } else if !shown.insert(target) {
+                    println!("{}{} -> {} (shown above)", indent, label, target.display());
+                } else {
+                    println!("{}{} -> {}", indent, label, target.display());
+                    stack.push(target);
+                    self.print_references(target, depth + 1, stack, shown);
+                    stack.pop();
+                }
+            }
+        }
+    }
+
+    /// Walk breadth-first from every entry point, recording the shortest
+    /// chain to each file reached.
+    fn find_chains(&mut self) {
+        let mut chains: BTreeMap<PathBuf, Vec<Vec<PathBuf>>> = BTreeMap::new();
+        for entry in self.entry_points() {
+            let mut previous: BTreeMap<&Path, &Path> = BTreeMap::new();
+            let mut queue = VecDeque::from([entry]);
+            while let Some(file) = queue.pop_front() {
+                for next in self.references(file).iter().flat_map(|r| &r.files) {
+                    if next.as_path() != entry && !previous.contains_key(next.as_path()) {
+                        previous.insert(next, file);
+                        queue.push_back(next);
+                    }
+                }
+            }
+
+            for &file in previous.keys() {
+                let mut chain = vec![file.to_path_buf()];
+                let mut current = file;
+                while let Some(prev) = previous.get(current) {
+                    chain.push(prev.to_path_buf());
+                    current = prev;
+                }
+                chain.reverse();
+                chains.entry(file.to_path_buf()).or_default().push(chain);
+            }
+        }
+        self.chains = chains;
+    }
+
+    /// The references of a parsed file, resolved against the file system.
+    fn references_in(&self, file: &Document) -> Vec<Reference> {
+        let mut references = Vec::new();
+        let mut first_play = 0;
+        for doc in file.documents() {
+            self.document_references(doc, first_play, &mut references);
+            first_play += doc.plays.len();
+        }
+        references
+    }
+
+    /// Add the references of one document of a stream whose plays are
+    /// numbered from `first_play`.
+    fn document_references(
+        &self,
+        doc: &Document,
+        first_play: usize,
+        references: &mut Vec<Reference>,
+    ) {
+        for (play_idx, play) in doc.plays.iter().enumerate() {
+            if let Some(ref target) = play.import_playbook {
+                let key = if play.raw.contains_key("import_playbook") {
+                    "import_playbook"
+                } else {
+                    "ansible.builtin.import_playbook"
+                };
+                let span = doc.value_span(&yaml::key(&play.path, key));
+                references.push(self.reference(doc, RefKind::ImportPlaybook, target, None, span));
+            }
+            for (idx, role) in play.roles.iter().enumerate() {
+                let path = yaml::child(&yaml::key(&play.path, "roles"), PathSegment::Index(idx));
+                let span = doc.value_span(&path);
+                let mut reference = self.reference(doc, RefKind::Role, &role.name, None, span);
+                reference.params = role.params();
+                reference.play = Some(first_play + play_idx);
+                references.push(reference);
+            }
+        }
+
+        for task_ref in doc.tasks() {
+            let task = task_ref.task;
+            let kind = match task.fqcn.as_deref() {
+                Some("ansible.builtin.include_tasks" | "ansible.builtin.include") => {
+                    RefKind::IncludeTasks
+                }
+                Some("ansible.builtin.import_tasks") => RefKind::ImportTasks,
+                Some("ansible.builtin.include_role") => RefKind::IncludeRole,
+                Some("ansible.builtin.import_role") => RefKind::ImportRole,
+                Some("ansible.builtin.template") => RefKind::Template,
+                // A `remote_src` is on the managed host
+                Some("ansible.builtin.copy")
+                    if !task
+                        .args
+                        .get("remote_src")
+                        .and_then(as_ansible_bool)
+                        .unwrap_or(false) =>
+                {
+                    RefKind::Copy
+                }
+                _ => continue,
+            };
+            let arg = if kind.is_role() {
+                "name"
+            } else if matches!(kind, RefKind::Template | RefKind::Copy) {
+                "src"
+            } else if task.args.contains_key("file") {
+                "file"
+            } else {
+                modules::RAW_PARAMS
+            };
+            let Some(target) = task.args.get(arg).and_then(scalar) else {
+                continue;
+            };
+            let tasks_from = task.args.get("tasks_from").and_then(scalar);
+            let span = doc.arg_span(task, arg);
+            references.push(self.reference(doc, kind, &target, tasks_from, span));
+        }
+
+        if doc.kind == FileKind::RoleMeta {
+            let dependencies = doc
+                .root
+                .as_ref()
+                .and_then(|root| root.get("dependencies"))
+                .and_then(Value::as_sequence);
+            for (idx, value) in dependencies.into_iter().flatten().enumerate() {
+                let Some(role) = Role::from_value(value) else {
+                    continue;
+                };
+                let path = yaml::child(&yaml::key(&[], "dependencies"), PathSegment::Index(idx));
+                let span = doc.value_span(&path);
+                let mut reference =
+                    self.reference(doc, RefKind::RoleDependency, &role.name, None, span);
+                reference.params = role.params();
+                references.push(reference);
+            }
+        }
+    }
+
+    fn reference(
+        &self,
+        doc: &Document,
+        kind: RefKind,
+        target: &str,
+        tasks_from: Option<String>,
+        span: Span,
+    ) -> Reference {
+        let mut reference = Reference {
+            kind,
+            target: target.to_string(),
+            tasks_from,
+            span,
+            resolved: None,
+            files: Vec::new(),
+            params: Mapping::new(),
+            play: None,
+        };
+        if !reference.is_templated() {
+            self.resolve(&doc.path, &mut reference, &[]);
+        }
+        reference
+    }
+
+    /// Find the file or role a reference names, as Ansible would: in the
+    /// role the referring file belongs to, next to the file, then next to
+    /// `playbook_dirs`.
+    fn resolve(&self, from: &Path, reference: &mut Reference, playbook_dirs: &[PathBuf]) {
+        let dir = from.parent().unwrap_or(Path::new(""));
+        let role = role_root(from);
+
+        if reference.kind.is_role() {
+            let Some(role_dir) =
+                self.find_role(dir, role.as_deref(), playbook_dirs, &reference.target)
+            else {
+                return;
+            };
+            let entry = reference.tasks_from.as_deref().unwrap_or("main");
+            let tasks = yaml_file(&role_dir.join("tasks").join(entry));
+            if reference.tasks_from.is_some() && tasks.is_none() {
+                return;
+            }
+            reference.files = [("handlers", "main"), ("meta", "main")]
+                .iter()
+                .filter_map(|(sub, name)| yaml_file(&role_dir.join(sub).join(name)))
+                .collect();
+            reference.files.splice(0..0, tasks.clone());
+            reference.resolved = match reference.tasks_from {
+                Some(_) => tasks,
+                None => Some(role_dir),
+            };
+            return;
+        }
+
+        // Roles look in `templates/`, `files/` or `tasks/` first, and
+        // templates and files also in those directories of the playbook
+        let sub = match reference.kind {
+            RefKind::Template => "templates",
+            RefKind::Copy => "files",
+            _ => "tasks",
+        };
+        let target = Path::new(&reference.target);
+        let mut candidates = Vec::new();
+        if target.is_absolute() {
+            candidates.push(target.to_path_buf());
+        } else {
+            if let Some(ref role) = role {
+                if reference.kind != RefKind::ImportPlaybook {
+                    candidates.push(role.join(sub).join(target));
+                    candidates.push(role.join(target));
+                }
+            }
+            for base in std::iter::once(dir).chain(playbook_dirs.iter().map(PathBuf::as_path)) {
+                if matches!(reference.kind, RefKind::Template | RefKind::Copy) {
+                    candidates.push(base.join(sub).join(target));
+                }
+                candidates.push(base.join(target));
+            }
+        }
+
+        // `copy` also takes directories
+        let exists = |path: &Path| match reference.kind {
+            RefKind::Copy => path.exists(),
+            _ => path.is_file(),
+        };
+        let Some(found) = candidates
+            .into_iter()
+            .map(|candidate| normalize(&candidate))
+            .find(|candidate| exists(candidate))
+        else {
+            return;
+        };
+        if !matches!(reference.kind, RefKind::Template | RefKind::Copy) {
+            reference.files = vec![found.clone()];
+        }
+        reference.resolved = Some(found);
+    }
+
+    /// The directory of role `name`: under `roles/` next to the playbook, a
+    /// sibling of the including role, on the `roles_path`, a path relative
+    /// to the playbook, or a collection's role for a `namespace.collection.role`
+    /// name.
+    fn find_role(
+        &self,
+        dir: &Path,
+        role: Option<&Path>,
+        playbook_dirs: &[PathBuf],
+        name: &str,
+    ) -> Option<PathBuf> {
+        let mut candidates = Vec::new();
+        match role {
+            Some(role) => {
+                let roles_dir = role.parent().unwrap_or(Path::new(""));
+                candidates.push(roles_dir.join(name));
+                if let Some(project) = roles_dir.parent() {
+                    candidates.push(project.join("roles").join(name));
+                }
+            }
+            None => candidates.push(dir.join("roles").join(name)),
+        }
+        candidates.extend(playbook_dirs.iter().map(|dir| dir.join("roles").join(name)));
+        candidates.extend(self.roles_path.iter().map(|path| path.join(name)));
+        candidates.push(dir.join(name));
+
+        let parts: Vec<&str> = name.split('.').collect();
+        if let [namespace, collection, role_name] = parts[..] {
+            let collections = self
+                .collections_path
+                .iter()
+                .cloned()
+                .chain([dir.join("collections").join("ansible_collections")]);
+            for collections in collections {
+                candidates.push(
+                    collections
+                        .join(namespace)
+                        .join(collection)
+                        .join("roles")
+                        .join(role_name),
+                );
+            }
+        }
+
+        candidates
+            .into_iter()
+            .map(|candidate| normalize(&candidate))
+            .find(|candidate| candidate.is_dir())
+    }
+}
+
+/// The role a file belongs to: the directory above its `tasks/`, `meta/`
+/// or other role directory.
+pub fn role_root(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .skip(1)
+        .find(|dir| {
+            dir.file_name()
+                .is_some_and(|name| ROLE_DIRS.iter().any(|role_dir| name == *role_dir))
+        })
+        .and_then(Path::parent)
+        .map(Path::to_path_buf)
+}
+
+/// Lexically normalize a path: drop `.` components and resolve `..`
+/// against the components before it.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                let last = normalized.components().next_back();
+                if matches!(last, Some(Component::Normal(_))) {
+                    normalized.pop();
+                } else if !matches!(last, Some(Component::RootDir | Component::Prefix(_))) {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// `path` with a `.yml` or `.yaml` extension, if either exists.
+fn yaml_file(path: &Path) -> Option<PathBuf> {
+    ["yml", "yaml"]
+        .iter()
+        .map(|ext| normalize(&path.with_extension(ext)))
+        .find(|file| file.is_file())
+}
+
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.trim().to_string()),
+        _ => None,
+    }
+}
+
+/// `roles_path` and `collections_path` from the `[defaults]` section of
+/// `ansible.cfg` in `dir`, relative to it.
+fn read_ansible_cfg(dir: &Path) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let mut roles_path = Vec::new();
+    let mut collections_path = Vec::new();
+    let Ok(content) = fs::read_to_string(dir.join("ansible.cfg")) else {
+        return (roles_path, collections_path);
+    };
+
+    let mut in_defaults = false;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_defaults = line == "[defaults]";
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=').or_else(|| line.split_once(':')) else {
+            continue;
+        };
+        let paths = value
+            .split(':')
+            .map(str::trim)
+            .filter(|path| !path.is_empty() && !path.starts_with('~'))
+            .map(|path| normalize(&dir.join(path)));
+        match key.trim() {
+            "roles_path" if in_defaults => roles_path.extend(paths),
+            "collections_path" | "collections_paths" if in_defaults => {
+                collections_path.extend(paths.map(|path| path.join("ansible_collections")))
+            }
+            _ => {}
+        }
+    }
+    (roles_path, collections_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize(Path::new("./a/./b/../c.yml")),
+            Path::new("a/c.yml")
+        );
+        assert_eq!(normalize(Path::new("../x/../y")), Path::new("../y"));
+        assert_eq!(
+            role_root(Path::new("roles/web/tasks/sub/main.yml")),
+            Some(PathBuf::from("roles/web"))
+        );
+        assert_eq!(role_root(Path::new("site.yml")), None);
+    }
+}
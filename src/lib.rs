@@ -1,11 +1,27 @@
+pub mod baseline;
 pub mod cache;
 pub mod cli;
 pub mod config;
+pub mod discovery;
 pub mod errors;
+pub mod graph;
 pub mod linter;
+pub mod matcher;
+pub mod model;
+pub mod modules;
+pub mod overrides;
+pub mod pattern;
 pub mod policy;
+pub mod profiles;
+pub mod references;
 pub mod reporting;
+pub mod roles;
 pub mod rules;
 pub mod sbom;
 pub mod scanner;
+pub mod schema;
 pub mod secrets;
+pub mod selftest;
+pub mod suppression;
+pub mod validation;
+pub mod yaml;
@@ -1,146 +1,226 @@
 use crate::config::Config;
 use crate::errors::Result;
+use crate::model::Document;
+use crate::modules;
+use crate::overrides::RuleOverrides;
+use crate::rules::RuleListing;
 use crate::scanner::Finding;
+use crate::yaml::{self, Span};
+
+/// Every lint rule: ID, default severity and what it reports.
+pub const LINT_RULES: &[(&str, &str, &str)] = &[
+    ("LINT_001", "MEDIUM", "Play should define 'hosts'"),
+    ("LINT_002", "LOW", "Play should have a descriptive 'name'"),
+    (
+        "LINT_003",
+        "MEDIUM",
+        "Package management tasks should use 'become: true'",
+    ),
+    (
+        "LINT_004",
+        "LOW",
+        "Command tasks should define 'changed_when'",
+    ),
+    ("LINT_005", "LOW", "Task name is too short"),
+    (
+        "LINT_006",
+        "INFO",
+        "Task name should start with uppercase letter",
+    ),
+    ("LINT_007", "INFO", "Line too long"),
+    ("LINT_008", "INFO", "Trailing whitespace"),
+    (
+        "LINT_009",
+        "HIGH",
+        "YAML does not allow tabs for indentation",
+    ),
+    ("LINT_010", "LOW", "Task should have a 'name'"),
+    (
+        "LINT_011",
+        "MEDIUM",
+        "File mode is read as a decimal number",
+    ),
+    ("LINT_012", "HIGH", "YAML document does not parse"),
+];
+
+/// A finding of lint rule `rule_id`, with its default severity from
+/// `LINT_RULES`.
+fn finding(rule_id: &str, span: Span, message: String, context: String) -> Finding {
+    let severity = LINT_RULES
+        .iter()
+        .find(|(id, _, _)| *id == rule_id)
+        .map_or("INFO", |&(_, severity, _)| severity);
+    Finding {
+        line: span.start.line,
+        column: span.start.column,
+        end_line: span.end.line,
+        end_column: span.end.column,
+        severity: severity.to_string(),
+        rule_id: rule_id.to_string(),
+        message,
+        context: Some(context),
+        remediation: None,
+        suppression: None,
+        baselined: false,
+    }
+}
 
 pub struct Linter {
-    #[allow(dead_code)]
     config: Config,
+    /// Overrides of the profile and `rules:` section
+    rules: RuleOverrides,
 }
 
 impl Linter {
-    pub fn new(config: Config) -> Self {
-        Self { config }
+    pub fn new(config: Config) -> Result<Self> {
+        let rules = config.effective_rules()?;
+        Ok(Self { config, rules })
     }
 
-    pub fn lint(&self, content: &str) -> Result<Vec<Finding>> {
+    pub fn lint(&self, doc: &Document) -> Result<Vec<Finding>> {
         let mut findings = Vec::new();
 
-        // Parse YAML structure
-        if let Ok(yaml) = serde_yaml::from_str::<serde_yaml::Value>(content) {
-            findings.extend(self.check_yaml_structure(&yaml, content)?);
-            findings.extend(self.check_best_practices(&yaml, content)?);
-            findings.extend(self.check_naming_conventions(&yaml, content)?);
+        // Structural checks only apply to YAML content, in every document
+        // of a stream
+        for document in doc.documents().filter(|document| document.root.is_some()) {
+            findings.extend(self.check_yaml_structure(document)?);
+            findings.extend(self.check_best_practices(document)?);
+            findings.extend(self.check_naming_conventions(document)?);
+            if self.is_enabled("LINT_011") {
+                findings.extend(self.check_file_modes(document)?);
+            }
         }
 
         // General linting checks
-        findings.extend(self.check_line_length(content)?);
-        findings.extend(self.check_trailing_whitespace(content)?);
-        findings.extend(self.check_yaml_syntax(content)?);
+        findings.extend(self.check_line_length(&doc.content)?);
+        findings.extend(self.check_trailing_whitespace(&doc.content)?);
+        findings.extend(self.check_yaml_syntax(&doc.content)?);
+        findings.extend(self.check_parse_errors(doc)?);
+
+        findings.retain_mut(|finding| {
+            let mut enabled = self.setting(&finding.rule_id);
+            self.rules
+                .apply(&finding.rule_id, &mut finding.severity, &mut enabled);
+            enabled
+        });
 
         Ok(findings)
     }
 
-    fn check_yaml_structure(
-        &self,
-        yaml: &serde_yaml::Value,
-        content: &str,
-    ) -> Result<Vec<Finding>> {
+    /// Whether a rule runs: the `linting` setting that gates it, unless the
+    /// profile or `rules:` section turns it on or off.
+    fn is_enabled(&self, rule_id: &str) -> bool {
+        let Some(&(_, severity, _)) = LINT_RULES.iter().find(|(id, _, _)| *id == rule_id) else {
+            return true;
+        };
+        let mut severity = severity.to_string();
+        let mut enabled = self.setting(rule_id);
+        self.rules.apply(rule_id, &mut severity, &mut enabled);
+        enabled
+    }
+
+    fn setting(&self, rule_id: &str) -> bool {
+        match rule_id {
+            "LINT_002" | "LINT_010" => self.config.linting.require_name,
+            "LINT_011" => self.config.linting.check_permissions,
+            _ => true,
+        }
+    }
+
+    /// The lint rules as shown by `rules list`.
+    pub fn listings(&self) -> Vec<RuleListing> {
+        LINT_RULES
+            .iter()
+            .map(|&(id, severity, name)| {
+                let mut severity = severity.to_string();
+                let mut enabled = self.setting(id);
+                let overridden = self.rules.apply(id, &mut severity, &mut enabled);
+                RuleListing {
+                    id: id.to_string(),
+                    name: name.to_string(),
+                    severity,
+                    enabled,
+                    description: String::new(),
+                    remediation: None,
+                    manual: false,
+                    overridden,
+                }
+            })
+            .collect()
+    }
+
+    fn check_yaml_structure(&self, doc: &Document) -> Result<Vec<Finding>> {
         let mut findings = Vec::new();
 
         // Check for proper playbook structure
-        if yaml.is_sequence() {
-            if let Some(plays) = yaml.as_sequence() {
-                for (idx, play) in plays.iter().enumerate() {
-                    if let Some(obj) = play.as_mapping() {
-                        // Check for required fields
-                        if !obj.contains_key("hosts") {
-                            findings.push(Finding {
-                                line: self.find_line_number(content, "name", idx),
-                                column: 0,
-                                severity: "MEDIUM".to_string(),
-                                rule_id: "LINT_001".to_string(),
-                                message: "Play should define 'hosts'".to_string(),
-                                context: Some(
-                                    "Every play should specify which hosts to run on".to_string(),
-                                ),
-                            });
-                        }
-
-                        if !obj.contains_key("name") {
-                            findings.push(Finding {
-                                line: 1,
-                                column: 0,
-                                severity: "LOW".to_string(),
-                                rule_id: "LINT_002".to_string(),
-                                message: "Play should have a descriptive 'name'".to_string(),
-                                context: Some(
-                                    "Named plays improve readability and debugging".to_string(),
-                                ),
-                            });
-                        }
-                    }
-                }
+        for play in &doc.plays {
+            // Playbook imports are not plays of their own
+            if play.import_playbook.is_some() {
+                continue;
+            }
+
+            let span = doc.play_span(play);
+
+            // Check for required fields
+            if play.hosts.is_none() {
+                findings.push(finding(
+                    "LINT_001",
+                    span,
+                    "Play should define 'hosts'".to_string(),
+                    "Every play should specify which hosts to run on".to_string(),
+                ));
+            }
+
+            if play.name.is_none() && self.is_enabled("LINT_002") {
+                findings.push(finding(
+                    "LINT_002",
+                    span,
+                    "Play should have a descriptive 'name'".to_string(),
+                    "Named plays improve readability and debugging".to_string(),
+                ));
             }
         }
 
         Ok(findings)
     }
 
-    fn check_best_practices(
-        &self,
-        yaml: &serde_yaml::Value,
-        content: &str,
-    ) -> Result<Vec<Finding>> {
+    fn check_best_practices(&self, doc: &Document) -> Result<Vec<Finding>> {
         let mut findings = Vec::new();
 
-        // Check for become usage
-        if let Some(tasks) = yaml.get("tasks").and_then(|t| t.as_sequence()) {
-            for (idx, task) in tasks.iter().enumerate() {
-                if let Some(obj) = task.as_mapping() {
-                    // Check for package managers without become
-                    let package_modules = ["apt", "yum", "dnf", "package", "pip"];
-                    let has_package_module = obj.keys().any(|k| {
-                        if let Some(key_str) = k.as_str() {
-                            package_modules.contains(&key_str)
-                        } else {
-                            false
-                        }
-                    });
-
-                    if has_package_module {
-                        let has_become =
-                            obj.get("become").and_then(|v| v.as_bool()).unwrap_or(false);
-
-                        if !has_become {
-                            findings.push(Finding {
-                                line: self.find_line_number(content, "name", idx),
-                                column: 0,
-                                severity: "MEDIUM".to_string(),
-                                rule_id: "LINT_003".to_string(),
-                                message: "Package management tasks should use 'become: true'"
-                                    .to_string(),
-                                context: Some(
-                                    "Package installation typically requires elevated privileges"
-                                        .to_string(),
-                                ),
-                            });
-                        }
-                    }
-
-                    // Check for changed_when/failed_when on command/shell
-                    let command_modules = ["command", "shell"];
-                    let has_command_module = obj.keys().any(|k| {
-                        if let Some(key_str) = k.as_str() {
-                            command_modules.contains(&key_str)
-                        } else {
-                            false
-                        }
-                    });
-
-                    if has_command_module {
-                        let has_changed_when = obj.contains_key("changed_when");
-
-                        if !has_changed_when {
-                            findings.push(Finding {
-                                line: self.find_line_number(content, "name", idx),
-                                column: 0,
-                                severity: "LOW".to_string(),
-                                rule_id: "LINT_004".to_string(),
-                                message: "Command tasks should define 'changed_when'".to_string(),
-                                context: Some("Improves idempotency tracking".to_string()),
-                            });
-                        }
-                    }
+        for task in doc.tasks() {
+            let fqcn = task.task.fqcn.as_deref().unwrap_or_default();
+            let span = doc.module_span(task.task);
+
+            // Check for package managers without become
+            let package_modules = ["apt", "yum", "dnf", "package", "pip"];
+            let has_package_module = modules::is_any_module(fqcn, &package_modules);
+
+            if has_package_module {
+                // become may be inherited from an enclosing block or the play
+                if !task.become_enabled() {
+                    findings.push(finding(
+                        "LINT_003",
+                        span,
+                        "Package management tasks should use 'become: true'".to_string(),
+                        "Package installation typically requires elevated privileges".to_string(),
+                    ));
+                }
+            }
+
+            // Check for changed_when/failed_when on command/shell
+            let command_modules = ["command", "shell"];
+            let has_command_module = modules::is_any_module(fqcn, &command_modules);
+
+            if has_command_module {
+                let has_changed_when = task.task.raw.contains_key("changed_when");
+
+                if !has_changed_when {
+                    findings.push(finding(
+                        "LINT_004",
+                        span,
+                        "Command tasks should define 'changed_when'".to_string(),
+                        "Improves idempotency tracking".to_string(),
+                    ));
                 }
             }
         }
@@ -148,44 +228,41 @@ impl Linter {
         Ok(findings)
     }
 
-    fn check_naming_conventions(
-        &self,
-        yaml: &serde_yaml::Value,
-        content: &str,
-    ) -> Result<Vec<Finding>> {
+    fn check_naming_conventions(&self, doc: &Document) -> Result<Vec<Finding>> {
         let mut findings = Vec::new();
 
-        if let Some(tasks) = yaml.get("tasks").and_then(|t| t.as_sequence()) {
-            for (idx, task) in tasks.iter().enumerate() {
-                if let Some(obj) = task.as_mapping() {
-                    if let Some(name) = obj.get("name").and_then(|n| n.as_str()) {
-                        // Check naming conventions
-                        if name.len() < 5 {
-                            findings.push(Finding {
-                                line: self.find_line_number(content, "name", idx),
-                                column: 0,
-                                severity: "LOW".to_string(),
-                                rule_id: "LINT_005".to_string(),
-                                message: "Task name is too short".to_string(),
-                                context: Some(
-                                    "Use descriptive task names (at least 5 characters)"
-                                        .to_string(),
-                                ),
-                            });
-                        }
-
-                        // Check if name starts with uppercase
-                        if !name.chars().next().unwrap_or(' ').is_uppercase() {
-                            findings.push(Finding {
-                                line: self.find_line_number(content, "name", idx),
-                                column: 0,
-                                severity: "INFO".to_string(),
-                                rule_id: "LINT_006".to_string(),
-                                message: "Task name should start with uppercase letter".to_string(),
-                                context: Some("Follow consistent naming conventions".to_string()),
-                            });
-                        }
-                    }
+        for task in doc.tasks() {
+            if task.task.name.is_none() && self.is_enabled("LINT_010") {
+                let span = doc.module_span(task.task);
+                findings.push(finding(
+                    "LINT_010",
+                    span,
+                    "Task should have a 'name'".to_string(),
+                    "Named tasks make the play output readable".to_string(),
+                ));
+            }
+
+            if let Some(ref name) = task.task.name {
+                let span = doc.value_span(&yaml::key(&task.task.path, "name"));
+
+                // Check naming conventions
+                if name.len() < 5 {
+                    findings.push(finding(
+                        "LINT_005",
+                        span,
+                        "Task name is too short".to_string(),
+                        "Use descriptive task names (at least 5 characters)".to_string(),
+                    ));
+                }
+
+                // Check if name starts with uppercase
+                if !name.chars().next().unwrap_or(' ').is_uppercase() {
+                    findings.push(finding(
+                        "LINT_006",
+                        span,
+                        "Task name should start with uppercase letter".to_string(),
+                        "Follow consistent naming conventions".to_string(),
+                    ));
                 }
             }
         }
@@ -193,20 +270,47 @@ impl Linter {
         Ok(findings)
     }
 
+    /// Modes written as plain numbers, e.g. `mode: 644`, are read by
+    /// Ansible as decimal and give unexpected permissions. `0o644` is octal
+    /// and fine.
+    fn check_file_modes(&self, doc: &Document) -> Result<Vec<Finding>> {
+        let mut findings = Vec::new();
+
+        for task in doc.tasks() {
+            let Some(serde_yaml::Value::Number(_)) = task.task.args.get("mode") else {
+                continue;
+            };
+            let source = doc.source(doc.arg_value_span(task.task, "mode"));
+            let mode = source.trim();
+            if mode.is_empty() || !mode.chars().all(|c| c.is_ascii_digit()) {
+                continue;
+            }
+
+            let span = doc.arg_span(task.task, "mode");
+            findings.push(finding(
+                "LINT_011",
+                span,
+                format!("File mode {} is read as a decimal number", mode),
+                format!("Quote the mode as an octal string: '0{}'", mode),
+            ));
+        }
+
+        Ok(findings)
+    }
+
     fn check_line_length(&self, content: &str) -> Result<Vec<Finding>> {
         let mut findings = Vec::new();
-        const MAX_LINE_LENGTH: usize = 160;
+        let max_line_length = self.config.linting.max_line_length;
 
         for (line_num, line) in content.lines().enumerate() {
-            if line.len() > MAX_LINE_LENGTH {
-                findings.push(Finding {
-                    line: line_num + 1,
-                    column: MAX_LINE_LENGTH,
-                    severity: "INFO".to_string(),
-                    rule_id: "LINT_007".to_string(),
-                    message: format!("Line too long ({} > {})", line.len(), MAX_LINE_LENGTH),
-                    context: Some("Consider breaking long lines for readability".to_string()),
-                });
+            let length = line.chars().count();
+            if length > max_line_length {
+                findings.push(finding(
+                    "LINT_007",
+                    Span::on_line(line_num + 1, max_line_length + 1, length + 1),
+                    format!("Line too long ({} > {})", length, max_line_length),
+                    "Consider breaking long lines for readability".to_string(),
+                ));
             }
         }
 
@@ -218,51 +322,59 @@ impl Linter {
 
         for (line_num, line) in content.lines().enumerate() {
             if line.ends_with(' ') || line.ends_with('\t') {
-                findings.push(Finding {
-                    line: line_num + 1,
-                    column: line.trim_end().len(),
-                    severity: "INFO".to_string(),
-                    rule_id: "LINT_008".to_string(),
-                    message: "Trailing whitespace".to_string(),
-                    context: Some("Remove trailing whitespace".to_string()),
-                });
+                findings.push(finding(
+                    "LINT_008",
+                    Span::on_line(
+                        line_num + 1,
+                        line.trim_end().chars().count() + 1,
+                        line.chars().count() + 1,
+                    ),
+                    "Trailing whitespace".to_string(),
+                    "Remove trailing whitespace".to_string(),
+                ));
             }
         }
 
         Ok(findings)
     }
 
+    /// Documents of the stream left out because they are not valid YAML.
+    fn check_parse_errors(&self, doc: &Document) -> Result<Vec<Finding>> {
+        Ok(doc
+            .parse_errors
+            .iter()
+            .map(|error| {
+                finding(
+                    "LINT_012",
+                    Span::on_line(
+                        error.position.line,
+                        error.position.column,
+                        error.position.column + 1,
+                    ),
+                    "YAML document does not parse".to_string(),
+                    error.message.clone(),
+                )
+            })
+            .collect())
+    }
+
     fn check_yaml_syntax(&self, content: &str) -> Result<Vec<Finding>> {
         let mut findings = Vec::new();
 
         // Check for common YAML issues
         for (line_num, line) in content.lines().enumerate() {
             // Check for tabs (YAML doesn't allow tabs for indentation)
-            if line.contains('\t') {
-                findings.push(Finding {
-                    line: line_num + 1,
-                    column: line.find('\t').unwrap_or(0),
-                    severity: "HIGH".to_string(),
-                    rule_id: "LINT_009".to_string(),
-                    message: "YAML does not allow tabs for indentation".to_string(),
-                    context: Some("Use spaces instead of tabs".to_string()),
-                });
+            if let Some(pos) = line.find('\t') {
+                let column = line[..pos].chars().count() + 1;
+                findings.push(finding(
+                    "LINT_009",
+                    Span::on_line(line_num + 1, column, column + 1),
+                    "YAML does not allow tabs for indentation".to_string(),
+                    "Use spaces instead of tabs".to_string(),
+                ));
             }
         }
 
         Ok(findings)
     }
-
-    fn find_line_number(&self, content: &str, search_term: &str, occurrence: usize) -> usize {
-        let mut count = 0;
-        for (line_num, line) in content.lines().enumerate() {
-            if line.contains(search_term) {
-                if count == occurrence {
-                    return line_num + 1;
-                }
-                count += 1;
-            }
-        }
-        1
-    }
 }
@@ -1,14 +1,30 @@
+mod baseline;
 mod cache;
 mod cli;
 mod config;
+mod discovery;
 mod errors;
+mod graph;
 mod linter;
+mod matcher;
+mod model;
+mod modules;
+mod overrides;
+mod pattern;
 mod policy;
+mod profiles;
+mod references;
 mod reporting;
+mod roles;
 mod rules;
 mod sbom;
 mod scanner;
+mod schema;
 mod secrets;
+mod selftest;
+mod suppression;
+mod validation;
+mod yaml;
 
 use anyhow::Result;
 use clap::Parser;
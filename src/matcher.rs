@@ -0,0 +1,567 @@
+use schemars::JsonSchema;
+use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::errors::{AnsibleSecError, Result};
+use crate::model::{as_ansible_bool, TaskRef};
+use crate::modules;
+use crate::pattern::Pattern;
+use crate::policy::parse_mode;
+
+/// Task keywords that blocks and plays pass down to their tasks.
+const INHERITED_KEYWORDS: &[&str] = &[
+    "any_errors_fatal",
+    "become",
+    "become_exe",
+    "become_flags",
+    "become_method",
+    "become_user",
+    "check_mode",
+    "collections",
+    "connection",
+    "debugger",
+    "delegate_to",
+    "diff",
+    "environment",
+    "ignore_errors",
+    "ignore_unreachable",
+    "module_defaults",
+    "no_log",
+    "port",
+    "remote_user",
+    "run_once",
+    "throttle",
+    "timeout",
+];
+
+/// Selects tasks for a `Task` rule: an optional list of modules and an
+/// optional condition on the task's arguments and keywords.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TaskSpec {
+    /// Modules the rule applies to (short names or FQCNs); empty means all tasks
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modules: Vec<String>,
+    #[serde(default, rename = "match", skip_serializing_if = "Option::is_none")]
+    pub condition: Option<Condition>,
+}
+
+/// A condition on a task. `all`, `any` and `not` combine other conditions
+/// and take no other keys; anything else tests a single field.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(untagged)]
+#[schemars(deny_unknown_fields)]
+pub enum Condition {
+    All { all: Vec<Condition> },
+    Any { any: Vec<Condition> },
+    Not { not: Box<Condition> },
+    Field(Box<FieldCondition>),
+}
+
+impl<'de> Deserialize<'de> for Condition {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct ConditionVisitor;
+
+        impl<'de> Visitor<'de> for ConditionVisitor {
+            type Value = Condition;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a condition: `all`, `any`, `not` or a field test")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> std::result::Result<Self::Value, A::Error> {
+                let mut entries = Mapping::new();
+                while let Some((key, value)) = map.next_entry::<String, Value>()? {
+                    entries.insert(Value::String(key), value);
+                }
+
+                let combinator = ["all", "any", "not"]
+                    .into_iter()
+                    .find(|key| entries.contains_key(*key));
+                let Some(combinator) = combinator else {
+                    return serde_yaml::from_value(Value::Mapping(entries))
+                        .map(|field| Condition::Field(Box::new(field)))
+                        .map_err(de::Error::custom);
+                };
+                if let Some(other) = entries
+                    .keys()
+                    .filter_map(Value::as_str)
+                    .find(|key| *key != combinator)
+                {
+                    return Err(de::Error::custom(format!(
+                        "`{}` cannot be combined with other keys, found `{}`",
+                        combinator, other
+                    )));
+                }
+
+                let inner = entries.remove(combinator).unwrap_or_default();
+                let in_combinator =
+                    |e: serde_yaml::Error| de::Error::custom(format!("{}: {}", combinator, e));
+                match combinator {
+                    "all" => serde_yaml::from_value(inner)
+                        .map(|all| Condition::All { all })
+                        .map_err(in_combinator),
+                    "any" => serde_yaml::from_value(inner)
+                        .map(|any| Condition::Any { any })
+                        .map_err(in_combinator),
+                    _ => serde_yaml::from_value(inner)
+                        .map(|not| Condition::Not { not })
+                        .map_err(in_combinator),
+                }
+            }
+        }
+
+        deserializer.deserialize_map(ConditionVisitor)
+    }
+}
+
+/// A test on one field. `field` is `args.<name>` for module arguments
+/// (free-form `k=v` arguments included) or a task keyword such as `become`;
+/// keywords are inherited from enclosing blocks and the play. Nested values
+/// are addressed with further dotted segments, e.g. `args.headers.Authorization`.
+///
+/// Exactly one operator must be set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FieldCondition {
+    pub field: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<serde_json::Value>")]
+    pub equals: Option<Value>,
+    #[serde(default, rename = "in", skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<Vec<serde_json::Value>>")]
+    pub one_of: Option<Vec<Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exists: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub absent: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<serde_json::Value>")]
+    pub gt: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<serde_json::Value>")]
+    pub ge: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<serde_json::Value>")]
+    pub lt: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<serde_json::Value>")]
+    pub le: Option<Value>,
+    /// Octal file mode bits that must all be set, e.g. `"0002"` for world-writable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bits: Option<String>,
+    /// Compare `gt`/`ge`/`lt`/`le` as octal, e.g. file modes
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub octal: bool,
+}
+
+/// A `TaskSpec` with its regexes compiled.
+#[derive(Debug, Clone)]
+pub struct TaskMatcher {
+    modules: Vec<String>,
+    condition: Option<Compiled>,
+}
+
+#[derive(Debug, Clone)]
+enum Compiled {
+    All(Vec<Compiled>),
+    Any(Vec<Compiled>),
+    Not(Box<Compiled>),
+    Field(Vec<String>, Operator),
+}
+
+#[derive(Debug, Clone)]
+enum Operator {
+    Equals(Value),
+    In(Vec<Value>),
+    Regex(Pattern),
+    Exists(bool),
+    /// `actual` compared to `bound` must give `ordering` (or equal if `inclusive`)
+    Compare {
+        ordering: Ordering,
+        inclusive: bool,
+        bound: f64,
+        octal: bool,
+    },
+    /// Every bit of the mask is set in the value read as a file mode
+    Bits(u32),
+}
+
+impl TaskMatcher {
+    pub fn compile(spec: &TaskSpec) -> Result<Self> {
+        Ok(Self {
+            modules: spec.modules.clone(),
+            condition: spec.condition.as_ref().map(compile).transpose()?,
+        })
+    }
+
+    pub fn matches(&self, task: &TaskRef) -> bool {
+        if !self.modules.is_empty() {
+            let fqcn = task.task.fqcn.as_deref().unwrap_or_default();
+            if !self.modules.iter().any(|m| modules::is_module(fqcn, m)) {
+                return false;
+            }
+        }
+
+        self.condition
+            .as_ref()
+            .is_none_or(|condition| evaluate(condition, task))
+    }
+
+    /// The regexes of the condition's `regex` tests.
+    pub fn patterns(&self) -> Vec<&Pattern> {
+        fn collect<'a>(condition: &'a Compiled, patterns: &mut Vec<&'a Pattern>) {
+            match condition {
+                Compiled::All(conditions) | Compiled::Any(conditions) => {
+                    conditions.iter().for_each(|c| collect(c, patterns))
+                }
+                Compiled::Not(condition) => collect(condition, patterns),
+                Compiled::Field(_, Operator::Regex(pattern)) => patterns.push(pattern),
+                Compiled::Field(..) => {}
+            }
+        }
+
+        let mut patterns = Vec::new();
+        if let Some(ref condition) = self.condition {
+            collect(condition, &mut patterns);
+        }
+        patterns
+    }
+}
+
+fn compile(condition: &Condition) -> Result<Compiled> {
+    Ok(match condition {
+        Condition::All { all } => Compiled::All(all.iter().map(compile).collect::<Result<_>>()?),
+        Condition::Any { any } => Compiled::Any(any.iter().map(compile).collect::<Result<_>>()?),
+        Condition::Not { not } => Compiled::Not(Box::new(compile(not)?)),
+        Condition::Field(field) => {
+            Compiled::Field(field_path(&field.field)?, compile_operator(field)?)
+        }
+    })
+}
+
+fn field_path(field: &str) -> Result<Vec<String>> {
+    let path: Vec<String> = field.split('.').map(str::to_string).collect();
+    if path.iter().any(|s| s.is_empty()) || (path[0] == "args" && path.len() < 2) {
+        return Err(AnsibleSecError::InvalidRuleFormat(format!(
+            "invalid field '{}'",
+            field
+        )));
+    }
+    Ok(path)
+}
+
+fn compile_operator(field: &FieldCondition) -> Result<Operator> {
+    let mut operators = Vec::new();
+    if let Some(ref value) = field.equals {
+        operators.push(Operator::Equals(value.clone()));
+    }
+    if let Some(ref values) = field.one_of {
+        operators.push(Operator::In(values.clone()));
+    }
+    if let Some(ref regex) = field.regex {
+        operators.push(Operator::Regex(Pattern::new(regex)?));
+    }
+    if let Some(exists) = field.exists {
+        operators.push(Operator::Exists(exists));
+    }
+    if let Some(absent) = field.absent {
+        operators.push(Operator::Exists(!absent));
+    }
+    if let Some(ref bits) = field.bits {
+        let mask = parse_mode(bits).ok_or_else(|| {
+            AnsibleSecError::InvalidRuleFormat(format!(
+                "field '{}' has bits '{}' that are not an octal file mode",
+                field.field, bits
+            ))
+        })?;
+        operators.push(Operator::Bits(mask));
+    }
+    for (bound, ordering, inclusive) in [
+        (&field.gt, Ordering::Greater, false),
+        (&field.ge, Ordering::Greater, true),
+        (&field.lt, Ordering::Less, false),
+        (&field.le, Ordering::Less, true),
+    ] {
+        if let Some(ref bound) = bound {
+            let number = as_number(bound, field.octal).ok_or_else(|| {
+                AnsibleSecError::InvalidRuleFormat(format!(
+                    "field '{}' compares with a non-numeric value",
+                    field.field
+                ))
+            })?;
+            operators.push(Operator::Compare {
+                ordering,
+                inclusive,
+                bound: number,
+                octal: field.octal,
+            });
+        }
+    }
+
+    match operators.len() {
+        1 => Ok(operators.remove(0)),
+        0 => Err(AnsibleSecError::InvalidRuleFormat(format!(
+            "field '{}' has no operator",
+            field.field
+        ))),
+        _ => Err(AnsibleSecError::InvalidRuleFormat(format!(
+            "field '{}' has more than one operator",
+            field.field
+        ))),
+    }
+}
+
+fn evaluate(condition: &Compiled, task: &TaskRef) -> bool {
+    match condition {
+        Compiled::All(conditions) => conditions.iter().all(|c| evaluate(c, task)),
+        Compiled::Any(conditions) => conditions.iter().any(|c| evaluate(c, task)),
+        Compiled::Not(condition) => !evaluate(condition, task),
+        Compiled::Field(path, operator) => {
+            // A key set to null is as good as missing
+            let value = lookup(task, path).filter(|v| !v.is_null());
+            match (operator, value) {
+                (Operator::Exists(exists), value) => value.is_some() == *exists,
+                (_, None) => false,
+                (Operator::Equals(expected), Some(value)) => values_equal(value, expected),
+                (Operator::In(expected), Some(value)) => {
+                    expected.iter().any(|e| values_equal(value, e))
+                }
+                (Operator::Regex(pattern), Some(value)) => match value {
+                    Value::Sequence(items) => items
+                        .iter()
+                        .filter_map(scalar_string)
+                        .any(|s| pattern.find(&s).is_some()),
+                    _ => scalar_string(value).is_some_and(|s| pattern.find(&s).is_some()),
+                },
+                (
+                    Operator::Compare {
+                        ordering,
+                        inclusive,
+                        bound,
+                        octal,
+                    },
+                    Some(value),
+                ) => {
+                    as_number(value, *octal).is_some_and(|actual| match actual.partial_cmp(bound) {
+                        Some(Ordering::Equal) => *inclusive,
+                        Some(o) => o == *ordering,
+                        None => false,
+                    })
+                }
+                (Operator::Bits(mask), Some(value)) => {
+                    as_number(value, true).is_some_and(|mode| mode as u32 & mask == *mask)
+                }
+            }
+        }
+    }
+}
+
+/// Resolve a field path against a task.
+fn lookup<'a>(task: &TaskRef<'a>, path: &[String]) -> Option<&'a Value> {
+    let (root, rest) = if path[0] == "args" {
+        (task.task.args.get(path[1].as_str())?, &path[2..])
+    } else {
+        (keyword(task, &path[0])?, &path[1..])
+    };
+
+    rest.iter().try_fold(root, |value, segment| match value {
+        Value::Mapping(obj) => obj.get(segment.as_str()),
+        Value::Sequence(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+        _ => None,
+    })
+}
+
+fn keyword<'a>(task: &TaskRef<'a>, key: &str) -> Option<&'a Value> {
+    if let Some(value) = task.task.raw.get(key) {
+        return Some(value);
+    }
+    if !INHERITED_KEYWORDS.contains(&key) {
+        return None;
+    }
+
+    task.blocks
+        .iter()
+        .rev()
+        .find_map(|block| block.raw.get(key))
+        .or_else(|| task.play.and_then(|play| play.raw.get(key)))
+}
+
+/// Compare the way Ansible would: booleans with its truthy strings, numbers
+/// numerically and everything else as text.
+pub fn values_equal(actual: &Value, expected: &Value) -> bool {
+    match expected {
+        Value::Bool(b) => as_ansible_bool(actual) == Some(*b),
+        Value::Number(_) => match (as_number(actual, false), as_number(expected, false)) {
+            (Some(a), Some(e)) => a == e,
+            _ => false,
+        },
+        Value::Null => actual.is_null(),
+        _ => match (scalar_string(actual), scalar_string(expected)) {
+            (Some(a), Some(e)) => a == e,
+            _ => actual == expected,
+        },
+    }
+}
+
+fn as_number(value: &Value, octal: bool) -> Option<f64> {
+    if octal {
+        // Text such as "0644" or "644" is read as octal; an unquoted mode is
+        // already a number, e.g. 0o644 is 420
+        return match value {
+            Value::Number(n) => n.as_f64(),
+            Value::String(s) => parse_mode(s).map(f64::from),
+            _ => None,
+        };
+    }
+
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+pub fn scalar_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Document;
+    use std::path::Path;
+
+    const PLAYBOOK: &str = r#"
+- hosts: all
+  become: true
+  tasks:
+    - name: Fetch archive
+      ansible.builtin.get_url:
+        url: https://example.com/app.tgz
+        dest: /tmp/app.tgz
+        mode: "0644"
+    - block:
+        - name: Call API
+          uri: url=https://example.com validate_certs=no
+      become_user: deploy
+    - name: Write scratch file
+      copy:
+        content: hi
+        dest: /var/tmp/scratch
+        mode: 0666
+"#;
+
+    fn matching(spec: &str) -> Vec<String> {
+        let spec: TaskSpec = serde_yaml::from_str(spec).unwrap();
+        let matcher = TaskMatcher::compile(&spec).unwrap();
+        let doc = Document::parse(Path::new("site.yml"), PLAYBOOK);
+        doc.tasks()
+            .iter()
+            .filter(|task| matcher.matches(task))
+            .filter_map(|task| task.task.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_modules_and_field_operators() {
+        assert_eq!(
+            matching("modules: [get_url]\nmatch: {field: args.checksum, absent: true}"),
+            ["Fetch archive"]
+        );
+        assert_eq!(
+            matching("modules: [ansible.builtin.uri]\nmatch: {field: args.validate_certs, equals: false}"),
+            ["Call API"]
+        );
+        assert_eq!(
+            matching("match: {field: args.dest, regex: '^/(var/)?tmp/'}"),
+            ["Fetch archive", "Write scratch file"]
+        );
+        assert_eq!(
+            matching("match: {field: args.mode, gt: '0644', octal: true}"),
+            ["Write scratch file"]
+        );
+    }
+
+    #[test]
+    fn test_octal_modes() {
+        let spec: TaskSpec =
+            serde_yaml::from_str("match: {field: args.mode, gt: '0644', octal: true}").unwrap();
+        let matcher = TaskMatcher::compile(&spec).unwrap();
+        let content = "- name: open\n  file: {path: /a, mode: 0o777}\n- name: closed\n  file: {path: /b, mode: 0o600}\n- name: quoted\n  file: {path: /c, mode: '0755'}\n";
+        let doc = Document::parse(Path::new("tasks/main.yml"), content);
+        let matched: Vec<_> = doc
+            .tasks()
+            .iter()
+            .filter(|task| matcher.matches(task))
+            .filter_map(|task| task.task.name.clone())
+            .collect();
+        assert_eq!(matched, ["open", "quoted"]);
+
+        let spec: TaskSpec =
+            serde_yaml::from_str("match: {field: args.mode, bits: '0022'}").unwrap();
+        let matcher = TaskMatcher::compile(&spec).unwrap();
+        let matched: Vec<_> = doc
+            .tasks()
+            .iter()
+            .filter(|task| matcher.matches(task))
+            .filter_map(|task| task.task.name.clone())
+            .collect();
+        assert_eq!(matched, ["open"]);
+    }
+
+    #[test]
+    fn test_inherited_keywords_and_combinators() {
+        assert_eq!(
+            matching(
+                "match: {all: [{field: become, equals: true}, {field: become_user, absent: true}]}"
+            )
+            .len(),
+            2
+        );
+        assert_eq!(
+            matching("match: {not: {field: become_user, in: [deploy, admin]}}"),
+            ["Fetch archive", "Write scratch file"]
+        );
+        assert_eq!(
+            matching("match: {any: [{field: args.url, exists: true}, {field: args.content, equals: hi}]}").len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_invalid_conditions() {
+        let spec: TaskSpec =
+            serde_yaml::from_str("match: {field: args.mode, exists: true, gt: 1}").unwrap();
+        assert!(TaskMatcher::compile(&spec).is_err());
+
+        let spec: TaskSpec = serde_yaml::from_str("match: {field: args.mode, bits: rwx}").unwrap();
+        assert!(TaskMatcher::compile(&spec).is_err());
+
+        let spec: TaskSpec = serde_yaml::from_str("match: {field: args}").unwrap();
+        assert!(TaskMatcher::compile(&spec).is_err());
+
+        let error = |yaml: &str| {
+            serde_yaml::from_str::<TaskSpec>(yaml)
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(error("match: {field: a, bogus: 1}").contains("unknown field `bogus`"));
+        assert!(error("match: {all: [], field: args.mode}")
+            .contains("`all` cannot be combined with other keys, found `field`"));
+        assert!(error("match: {not: {any: [], exists: true}}").contains("found `exists`"));
+    }
+}
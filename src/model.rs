@@ -0,0 +1,909 @@
+use serde::Deserialize;
+use serde_yaml::{Mapping, Value};
+use std::path::{Component, Path, PathBuf};
+
+use crate::modules;
+use crate::yaml::{self, NodePath, PathSegment, Position, Span, SpanIndex};
+
+/// Keys that only make sense on a play, used to tell plays apart from tasks.
+const PLAY_ONLY_KEYS: &[&str] = &[
+    "hosts",
+    "import_playbook",
+    "ansible.builtin.import_playbook",
+    "pre_tasks",
+    "tasks",
+    "post_tasks",
+    "handlers",
+    "roles",
+    "gather_facts",
+    "vars_files",
+];
+
+/// Task keywords, i.e. every key of a task that is not the module itself.
+const TASK_KEYWORDS: &[&str] = &[
+    "action",
+    "any_errors_fatal",
+    "args",
+    "async",
+    "become",
+    "become_exe",
+    "become_flags",
+    "become_method",
+    "become_user",
+    "changed_when",
+    "check_mode",
+    "collections",
+    "connection",
+    "debugger",
+    "delay",
+    "delegate_facts",
+    "delegate_to",
+    "diff",
+    "environment",
+    "failed_when",
+    "ignore_errors",
+    "ignore_unreachable",
+    "listen",
+    "local_action",
+    "loop",
+    "loop_control",
+    "module_defaults",
+    "name",
+    "no_log",
+    "notify",
+    "poll",
+    "port",
+    "register",
+    "remote_user",
+    "retries",
+    "run_once",
+    "tags",
+    "throttle",
+    "timeout",
+    "until",
+    "vars",
+    "when",
+];
+
+/// What kind of Ansible content a file holds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileKind {
+    Playbook,
+    TaskList,
+    VarsFile,
+    RoleMeta,
+    Requirements,
+    Inventory,
+    /// A Jinja2 template, e.g. under a role's `templates/`
+    Template,
+    /// A file copied as-is from a role's `files/`
+    RoleFile,
+    AnsibleConfig,
+    #[default]
+    Unknown,
+}
+
+impl FileKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            FileKind::Playbook => "playbook",
+            FileKind::TaskList => "task_list",
+            FileKind::VarsFile => "vars_file",
+            FileKind::RoleMeta => "role_meta",
+            FileKind::Requirements => "requirements",
+            FileKind::Inventory => "inventory",
+            FileKind::Template => "template",
+            FileKind::RoleFile => "role_file",
+            FileKind::AnsibleConfig => "ansible_config",
+            FileKind::Unknown => "unknown",
+        }
+    }
+
+    /// Kinds that are not Ansible YAML, so only secrets detection applies.
+    pub fn is_text(&self) -> bool {
+        matches!(
+            self,
+            FileKind::Template | FileKind::RoleFile | FileKind::AnsibleConfig
+        )
+    }
+}
+
+/// Keywords that can be set on plays, blocks and tasks.
+#[derive(Debug, Clone, Default)]
+pub struct Keywords {
+    pub become_enabled: Option<bool>,
+    pub no_log: Option<bool>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Role {
+    pub name: String,
+    pub raw: Value,
+}
+
+#[derive(Debug, Clone)]
+pub struct Task {
+    pub name: Option<String>,
+    /// Module name as written in the task (or in its `action:`/`local_action:`)
+    pub module: Option<String>,
+    /// Canonical FQCN of the module, used for rule matching
+    pub fqcn: Option<String>,
+    /// Normalized module arguments: inline `k=v` pairs parsed, free-form
+    /// text under `_raw_params` and the `args:` keyword merged in
+    pub args: Mapping,
+    pub keywords: Keywords,
+    pub vars: Option<Mapping>,
+    pub raw: Mapping,
+    /// Location of the task in the document
+    pub path: NodePath,
+    /// Key the module was found under (the module name, `action` or `local_action`)
+    pub module_key: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub keywords: Keywords,
+    pub vars: Option<Mapping>,
+    pub block: Vec<TaskItem>,
+    pub rescue: Vec<TaskItem>,
+    pub always: Vec<TaskItem>,
+    pub raw: Mapping,
+    pub path: NodePath,
+}
+
+#[derive(Debug, Clone)]
+pub enum TaskItem {
+    Task(Task),
+    Block(Block),
+}
+
+#[derive(Debug, Clone)]
+pub struct Play {
+    pub name: Option<String>,
+    pub hosts: Option<Value>,
+    pub import_playbook: Option<String>,
+    pub keywords: Keywords,
+    pub vars: Option<Mapping>,
+    pub roles: Vec<Role>,
+    pub pre_tasks: Vec<TaskItem>,
+    pub tasks: Vec<TaskItem>,
+    pub post_tasks: Vec<TaskItem>,
+    pub handlers: Vec<TaskItem>,
+    pub raw: Mapping,
+    pub path: NodePath,
+}
+
+/// A parsed Ansible file, shared by all analyzers.
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub path: PathBuf,
+    pub content: String,
+    pub kind: FileKind,
+    /// Parsed YAML, or `None` if the file is not valid YAML
+    pub root: Option<Value>,
+    pub plays: Vec<Play>,
+    /// Tasks of a bare task list (role tasks/handlers files)
+    pub task_file: Vec<TaskItem>,
+    /// Source positions of every YAML node
+    pub spans: SpanIndex,
+    /// The documents after the first of a `---` separated stream, each
+    /// with the whole file as `content` and spans into it
+    pub following: Vec<Document>,
+    /// Documents of the stream that are not valid YAML and were left out
+    pub parse_errors: Vec<ParseError>,
+}
+
+/// A document of a YAML stream that failed to parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub position: Position,
+    pub message: String,
+}
+
+impl From<serde_yaml::Error> for ParseError {
+    fn from(e: serde_yaml::Error) -> Self {
+        let position = e
+            .location()
+            .map(|location| Position {
+                line: location.line(),
+                column: location.column(),
+            })
+            .unwrap_or(FILE_START.start);
+        Self {
+            position,
+            message: e.to_string(),
+        }
+    }
+}
+
+/// Fallback position for nodes whose span is unknown.
+const FILE_START: Span = Span {
+    start: Position { line: 1, column: 1 },
+    end: Position { line: 1, column: 1 },
+};
+
+/// A task reached by walking a document, along with its enclosing scopes.
+#[derive(Debug, Clone)]
+pub struct TaskRef<'a> {
+    pub task: &'a Task,
+    pub play: Option<&'a Play>,
+    /// Enclosing blocks, outermost first
+    pub blocks: Vec<&'a Block>,
+}
+
+impl Document {
+    /// Parse a file into the model. Content that is not YAML yields a
+    /// document of kind `Unknown` so text-based checks can still run.
+    pub fn parse(path: &Path, content: &str) -> Self {
+        Self::parse_as(path, content, FileKind::Unknown)
+    }
+
+    /// Parse a file whose kind discovery already knows from its place in
+    /// the project layout; `Unknown` classifies it by content. Text kinds
+    /// are not parsed as YAML. A stream of several `---` separated documents
+    /// yields the first, with the others in `following`.
+    pub fn parse_as(path: &Path, content: &str, kind: FileKind) -> Self {
+        if kind.is_text() {
+            return Self::from_root(path, content, kind, None, SpanIndex::build(content));
+        }
+
+        // Documents that fail to parse are left out and reported; the rest
+        // are still analyzed
+        let mut roots = Vec::new();
+        let mut parse_errors = Vec::new();
+        for (idx, document) in serde_yaml::Deserializer::from_str(content).enumerate() {
+            match Value::deserialize(document) {
+                Ok(root) => roots.push((idx, root)),
+                Err(e) => {
+                    let error = ParseError::from(e);
+                    // A syntax error ends the stream: it is reported again
+                    // for every further document
+                    if parse_errors.last() == Some(&error) {
+                        break;
+                    }
+                    parse_errors.push(error);
+                }
+            }
+        }
+
+        let mut spans = SpanIndex::build_stream(content);
+        let mut documents = roots.into_iter().map(|(idx, root)| {
+            let spans = spans.get_mut(idx).map(std::mem::take).unwrap_or_default();
+            Self::from_root(path, content, kind, Some(root), spans)
+        });
+        let mut doc = documents
+            .next()
+            .unwrap_or_else(|| Self::from_root(path, content, kind, None, SpanIndex::default()));
+        doc.following = documents.collect();
+        doc.parse_errors = parse_errors;
+        doc
+    }
+
+    /// A document for one parsed YAML document of a file.
+    fn from_root(
+        path: &Path,
+        content: &str,
+        kind: FileKind,
+        root: Option<Value>,
+        spans: SpanIndex,
+    ) -> Self {
+        let kind = match (kind, &root) {
+            (FileKind::Unknown, Some(root)) => classify(path, root),
+            (kind, _) => kind,
+        };
+
+        let mut plays = Vec::new();
+        let mut task_file = Vec::new();
+
+        match (kind, &root) {
+            (FileKind::Playbook, Some(Value::Sequence(items))) => {
+                plays = items
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(idx, v)| v.as_mapping().map(|obj| (idx, obj)))
+                    .filter(|(_, obj)| is_play(obj))
+                    .map(|(idx, obj)| Play::from_mapping(obj, vec![PathSegment::Index(idx)]))
+                    .collect();
+            }
+            (FileKind::Playbook, Some(Value::Mapping(obj))) => {
+                plays.push(Play::from_mapping(obj, Vec::new()));
+            }
+            (FileKind::TaskList, Some(Value::Sequence(items))) => {
+                task_file = parse_task_list(items, &[]);
+            }
+            _ => {}
+        }
+
+        Self {
+            path: path.to_path_buf(),
+            content: content.to_string(),
+            kind,
+            root,
+            plays,
+            task_file,
+            spans,
+            following: Vec::new(),
+            parse_errors: Vec::new(),
+        }
+    }
+
+    /// Every document of the file: this one, then those in `following`.
+    pub fn documents(&self) -> impl Iterator<Item = &Document> {
+        std::iter::once(self).chain(&self.following)
+    }
+
+    /// Whether policy rules and the linter apply: YAML content that is not
+    /// a template or other text file. INI inventories do not count.
+    pub fn is_yaml(&self) -> bool {
+        if self.kind.is_text() {
+            return false;
+        }
+        // Keep invalid or empty `.yml` files so the linter reports them
+        self.documents()
+            .any(|doc| matches!(doc.root, Some(Value::Mapping(_) | Value::Sequence(_))))
+            || self
+                .path
+                .extension()
+                .is_some_and(|ext| ext == "yml" || ext == "yaml")
+    }
+
+    /// Walk every task in execution order: all task sections of every play,
+    /// bare task files and nested `block`/`rescue`/`always` sections. Blocks
+    /// themselves are not yielded, only the tasks inside them.
+    pub fn tasks(&self) -> Vec<TaskRef<'_>> {
+        let mut out = Vec::new();
+
+        for play in &self.plays {
+            for items in play.sections() {
+                walk_items(items, Some(play), &mut Vec::new(), &mut out);
+            }
+        }
+        walk_items(&self.task_file, None, &mut Vec::new(), &mut out);
+
+        out
+    }
+
+    /// Every `vars` mapping in the document along with its path: the whole
+    /// file for vars files, then each play, block and task.
+    pub fn vars_scopes(&self) -> Vec<(NodePath, &Mapping)> {
+        let mut scopes = Vec::new();
+
+        if let Some(obj) = self.root.as_ref().and_then(|r| r.as_mapping()) {
+            if self.kind == FileKind::VarsFile {
+                scopes.push((Vec::new(), obj));
+            }
+            // A play mapping's vars are picked up with the play below
+            if self.kind != FileKind::Playbook {
+                if let Some(vars) = obj.get("vars").and_then(|v| v.as_mapping()) {
+                    scopes.push((yaml::key(&[], "vars"), vars));
+                }
+            }
+        }
+
+        for play in &self.plays {
+            if let Some(ref vars) = play.vars {
+                scopes.push((yaml::key(&play.path, "vars"), vars));
+            }
+            for items in play.sections() {
+                collect_vars(items, &mut scopes);
+            }
+        }
+        collect_vars(&self.task_file, &mut scopes);
+
+        scopes
+    }
+
+    /// Span of the node at `path`, or the start of the file if it is unknown.
+    pub fn value_span(&self, path: &[PathSegment]) -> Span {
+        self.spans.value(path).unwrap_or(FILE_START)
+    }
+
+    /// Span of the key whose value is at `path`.
+    pub fn key_span(&self, path: &[PathSegment]) -> Span {
+        self.spans.key(path).unwrap_or(FILE_START)
+    }
+
+    /// Span of a play's `name:` key, or of the whole play if unnamed.
+    pub fn play_span(&self, play: &Play) -> Span {
+        if play.raw.contains_key("name") {
+            self.key_span(&yaml::key(&play.path, "name"))
+        } else {
+            self.value_span(&play.path)
+        }
+    }
+
+    /// Span of a task's module key, e.g. `shell:` or `action:`.
+    pub fn module_span(&self, task: &Task) -> Span {
+        match task.module_key {
+            Some(ref module_key) => self.key_span(&yaml::key(&task.path, module_key)),
+            None => self.value_span(&task.path),
+        }
+    }
+
+    /// Span of a task's `name:` key, falling back to its module.
+    pub fn task_span(&self, task: &Task) -> Span {
+        if task.raw.contains_key("name") {
+            self.key_span(&yaml::key(&task.path, "name"))
+        } else {
+            self.module_span(task)
+        }
+    }
+
+    /// Span of a module argument from its key through its value, wherever
+    /// it was written: under the module key, under `args:` or as `key=value`
+    /// in a free-form string.
+    pub fn arg_span(&self, task: &Task, arg: &str) -> Span {
+        let Some(ref module_key) = task.module_key else {
+            return self.value_span(&task.path);
+        };
+        let module_path = yaml::key(&task.path, module_key);
+
+        match task.raw.get(module_key.as_str()) {
+            Some(Value::Mapping(obj)) if obj.contains_key(arg) => {
+                return self.entry_span(&yaml::key(&module_path, arg));
+            }
+            Some(Value::String(_)) => {
+                if let Some(span) = self.find_inline_arg(&module_path, arg) {
+                    return span;
+                }
+            }
+            _ => {}
+        }
+
+        let args = task.raw.get("args").and_then(|v| v.as_mapping());
+        if args.is_some_and(|obj| obj.contains_key(arg)) {
+            return self.entry_span(&yaml::key(&yaml::key(&task.path, "args"), arg));
+        }
+
+        self.module_span(task)
+    }
+
+    /// Span of a mapping entry from its key through the value at `path`.
+    fn entry_span(&self, path: &[PathSegment]) -> Span {
+        Span {
+            start: self.key_span(path).start,
+            end: self.value_span(path).end,
+        }
+    }
+
+    /// Location of the value of module argument `arg`: the value rather
+    /// than the key of an argument in a mapping, otherwise as `arg_span`.
+    pub fn arg_value_span(&self, task: &Task, arg: &str) -> Span {
+        let has_arg = |value: Option<&Value>| {
+            value
+                .and_then(Value::as_mapping)
+                .is_some_and(|obj| obj.contains_key(arg))
+        };
+        if let Some(ref module_key) = task.module_key {
+            if has_arg(task.raw.get(module_key.as_str())) {
+                return self.value_span(&yaml::key(&yaml::key(&task.path, module_key), arg));
+            }
+        }
+        if has_arg(task.raw.get("args")) {
+            return self.value_span(&yaml::key(&yaml::key(&task.path, "args"), arg));
+        }
+        self.arg_span(task, arg)
+    }
+
+    /// The source text `span` covers.
+    pub fn source(&self, span: Span) -> String {
+        let first = span.start.line.max(1);
+        let mut text = String::new();
+        for (idx, line) in self.content.lines().enumerate().skip(first - 1) {
+            let line_no = idx + 1;
+            if line_no > span.end.line {
+                break;
+            }
+            if line_no > first {
+                text.push('\n');
+            }
+            let from = if line_no == first {
+                span.start.column.saturating_sub(1)
+            } else {
+                0
+            };
+            let to = if line_no == span.end.line {
+                span.end.column.saturating_sub(1)
+            } else {
+                usize::MAX
+            };
+            text.extend(line.chars().skip(from).take(to.saturating_sub(from)));
+        }
+        text
+    }
+
+    /// Locate `arg=` inside the free-form string at `path`.
+    fn find_inline_arg(&self, path: &[PathSegment], arg: &str) -> Option<Span> {
+        let value = self.spans.value(path)?;
+        let needle = format!("{}=", arg);
+
+        for (line_num, line) in self
+            .content
+            .lines()
+            .enumerate()
+            .skip(value.start.line - 1)
+            .take(value.end.line - value.start.line + 1)
+        {
+            let line_num = line_num + 1;
+            let chars: Vec<char> = line.chars().collect();
+            let from = if line_num == value.start.line {
+                value.start.column - 1
+            } else {
+                0
+            };
+            let haystack: String = chars.iter().skip(from).collect();
+
+            let Some(byte_pos) = haystack.find(&needle) else {
+                continue;
+            };
+            // Only match whole argument names, not suffixes of other names
+            let before = haystack[..byte_pos].chars().last();
+            if before.is_some_and(|c| !c.is_whitespace() && c != '"' && c != '\'') {
+                continue;
+            }
+
+            let column = from + haystack[..byte_pos].chars().count() + 1;
+            let length = haystack[byte_pos..]
+                .chars()
+                .take_while(|c| !c.is_whitespace())
+                .count();
+            return Some(Span::on_line(line_num, column, column + length));
+        }
+
+        None
+    }
+}
+
+impl Play {
+    fn from_mapping(obj: &Mapping, path: NodePath) -> Self {
+        let roles = obj
+            .get("roles")
+            .and_then(|v| v.as_sequence())
+            .map(|items| items.iter().filter_map(Role::from_value).collect())
+            .unwrap_or_default();
+
+        let section = |key: &str| {
+            obj.get(key)
+                .and_then(|v| v.as_sequence())
+                .map(|items| parse_task_list(items, &yaml::key(&path, key)))
+                .unwrap_or_default()
+        };
+
+        Self {
+            name: get_string(obj, "name"),
+            hosts: obj.get("hosts").cloned(),
+            import_playbook: get_string(obj, "import_playbook")
+                .or_else(|| get_string(obj, "ansible.builtin.import_playbook")),
+            keywords: Keywords::from_mapping(obj),
+            vars: obj.get("vars").and_then(|v| v.as_mapping()).cloned(),
+            roles,
+            pre_tasks: section("pre_tasks"),
+            tasks: section("tasks"),
+            post_tasks: section("post_tasks"),
+            handlers: section("handlers"),
+            raw: obj.clone(),
+            path,
+        }
+    }
+
+    /// Task sections in execution order.
+    pub fn sections(&self) -> [&[TaskItem]; 4] {
+        [
+            &self.pre_tasks,
+            &self.tasks,
+            &self.post_tasks,
+            &self.handlers,
+        ]
+    }
+}
+
+impl Role {
+    /// A role from a `roles:` or `dependencies:` entry: a name, or a
+    /// mapping with `role:` or `name:`.
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let name = match value {
+            Value::String(s) => Some(s.clone()),
+            Value::Mapping(obj) => get_string(obj, "role").or_else(|| get_string(obj, "name")),
+            _ => None,
+        }?;
+
+        Some(Self {
+            name,
+            raw: value.clone(),
+        })
+    }
+
+    /// Parameters passed to the role: the keys of a mapping entry other
+    /// than the role name, `tags` and `when`.
+    pub fn params(&self) -> Mapping {
+        let mut params = self.raw.as_mapping().cloned().unwrap_or_default();
+        for key in ["role", "name", "tags", "when"] {
+            params.remove(key);
+        }
+        params
+    }
+}
+
+impl TaskItem {
+    fn from_mapping(obj: &Mapping, path: NodePath) -> Self {
+        if obj.contains_key("block") {
+            let section = |key: &str| {
+                obj.get(key)
+                    .and_then(|v| v.as_sequence())
+                    .map(|items| parse_task_list(items, &yaml::key(&path, key)))
+                    .unwrap_or_default()
+            };
+
+            TaskItem::Block(Block {
+                keywords: Keywords::from_mapping(obj),
+                vars: obj.get("vars").and_then(|v| v.as_mapping()).cloned(),
+                block: section("block"),
+                rescue: section("rescue"),
+                always: section("always"),
+                raw: obj.clone(),
+                path,
+            })
+        } else {
+            TaskItem::Task(Task::from_mapping(obj, path))
+        }
+    }
+}
+
+impl Task {
+    fn from_mapping(obj: &Mapping, path: NodePath) -> Self {
+        let keywords = Keywords::from_mapping(obj);
+
+        let module_key = obj
+            .keys()
+            .filter_map(|k| k.as_str())
+            .find(|k| !is_task_keyword(k));
+
+        let (module, args, module_key) = if let Some(key) = module_key {
+            let args = obj.get(key).cloned().unwrap_or(Value::Null);
+            (Some(key.to_string()), args, Some(key))
+        } else if let Some((module, args)) = obj.get("action").and_then(modules::parse_action) {
+            (Some(module), args, Some("action"))
+        } else if let Some((module, args)) = obj.get("local_action").and_then(modules::parse_action)
+        {
+            (Some(module), args, Some("local_action"))
+        } else {
+            (None, Value::Null, None)
+        };
+
+        let fqcn = module.as_deref().map(modules::canonical_name);
+        let args =
+            modules::normalize_args(fqcn.as_deref().unwrap_or_default(), &args, obj.get("args"));
+
+        Self {
+            name: get_string(obj, "name"),
+            fqcn,
+            module,
+            args,
+            keywords,
+            vars: obj.get("vars").and_then(|v| v.as_mapping()).cloned(),
+            raw: obj.clone(),
+            path,
+            module_key: module_key.map(str::to_string),
+        }
+    }
+}
+
+impl Keywords {
+    fn from_mapping(obj: &Mapping) -> Self {
+        Self {
+            become_enabled: obj.get("become").and_then(as_ansible_bool),
+            no_log: obj.get("no_log").and_then(as_ansible_bool),
+        }
+    }
+}
+
+impl<'a> TaskRef<'a> {
+    /// Scopes from the innermost (the task) out to the play.
+    fn scopes(&self) -> impl Iterator<Item = &'a Keywords> + '_ {
+        std::iter::once(&self.task.keywords)
+            .chain(self.blocks.iter().rev().map(|b| &b.keywords))
+            .chain(self.play.map(|p| &p.keywords))
+    }
+
+    /// Effective `become`, inherited from enclosing blocks and the play.
+    pub fn become_enabled(&self) -> bool {
+        self.scopes()
+            .find_map(|k| k.become_enabled)
+            .unwrap_or(false)
+    }
+
+    /// Effective `no_log`, inherited from enclosing blocks and the play.
+    pub fn no_log(&self) -> bool {
+        self.scopes().find_map(|k| k.no_log).unwrap_or(false)
+    }
+}
+
+/// Interpret a value the way Ansible coerces booleans (`yes`, `on`, `1`...).
+pub fn as_ansible_bool(value: &Value) -> Option<bool> {
+    match value {
+        Value::Bool(b) => Some(*b),
+        Value::Number(n) => n.as_i64().map(|n| n != 0),
+        Value::String(s) => match s.to_lowercase().as_str() {
+            "yes" | "true" | "on" | "y" | "1" => Some(true),
+            "no" | "false" | "off" | "n" | "0" => Some(false),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The path relative to the working directory with `/` separators; a path
+/// outside it is kept as given.
+pub fn normalize_path(path: &Path) -> String {
+    relative_path(path, Path::new(".")).unwrap_or_else(|| slash_path(path))
+}
+
+/// The path relative to `root` with `/` separators, as matched by rule
+/// `paths` globs; `None` if the path is outside `root`. Relative paths on
+/// either side are taken from the working directory.
+pub fn relative_path(path: &Path, root: &Path) -> Option<String> {
+    absolute(path)
+        .strip_prefix(absolute(root))
+        .ok()
+        .map(slash_path)
+}
+
+/// The absolute path with `.` and `..` resolved lexically.
+pub fn absolute(path: &Path) -> PathBuf {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let mut absolute = PathBuf::new();
+    for component in cwd.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                absolute.pop();
+            }
+            component => absolute.push(component),
+        }
+    }
+    absolute
+}
+
+fn slash_path(path: &Path) -> String {
+    let normalized = path.to_string_lossy().replace('\\', "/");
+    normalized
+        .strip_prefix("./")
+        .unwrap_or(&normalized)
+        .to_string()
+}
+
+fn is_task_keyword(key: &str) -> bool {
+    TASK_KEYWORDS.contains(&key) || key.starts_with("with_")
+}
+
+fn is_play(obj: &Mapping) -> bool {
+    PLAY_ONLY_KEYS.iter().any(|key| obj.contains_key(*key))
+}
+
+/// Classify a file from its path and parsed content.
+fn classify(path: &Path, root: &Value) -> FileKind {
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let parent = path
+        .parent()
+        .and_then(|p| p.file_name())
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    if file_name.starts_with("requirements.") {
+        return FileKind::Requirements;
+    }
+
+    match root {
+        Value::Sequence(items) => {
+            let mappings: Vec<&Mapping> = items.iter().filter_map(|v| v.as_mapping()).collect();
+            if mappings.iter().any(|obj| is_play(obj)) {
+                FileKind::Playbook
+            } else if !mappings.is_empty() {
+                FileKind::TaskList
+            } else {
+                FileKind::Unknown
+            }
+        }
+        Value::Mapping(obj) => {
+            if parent == "meta" || obj.contains_key("galaxy_info") {
+                FileKind::RoleMeta
+            } else if is_play(obj) {
+                FileKind::Playbook
+            } else if is_inventory(obj) {
+                FileKind::Inventory
+            } else {
+                FileKind::VarsFile
+            }
+        }
+        _ => FileKind::Unknown,
+    }
+}
+
+/// YAML inventories are mappings of groups, each with `hosts`, `children` or `vars`.
+fn is_inventory(obj: &Mapping) -> bool {
+    if obj.contains_key("all") {
+        return true;
+    }
+
+    !obj.is_empty()
+        && obj.values().all(|group| {
+            group.as_mapping().is_some_and(|g| {
+                !g.is_empty()
+                    && g.keys().all(|k| {
+                        matches!(k.as_str(), Some("hosts") | Some("children") | Some("vars"))
+                    })
+            })
+        })
+}
+
+fn parse_task_list(items: &[Value], path: &[PathSegment]) -> Vec<TaskItem> {
+    items
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, v)| {
+            v.as_mapping()
+                .map(|obj| TaskItem::from_mapping(obj, yaml::child(path, PathSegment::Index(idx))))
+        })
+        .collect()
+}
+
+fn walk_items<'a>(
+    items: &'a [TaskItem],
+    play: Option<&'a Play>,
+    blocks: &mut Vec<&'a Block>,
+    out: &mut Vec<TaskRef<'a>>,
+) {
+    for item in items {
+        match item {
+            TaskItem::Task(task) => out.push(TaskRef {
+                task,
+                play,
+                blocks: blocks.clone(),
+            }),
+            TaskItem::Block(block) => {
+                blocks.push(block);
+                for nested in [&block.block, &block.rescue, &block.always] {
+                    walk_items(nested, play, blocks, out);
+                }
+                blocks.pop();
+            }
+        }
+    }
+}
+
+fn collect_vars<'a>(items: &'a [TaskItem], out: &mut Vec<(NodePath, &'a Mapping)>) {
+    for item in items {
+        match item {
+            TaskItem::Task(task) => {
+                if let Some(ref vars) = task.vars {
+                    out.push((yaml::key(&task.path, "vars"), vars));
+                }
+            }
+            TaskItem::Block(block) => {
+                if let Some(ref vars) = block.vars {
+                    out.push((yaml::key(&block.path, "vars"), vars));
+                }
+                for nested in [&block.block, &block.rescue, &block.always] {
+                    collect_vars(nested, out);
+                }
+            }
+        }
+    }
+}
+
+fn get_string(obj: &Mapping, key: &str) -> Option<String> {
+    obj.get(key).and_then(scalar_to_string)
+}
+
+fn scalar_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
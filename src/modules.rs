@@ -0,0 +1,482 @@
+use serde_yaml::{Mapping, Value};
+
+/// Modules shipped with ansible-core, addressable by short name.
+const BUILTIN_MODULES: &[&str] = &[
+    "add_host",
+    "apt",
+    "apt_key",
+    "apt_repository",
+    "assemble",
+    "assert",
+    "async_status",
+    "blockinfile",
+    "command",
+    "copy",
+    "cron",
+    "deb822_repository",
+    "debconf",
+    "debug",
+    "dnf",
+    "dnf5",
+    "dpkg_selections",
+    "expect",
+    "fail",
+    "fetch",
+    "file",
+    "find",
+    "gather_facts",
+    "get_url",
+    "getent",
+    "git",
+    "group",
+    "group_by",
+    "hostname",
+    "import_playbook",
+    "import_role",
+    "import_tasks",
+    "include",
+    "include_role",
+    "include_tasks",
+    "include_vars",
+    "iptables",
+    "known_hosts",
+    "lineinfile",
+    "meta",
+    "mount_facts",
+    "package",
+    "package_facts",
+    "pause",
+    "ping",
+    "pip",
+    "raw",
+    "reboot",
+    "replace",
+    "rpm_key",
+    "script",
+    "service",
+    "service_facts",
+    "set_fact",
+    "set_stats",
+    "setup",
+    "shell",
+    "slurp",
+    "stat",
+    "subversion",
+    "systemd",
+    "systemd_service",
+    "sysvinit",
+    "tempfile",
+    "template",
+    "unarchive",
+    "uri",
+    "user",
+    "validate_argument_spec",
+    "wait_for",
+    "wait_for_connection",
+    "yum",
+    "yum_repository",
+];
+
+/// Short names and old FQCNs that Ansible redirects to another collection.
+const REDIRECTS: &[(&str, &str)] = &[
+    ("authorized_key", "ansible.posix.authorized_key"),
+    ("docker_container", "community.docker.docker_container"),
+    ("docker_image", "community.docker.docker_image"),
+    ("docker_network", "community.docker.docker_network"),
+    ("ec2_group", "amazon.aws.ec2_security_group"),
+    ("ec2_instance", "amazon.aws.ec2_instance"),
+    ("firewalld", "ansible.posix.firewalld"),
+    ("gem", "community.general.gem"),
+    ("helm", "kubernetes.core.helm"),
+    ("htpasswd", "community.general.htpasswd"),
+    ("k8s", "kubernetes.core.k8s"),
+    ("mount", "ansible.posix.mount"),
+    ("mysql_db", "community.mysql.mysql_db"),
+    ("mysql_user", "community.mysql.mysql_user"),
+    ("npm", "community.general.npm"),
+    ("postgresql_db", "community.postgresql.postgresql_db"),
+    ("postgresql_user", "community.postgresql.postgresql_user"),
+    ("s3_bucket", "amazon.aws.s3_bucket"),
+    ("seboolean", "ansible.posix.seboolean"),
+    ("selinux", "ansible.posix.selinux"),
+    ("synchronize", "ansible.posix.synchronize"),
+    ("sysctl", "ansible.posix.sysctl"),
+    ("ufw", "community.general.ufw"),
+    ("win_command", "ansible.windows.win_command"),
+    ("win_copy", "ansible.windows.win_copy"),
+    ("win_shell", "ansible.windows.win_shell"),
+    (
+        "community.general.authorized_key",
+        "ansible.posix.authorized_key",
+    ),
+    (
+        "community.general.docker_container",
+        "community.docker.docker_container",
+    ),
+    (
+        "community.general.docker_image",
+        "community.docker.docker_image",
+    ),
+    ("community.general.firewalld", "ansible.posix.firewalld"),
+    ("community.general.k8s", "kubernetes.core.k8s"),
+    ("community.general.mysql_db", "community.mysql.mysql_db"),
+    ("community.general.mysql_user", "community.mysql.mysql_user"),
+    (
+        "community.general.postgresql_db",
+        "community.postgresql.postgresql_db",
+    ),
+    (
+        "community.general.postgresql_user",
+        "community.postgresql.postgresql_user",
+    ),
+    ("community.kubernetes.helm", "kubernetes.core.helm"),
+    ("community.kubernetes.k8s", "kubernetes.core.k8s"),
+];
+
+/// Resolve a module name to its canonical FQCN.
+///
+/// Short names of ansible-core modules and `ansible.legacy.*` names map to
+/// `ansible.builtin.*`, and known redirects are followed. Unknown names are
+/// returned unchanged so custom modules still match literally.
+pub fn canonical_name(name: &str) -> String {
+    let name = name.trim();
+    let short = name.strip_prefix("ansible.legacy.").unwrap_or(name);
+
+    if let Some((_, target)) = REDIRECTS.iter().find(|(from, _)| *from == short) {
+        return target.to_string();
+    }
+
+    if !short.contains('.') && BUILTIN_MODULES.contains(&short) {
+        return format!("ansible.builtin.{}", short);
+    }
+
+    short.to_string()
+}
+
+/// Returns true if `name` (short, legacy or FQCN) refers to the module `fqcn`.
+pub fn is_module(fqcn: &str, name: &str) -> bool {
+    canonical_name(fqcn) == canonical_name(name)
+}
+
+/// Returns true if `fqcn` refers to any module in `names`.
+pub fn is_any_module(fqcn: &str, names: &[&str]) -> bool {
+    names.iter().any(|name| is_module(fqcn, name))
+}
+
+/// Split an `action:`/`local_action:` value into the module name and its
+/// arguments.
+///
+/// Supports the string form (`shell echo hi`, `module=shell echo hi`) and
+/// the mapping form (`{module: shell, cmd: ...}`).
+pub fn parse_action(value: &Value) -> Option<(String, Value)> {
+    match value {
+        Value::String(s) => {
+            let s = s.trim();
+            let (first, rest) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+            let rest = rest.trim();
+
+            if let Some(module) = first.strip_prefix("module=") {
+                Some((module.to_string(), Value::String(rest.to_string())))
+            } else if !first.is_empty() && !first.contains('=') {
+                Some((first.to_string(), Value::String(rest.to_string())))
+            } else {
+                // `k=v ... module=shell` with the module given later on
+                let module = s
+                    .split_whitespace()
+                    .find_map(|token| token.strip_prefix("module="))?;
+                let args: Vec<&str> = s
+                    .split_whitespace()
+                    .filter(|token| !token.starts_with("module="))
+                    .collect();
+                Some((module.to_string(), Value::String(args.join(" "))))
+            }
+        }
+        Value::Mapping(obj) => {
+            let module = obj.get("module").and_then(|m| m.as_str())?;
+            let args: Mapping = obj
+                .iter()
+                .filter(|(k, _)| k.as_str() != Some("module"))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect();
+            Some((module.to_string(), Value::Mapping(args)))
+        }
+        _ => None,
+    }
+}
+
+/// Modules whose free-form text is a command line rather than `k=v` pairs.
+const RAW_PARAM_MODULES: &[&str] = &[
+    "ansible.builtin.command",
+    "ansible.builtin.shell",
+    "ansible.builtin.raw",
+    "ansible.builtin.script",
+    "ansible.windows.win_command",
+    "ansible.windows.win_shell",
+];
+
+/// The only `k=v` options extracted from the free-form text of raw modules.
+const RAW_PARAM_KEYS: &[&str] = &[
+    "argv",
+    "chdir",
+    "creates",
+    "executable",
+    "removes",
+    "stdin",
+    "stdin_add_newline",
+    "strip_empty_ends",
+    "warn",
+];
+
+/// Key under which Ansible stores free-form text that is not `k=v`.
+pub const RAW_PARAMS: &str = "_raw_params";
+
+/// Build the normalized argument map of a task.
+///
+/// `value` is the module value as written (mapping, inline `k=v` string or
+/// free-form text) and `extra` the task's `args:` keyword. Inline arguments
+/// take precedence over `args:`, as in Ansible.
+pub fn normalize_args(fqcn: &str, value: &Value, extra: Option<&Value>) -> Mapping {
+    let check_raw = RAW_PARAM_MODULES.contains(&fqcn);
+
+    let mut args = match value {
+        Value::Mapping(obj) => obj.clone(),
+        Value::String(s) => parse_kv(s, check_raw),
+        Value::Number(n) => raw_params(n.to_string()),
+        Value::Bool(b) => raw_params(b.to_string()),
+        _ => Mapping::new(),
+    };
+
+    let extra = match extra {
+        Some(Value::Mapping(obj)) => obj.clone(),
+        Some(Value::String(s)) => parse_kv(s, false),
+        _ => Mapping::new(),
+    };
+    for (key, value) in extra {
+        if !args.contains_key(&key) {
+            args.insert(key, value);
+        }
+    }
+
+    args
+}
+
+/// Parse Ansible's inline `k=v` argument syntax.
+///
+/// Values may be single or double quoted and Jinja expressions may contain
+/// spaces. Text that is not a `k=v` pair is joined into `_raw_params`; with
+/// `check_raw` set only the options raw modules accept are extracted.
+pub fn parse_kv(input: &str, check_raw: bool) -> Mapping {
+    let mut args = Mapping::new();
+    let mut raw = Vec::new();
+
+    for token in split_args(input) {
+        match split_kv(&token) {
+            Some((key, value)) if !check_raw || RAW_PARAM_KEYS.contains(&key.as_str()) => {
+                args.insert(Value::String(key), Value::String(value));
+            }
+            _ => raw.push(token),
+        }
+    }
+
+    if !raw.is_empty() {
+        args.insert(
+            Value::String(RAW_PARAMS.to_string()),
+            Value::String(raw.join(" ")),
+        );
+    }
+
+    args
+}
+
+fn raw_params(text: String) -> Mapping {
+    let mut args = Mapping::new();
+    args.insert(Value::String(RAW_PARAMS.to_string()), Value::String(text));
+    args
+}
+
+/// Split on whitespace that is outside quotes and Jinja blocks.
+fn split_args(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut jinja_depth = 0usize;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if quote.is_some() => {
+                current.push(c);
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            '"' | '\'' if jinja_depth == 0 => {
+                if quote == Some(c) {
+                    quote = None;
+                } else if quote.is_none() {
+                    quote = Some(c);
+                }
+                current.push(c);
+            }
+            '{' if matches!(chars.peek(), Some('{') | Some('%')) => {
+                jinja_depth += 1;
+                current.push(c);
+                current.push(chars.next().unwrap_or_default());
+            }
+            '}' | '%' if jinja_depth > 0 && chars.peek() == Some(&'}') => {
+                jinja_depth -= 1;
+                current.push(c);
+                current.push(chars.next().unwrap_or_default());
+            }
+            c if c.is_whitespace() && quote.is_none() && jinja_depth == 0 => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+/// Split a token into a key and unquoted value if it is a `k=v` pair.
+fn split_kv(token: &str) -> Option<(String, String)> {
+    let (key, value) = token.split_once('=')?;
+
+    let valid_key = key
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid_key {
+        return None;
+    }
+
+    Some((key.to_string(), unquote(value)))
+}
+
+fn unquote(value: &str) -> String {
+    let bytes = value.as_bytes();
+    if bytes.len() >= 2
+        && (bytes[0] == b'"' || bytes[0] == b'\'')
+        && bytes[bytes.len() - 1] == bytes[0]
+    {
+        let inner = &value[1..value.len() - 1];
+        let quote = bytes[0] as char;
+        let mut out = String::with_capacity(inner.len());
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            if c == '\\' {
+                match chars.next() {
+                    Some(next) if next == quote || next == '\\' => out.push(next),
+                    Some(next) => {
+                        out.push(c);
+                        out.push(next);
+                    }
+                    None => out.push(c),
+                }
+            } else {
+                out.push(c);
+            }
+        }
+        out
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical_name() {
+        assert_eq!(canonical_name("shell"), "ansible.builtin.shell");
+        assert_eq!(
+            canonical_name("ansible.legacy.shell"),
+            "ansible.builtin.shell"
+        );
+        assert_eq!(
+            canonical_name("ansible.builtin.shell"),
+            "ansible.builtin.shell"
+        );
+        assert_eq!(canonical_name("mysql_user"), "community.mysql.mysql_user");
+        assert_eq!(
+            canonical_name("community.general.docker_container"),
+            "community.docker.docker_container"
+        );
+        assert_eq!(canonical_name("my_custom_module"), "my_custom_module");
+    }
+
+    #[test]
+    fn test_parse_action() {
+        let (module, args) = parse_action(&Value::String("shell echo hi".into())).unwrap();
+        assert_eq!(module, "shell");
+        assert_eq!(args.as_str(), Some("echo hi"));
+
+        let (module, _) = parse_action(&Value::String("module=raw uptime".into())).unwrap();
+        assert_eq!(module, "raw");
+
+        let value: Value = serde_yaml::from_str("{module: copy, dest: /tmp/x}").unwrap();
+        let (module, args) = parse_action(&value).unwrap();
+        assert_eq!(module, "copy");
+        assert!(args.get("module").is_none());
+        assert!(args.get("dest").is_some());
+    }
+
+    #[test]
+    fn test_parse_kv() {
+        let args = parse_kv(
+            r#"path=/etc/app mode=0777 owner="app user" state='directory'"#,
+            false,
+        );
+        assert_eq!(args.get("path").and_then(|v| v.as_str()), Some("/etc/app"));
+        assert_eq!(args.get("mode").and_then(|v| v.as_str()), Some("0777"));
+        assert_eq!(args.get("owner").and_then(|v| v.as_str()), Some("app user"));
+        assert_eq!(
+            args.get("state").and_then(|v| v.as_str()),
+            Some("directory")
+        );
+        assert!(args.get(RAW_PARAMS).is_none());
+
+        let args = parse_kv(
+            "dest={{ base_dir }}/{{ name | default('x y') }} src=a",
+            false,
+        );
+        assert_eq!(
+            args.get("dest").and_then(|v| v.as_str()),
+            Some("{{ base_dir }}/{{ name | default('x y') }}")
+        );
+    }
+
+    #[test]
+    fn test_parse_kv_raw_modules() {
+        let args = parse_kv("echo a=b > /tmp/out creates=/tmp/out", true);
+        assert_eq!(
+            args.get(RAW_PARAMS).and_then(|v| v.as_str()),
+            Some("echo a=b > /tmp/out")
+        );
+        assert_eq!(
+            args.get("creates").and_then(|v| v.as_str()),
+            Some("/tmp/out")
+        );
+        assert!(args.get("a").is_none());
+    }
+
+    #[test]
+    fn test_normalize_args_merges_args_keyword() {
+        let extra: Value = serde_yaml::from_str("{mode: '0600', owner: root}").unwrap();
+        let args = normalize_args(
+            "ansible.builtin.copy",
+            &Value::String("src=a dest=b mode=777".into()),
+            Some(&extra),
+        );
+        assert_eq!(args.get("mode").and_then(|v| v.as_str()), Some("777"));
+        assert_eq!(args.get("owner").and_then(|v| v.as_str()), Some("root"));
+    }
+}
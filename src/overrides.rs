@@ -0,0 +1,255 @@
+use globset::{Glob, GlobMatcher};
+use schemars::JsonSchema;
+use serde::de::{MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// Changes to rules from the `rules:` section of the configuration, keyed by
+/// rule ID or a glob over IDs such as `POLICY_*`. Entries apply in order, a
+/// later entry winning over an earlier one for the fields it sets.
+#[derive(Debug, Clone, Default)]
+pub struct RuleOverrides {
+    entries: Vec<Entry>,
+    /// Rules below this severity are disabled unless an entry enables
+    /// them; set by profiles
+    pub min_severity: Option<OverrideSeverity>,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    pattern: String,
+    matcher: GlobMatcher,
+    rule: RuleOverride,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuleOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub severity: Option<OverrideSeverity>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// Fields of a policy rule's `rule_type` to replace, e.g. `max_permissions`
+    #[serde(skip_serializing_if = "serde_yaml::Mapping::is_empty")]
+    #[schemars(with = "serde_json::Map<String, serde_json::Value>")]
+    pub params: serde_yaml::Mapping,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum OverrideSeverity {
+    #[serde(alias = "critical")]
+    Critical,
+    #[serde(alias = "high")]
+    High,
+    #[serde(alias = "medium")]
+    Medium,
+    #[serde(alias = "low")]
+    Low,
+    #[serde(alias = "info")]
+    Info,
+}
+
+impl OverrideSeverity {
+    pub fn parse(severity: &str) -> Option<Self> {
+        serde_yaml::from_str(&severity.to_uppercase()).ok()
+    }
+
+    /// 0 for `CRITICAL` up to 4 for `INFO`.
+    fn rank(self) -> u8 {
+        self as u8
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OverrideSeverity::Critical => "CRITICAL",
+            OverrideSeverity::High => "HIGH",
+            OverrideSeverity::Medium => "MEDIUM",
+            OverrideSeverity::Low => "LOW",
+            OverrideSeverity::Info => "INFO",
+        }
+    }
+}
+
+impl RuleOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The ID or glob of every entry, in order.
+    pub fn patterns(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|entry| entry.pattern.as_str())
+    }
+
+    /// Append the entries of a later layer. An entry for a pattern that is
+    /// already present replaces the earlier one's fields it sets.
+    pub fn extend(&mut self, other: RuleOverrides) {
+        for mut entry in other.entries {
+            if let Some(idx) = self.entries.iter().position(|e| e.pattern == entry.pattern) {
+                let earlier = self.entries.remove(idx);
+                entry.rule = earlier.rule.merged(&entry.rule);
+            }
+            self.entries.push(entry);
+        }
+    }
+
+    /// Everything the entries matching `rule_id` change.
+    pub fn for_rule(&self, rule_id: &str) -> RuleOverride {
+        self.entries
+            .iter()
+            .filter(|entry| entry.matcher.is_match(rule_id))
+            .fold(RuleOverride::default(), |acc, entry| {
+                acc.merged(&entry.rule)
+            })
+    }
+
+    /// Apply the severity and `enabled` overrides for a rule, returning a
+    /// note for each value changed.
+    pub fn apply(&self, rule_id: &str, severity: &mut String, enabled: &mut bool) -> Vec<String> {
+        let rule = self.for_rule(rule_id);
+        let mut notes = Vec::new();
+        if let Some(new) = rule.severity {
+            if !severity.eq_ignore_ascii_case(new.as_str()) {
+                notes.push(format!("severity was {}", severity));
+                *severity = new.as_str().to_string();
+            }
+        }
+        if let Some(new) = rule.enabled {
+            if *enabled != new {
+                notes.push(format!("enabled was {}", enabled));
+                *enabled = new;
+            }
+        } else if let Some(min) = self.min_severity {
+            let below = OverrideSeverity::parse(severity).is_some_and(|s| s.rank() > min.rank());
+            if *enabled && below {
+                notes.push(format!("disabled below {}", min.as_str()));
+                *enabled = false;
+            }
+        }
+        notes
+    }
+
+    /// Entries whose pattern matches none of `rule_ids`.
+    pub fn unmatched<'a>(&'a self, rule_ids: &[&str]) -> Vec<&'a str> {
+        self.entries
+            .iter()
+            .filter(|entry| !rule_ids.iter().any(|id| entry.matcher.is_match(id)))
+            .map(|entry| entry.pattern.as_str())
+            .collect()
+    }
+}
+
+impl RuleOverride {
+    /// This override with the fields `later` sets replacing its own.
+    fn merged(&self, later: &RuleOverride) -> RuleOverride {
+        let mut params = self.params.clone();
+        for (key, value) in &later.params {
+            params.insert(key.clone(), value.clone());
+        }
+        RuleOverride {
+            severity: later.severity.or(self.severity),
+            enabled: later.enabled.or(self.enabled),
+            params,
+        }
+    }
+}
+
+impl PartialEq for RuleOverrides {
+    fn eq(&self, other: &Self) -> bool {
+        self.min_severity == other.min_severity
+            && self.entries.len() == other.entries.len()
+            && self
+                .entries
+                .iter()
+                .zip(&other.entries)
+                .all(|(a, b)| a.pattern == b.pattern && a.rule == b.rule)
+    }
+}
+
+impl Serialize for RuleOverrides {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.entries.len()))?;
+        for entry in &self.entries {
+            map.serialize_entry(&entry.pattern, &entry.rule)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for RuleOverrides {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct OverridesVisitor;
+
+        impl<'de> Visitor<'de> for OverridesVisitor {
+            type Value = RuleOverrides;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a mapping of rule IDs or globs to overrides")
+            }
+
+            fn visit_unit<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+                Ok(RuleOverrides::default())
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut overrides = RuleOverrides::default();
+                while let Some((pattern, rule)) = map.next_entry::<String, RuleOverride>()? {
+                    let matcher = Glob::new(&pattern)
+                        .map_err(|e| {
+                            serde::de::Error::custom(format!(
+                                "invalid rule glob '{}': {}",
+                                pattern,
+                                e.kind()
+                            ))
+                        })?
+                        .compile_matcher();
+                    overrides.extend(RuleOverrides {
+                        entries: vec![Entry {
+                            pattern,
+                            matcher,
+                            rule,
+                        }],
+                        min_severity: None,
+                    });
+                }
+                Ok(overrides)
+            }
+        }
+
+        deserializer.deserialize_map(OverridesVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_later_entries_win() {
+        let overrides: RuleOverrides = serde_yaml::from_str(
+            "POLICY_*: {severity: LOW, enabled: false}\nPOLICY_005: {severity: HIGH}\n",
+        )
+        .unwrap();
+
+        let rule = overrides.for_rule("POLICY_005");
+        assert_eq!(rule.severity, Some(OverrideSeverity::High));
+        assert_eq!(rule.enabled, Some(false));
+        assert_eq!(
+            overrides.for_rule("POLICY_003").severity,
+            Some(OverrideSeverity::Low)
+        );
+        assert_eq!(overrides.for_rule("LINT_001"), RuleOverride::default());
+        assert_eq!(
+            overrides.unmatched(&["LINT_001"]),
+            ["POLICY_*", "POLICY_005"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_override() {
+        assert!(serde_yaml::from_str::<RuleOverrides>("LINT_[: {enabled: false}").is_err());
+        assert!(serde_yaml::from_str::<RuleOverrides>("LINT_001: {severity: urgent}").is_err());
+        assert!(serde_yaml::from_str::<RuleOverrides>("LINT_001: {level: LOW}").is_err());
+    }
+}
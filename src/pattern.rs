@@ -0,0 +1,235 @@
+use fancy_regex::RegexBuilder;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use log::debug;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::errors::{AnsibleSecError, Result};
+use crate::model::{normalize_path, relative_path};
+
+/// Backtracking steps a look-around pattern may take on a single line before
+/// the line is skipped.
+pub const DEFAULT_BACKTRACK_LIMIT: usize = 1_000_000;
+
+/// A rule pattern. Patterns run on the linear-time `regex` engine whenever it
+/// accepts them; look-around and backreferences fall back to `fancy-regex`
+/// with a bounded number of backtracking steps.
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Fast(regex::Regex),
+    Backtracking {
+        regex: fancy_regex::Regex,
+        /// Lines the pattern gave up on, shared by clones
+        gave_up: Arc<AtomicUsize>,
+    },
+}
+
+/// A match of a pattern within a haystack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PatternMatch<'t> {
+    /// Byte offset of the match
+    pub start: usize,
+    pub text: &'t str,
+}
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Result<Self> {
+        Self::with_backtrack_limit(pattern, DEFAULT_BACKTRACK_LIMIT)
+    }
+
+    pub fn with_backtrack_limit(pattern: &str, limit: usize) -> Result<Self> {
+        if let Ok(regex) = regex::Regex::new(pattern) {
+            return Ok(Pattern::Fast(regex));
+        }
+
+        RegexBuilder::new(pattern)
+            .backtrack_limit(limit)
+            .build()
+            .map(|regex| Pattern::Backtracking {
+                regex,
+                gave_up: Arc::default(),
+            })
+            .map_err(|e| {
+                AnsibleSecError::InvalidRuleFormat(format!("invalid pattern '{}': {}", pattern, e))
+            })
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Pattern::Fast(regex) => regex.as_str(),
+            Pattern::Backtracking { regex, .. } => regex.as_str(),
+        }
+    }
+
+    /// How many times the pattern exceeded its backtracking limit and gave
+    /// up on a line.
+    pub fn gave_up(&self) -> usize {
+        match self {
+            Pattern::Fast(_) => 0,
+            Pattern::Backtracking { gave_up, .. } => gave_up.load(Ordering::Relaxed),
+        }
+    }
+
+    /// First match in `text`. A pattern that exceeds its backtracking limit
+    /// is treated as not matching and counted in `gave_up`.
+    pub fn find<'t>(&self, text: &'t str) -> Option<PatternMatch<'t>> {
+        match self {
+            Pattern::Fast(regex) => regex.find(text).map(|m| PatternMatch {
+                start: m.start(),
+                text: m.as_str(),
+            }),
+            Pattern::Backtracking { regex, .. } => match regex.find(text) {
+                Ok(m) => m.map(|m| PatternMatch {
+                    start: m.start(),
+                    text: m.as_str(),
+                }),
+                Err(e) => {
+                    self.give_up(&e);
+                    None
+                }
+            },
+        }
+    }
+
+    /// All non-overlapping matches in `text`. Matching stops at the first
+    /// runtime error, keeping the matches found so far.
+    pub fn find_iter<'t>(&self, text: &'t str) -> Vec<PatternMatch<'t>> {
+        match self {
+            Pattern::Fast(regex) => regex
+                .find_iter(text)
+                .map(|m| PatternMatch {
+                    start: m.start(),
+                    text: m.as_str(),
+                })
+                .collect(),
+            Pattern::Backtracking { regex, .. } => {
+                let mut matches = Vec::new();
+                for m in regex.find_iter(text) {
+                    match m {
+                        Ok(m) => matches.push(PatternMatch {
+                            start: m.start(),
+                            text: m.as_str(),
+                        }),
+                        Err(e) => {
+                            self.give_up(&e);
+                            break;
+                        }
+                    }
+                }
+                matches
+            }
+        }
+    }
+
+    fn give_up(&self, error: &fancy_regex::Error) {
+        debug!("Pattern '{}' gave up on a line: {}", self.as_str(), error);
+        if let Pattern::Backtracking { gave_up, .. } = self {
+            gave_up.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Path globs, e.g. a rule's `paths`. `*` stays within a directory and `**`
+/// crosses directories; a glob without `/` matches at any depth.
+#[derive(Debug, Clone)]
+pub struct Globs {
+    set: GlobSet,
+    /// Directory the globs are relative to; `None` for the working directory
+    root: Option<PathBuf>,
+}
+
+impl Globs {
+    pub fn new(globs: &[String]) -> std::result::Result<Self, String> {
+        let mut builder = GlobSetBuilder::new();
+        for glob in globs {
+            let anchored = if glob.contains('/') {
+                glob.trim_start_matches('/').to_string()
+            } else {
+                format!("**/{}", glob)
+            };
+            let compiled = GlobBuilder::new(&anchored)
+                .literal_separator(true)
+                .build()
+                .map_err(|e| format!("invalid glob '{}': {}", glob, e.kind()))?;
+            builder.add(compiled);
+        }
+        let set = builder
+            .build()
+            .map_err(|e| format!("invalid globs: {}", e))?;
+        Ok(Self { set, root: None })
+    }
+
+    /// These globs matched relative to `root` instead of the working
+    /// directory.
+    pub fn relative_to(mut self, root: &Path) -> Self {
+        self.root = Some(root.to_path_buf());
+        self
+    }
+
+    /// Whether `path`, relative to the globs' root, matches any glob. Paths
+    /// outside the root never match.
+    pub fn is_match(&self, path: &Path) -> bool {
+        match self.root {
+            Some(ref root) => relative_path(path, root).is_some_and(|path| self.set.is_match(path)),
+            None => self.set.is_match(normalize_path(path)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_engine_selection() {
+        let backtracking =
+            |pattern: &str| matches!(Pattern::new(pattern).unwrap(), Pattern::Backtracking { .. });
+        assert!(!backtracking(r"AKIA[0-9A-Z]{16}"));
+        assert!(backtracking(r"password:\s*(?!\{\{)\S+"));
+        assert!(backtracking(r"(['\x22])\w+\1"));
+        assert!(Pattern::new(r"(unclosed").is_err());
+    }
+
+    #[test]
+    fn test_look_ahead_matches() {
+        let pattern = Pattern::new(r"password:\s*(?!\{\{)\S+").unwrap();
+
+        let m = pattern.find("  password: hunter2").unwrap();
+        assert_eq!(m.start, 2);
+        assert_eq!(m.text, "password: hunter2");
+        assert!(pattern.find("password: {{ vault_password }}").is_none());
+        assert_eq!(pattern.find_iter("password: a password: b").len(), 2);
+    }
+
+    #[test]
+    fn test_backtrack_limit() {
+        let pattern = Pattern::with_backtrack_limit(r"^(a|aa)+(?=b)\1", 1_000).unwrap();
+        let line = format!("{}c", "a".repeat(40));
+
+        // The limit is hit rather than exploring every split of the a's
+        let Pattern::Backtracking { ref regex, .. } = pattern else {
+            panic!("expected a backtracking pattern");
+        };
+        assert!(regex.find(&line).is_err());
+
+        assert!(pattern.find(&line).is_none());
+        assert!(pattern.find_iter(&line).is_empty());
+        // Counted across clones, so the scan can report it
+        assert_eq!(pattern.clone().gave_up(), 2);
+    }
+
+    #[test]
+    fn test_globs() {
+        let globs = Globs::new(&["infra/prod/**".to_string(), "*.retry".to_string()]).unwrap();
+        assert!(globs.is_match(Path::new("infra/prod/site.yml")));
+        assert!(globs.is_match(Path::new("./infra/prod/roles/web/tasks/main.yml")));
+        assert!(!globs.is_match(Path::new("infra/staging/site.yml")));
+        assert!(globs.is_match(Path::new("sandbox/site.retry")));
+        assert!(Globs::new(&["[".to_string()]).is_err());
+
+        let globs = globs.relative_to(Path::new("monorepo"));
+        assert!(globs.is_match(Path::new("monorepo/infra/prod/site.yml")));
+        assert!(!globs.is_match(Path::new("infra/prod/site.yml")));
+    }
+}
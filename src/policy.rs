@@ -1,110 +1,303 @@
 use serde_yaml::Value;
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 
 use crate::config::Config;
-use crate::errors::Result;
-use crate::rules::{Rule, RuleType, RulesEngine};
+use crate::errors::{AnsibleSecError, Result};
+use crate::matcher::{self, TaskMatcher};
+use crate::model::{Document, TaskRef};
+use crate::modules;
+use crate::pattern::{Globs, Pattern};
+use crate::rules::{PathMode, PathScope, Rule, RuleType, RulesEngine};
 use crate::scanner::Finding;
+use crate::yaml::{self, NodePath, PathExpr, Span};
 
 pub struct PolicyEngine {
-    #[allow(dead_code)]
-    config: Config,
     rules_engine: RulesEngine,
+    /// Compiled regexes of `Pattern` rules, by rule ID
+    patterns: HashMap<String, Pattern>,
+    /// Compiled conditions of `Task` rules, by rule ID
+    matchers: HashMap<String, TaskMatcher>,
+    /// Compiled `CustomYamlPath` rules, by rule ID
+    yaml_paths: HashMap<String, YamlPathCheck>,
+    /// Compiled `paths` of rules scoped to some files, by rule ID
+    scopes: HashMap<String, Globs>,
 }
 
-impl PolicyEngine {
-    pub fn new(config: Config) -> Self {
-        let rules_engine = if let Some(ref rules_file) = config.policies.rules_file {
-            RulesEngine::load(Path::new(rules_file)).unwrap_or_default()
+/// One document, play or task a `CustomYamlPath` rule is evaluated in.
+struct PathScopeNodes<'a> {
+    /// Path of the scope's node from the document root
+    base: NodePath,
+    /// Where to report a path missing from this scope
+    span: Span,
+    /// e.g. `play 'Deploy'`, for messages
+    label: Option<String>,
+    /// Nodes selected by the rule's path, relative to `base`
+    selected: Vec<(NodePath, &'a Value)>,
+}
+
+/// A `CustomYamlPath` rule with its path parsed and its mode resolved.
+struct YamlPathCheck {
+    expr: PathExpr,
+    scope: PathScope,
+    mode: PathMode,
+    expected: Option<Value>,
+    regex: Option<Pattern>,
+}
+
+impl YamlPathCheck {
+    fn compile(
+        path: &str,
+        scope: PathScope,
+        mode: Option<PathMode>,
+        expected_value: &Option<String>,
+        regex: &Option<String>,
+    ) -> Result<Self> {
+        let invalid = |message: String| AnsibleSecError::InvalidRuleFormat(message);
+
+        let expr = PathExpr::parse(path).map_err(invalid)?;
+        let mode = mode.unwrap_or(if expected_value.is_some() {
+            PathMode::Equals
         } else {
-            RulesEngine::default()
+            PathMode::Exists
+        });
+
+        // The expected value is read as YAML so `true` also accepts `yes`
+        let expected = match (mode, expected_value) {
+            (PathMode::Equals, None) => {
+                return Err(invalid(
+                    "mode `equals` needs an `expected_value`".to_string(),
+                ))
+            }
+            (PathMode::Equals, Some(expected)) => Some(
+                serde_yaml::from_str(expected).unwrap_or_else(|_| Value::String(expected.clone())),
+            ),
+            _ => None,
         };
+        let regex = match (mode, regex) {
+            (PathMode::Matches, None) => {
+                return Err(invalid("mode `matches` needs a `regex`".to_string()))
+            }
+            (PathMode::Matches, Some(regex)) => Some(Pattern::new(regex)?),
+            _ => None,
+        };
+
+        Ok(Self {
+            expr,
+            scope,
+            mode,
+            expected,
+            regex,
+        })
+    }
+}
 
-        Self {
-            config,
+impl PolicyEngine {
+    pub fn new(config: Config) -> Result<Self> {
+        let rules_engine = RulesEngine::from_config(&config)?;
+        Self::with_rules(rules_engine)
+    }
+
+    /// An engine for the given rules instead of the configured rules file.
+    pub fn with_rules(rules_engine: RulesEngine) -> Result<Self> {
+        let mut patterns = HashMap::new();
+        let mut matchers = HashMap::new();
+        let mut yaml_paths = HashMap::new();
+        let mut scopes = HashMap::new();
+        for rule in rules_engine.get_enabled_rules() {
+            let in_rule = |e: AnsibleSecError| e.in_rule(&rule.id);
+
+            if !rule.paths.is_empty() {
+                let globs = Globs::new(&rule.paths)
+                    .map_err(|e| in_rule(AnsibleSecError::InvalidRuleFormat(e)))?;
+                scopes.insert(rule.id.clone(), globs);
+            }
+
+            match rule.rule_type {
+                RuleType::Pattern { ref pattern } => {
+                    patterns.insert(rule.id.clone(), Pattern::new(pattern).map_err(in_rule)?);
+                }
+                RuleType::CheckPermissions {
+                    ref max_permissions,
+                    ..
+                } if parse_mode(max_permissions).is_none() => {
+                    return Err(in_rule(AnsibleSecError::InvalidRuleFormat(format!(
+                        "max_permissions '{}' is not an octal file mode",
+                        max_permissions
+                    ))));
+                }
+                RuleType::Task(ref spec) => {
+                    matchers.insert(
+                        rule.id.clone(),
+                        TaskMatcher::compile(spec).map_err(in_rule)?,
+                    );
+                }
+                RuleType::CustomYamlPath {
+                    ref path,
+                    scope,
+                    mode,
+                    ref expected_value,
+                    ref regex,
+                } => {
+                    yaml_paths.insert(
+                        rule.id.clone(),
+                        YamlPathCheck::compile(path, scope, mode, expected_value, regex)
+                            .map_err(in_rule)?,
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self {
             rules_engine,
+            patterns,
+            matchers,
+            yaml_paths,
+            scopes,
+        })
+    }
+
+    /// This engine with rule `paths` matched relative to `root` instead of
+    /// the working directory.
+    pub fn relative_to(mut self, root: &Path) -> Self {
+        for globs in self.scopes.values_mut() {
+            *globs = globs.clone().relative_to(root);
         }
+        self
     }
 
-    pub fn check_policies(&self, _file_path: &Path, content: &str) -> Result<Vec<Finding>> {
-        let mut findings = Vec::new();
+    /// Every loaded rule, enabled or not.
+    pub fn rules(&self) -> &[Rule] {
+        self.rules_engine.rules()
+    }
 
-        // Parse YAML
-        let yaml: Value = match serde_yaml::from_str(content) {
-            Ok(v) => v,
-            Err(_) => return Ok(findings), // Skip non-YAML files
+    /// Lines each rule's regexes gave up on so far, for the rules that gave
+    /// up on any.
+    pub fn gave_up(&self) -> Vec<(String, usize)> {
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        let mut add = |id: &str, pattern: &Pattern| {
+            *counts.entry(id.to_string()).or_default() += pattern.gave_up();
         };
+        for (id, pattern) in &self.patterns {
+            add(id, pattern);
+        }
+        for (id, check) in &self.yaml_paths {
+            if let Some(ref regex) = check.regex {
+                add(id, regex);
+            }
+        }
+        for (id, matcher) in &self.matchers {
+            for pattern in matcher.patterns() {
+                add(id, pattern);
+            }
+        }
+        counts.into_iter().filter(|(_, count)| *count > 0).collect()
+    }
+
+    pub fn check_policies(&self, doc: &Document) -> Result<Vec<Finding>> {
+        let mut findings = Vec::new();
 
         let enabled_rules = self.rules_engine.get_enabled_rules();
 
         for rule in enabled_rules {
-            findings.extend(self.check_rule(rule, &yaml, content)?);
+            if let Some(scope) = self.scopes.get(&rule.id) {
+                if !scope.is_match(&doc.path) {
+                    continue;
+                }
+            }
+            findings.extend(self.check_rule(rule, doc)?);
         }
 
         Ok(findings)
     }
 
-    fn check_rule(&self, rule: &Rule, yaml: &Value, content: &str) -> Result<Vec<Finding>> {
+    fn check_rule(&self, rule: &Rule, doc: &Document) -> Result<Vec<Finding>> {
         let mut findings = Vec::new();
 
         match &rule.rule_type {
-            RuleType::DisallowModule { modules } => {
-                findings.extend(self.check_disallow_module(rule, yaml, modules, content));
-            }
-            RuleType::RequireVault { exceptions } => {
-                findings.extend(self.check_require_vault(rule, yaml, exceptions, content));
-            }
+            // Line-based rules read the whole file once
             RuleType::DisallowHardcodedCredentials => {
-                findings.extend(self.check_hardcoded_credentials(rule, yaml, content));
-            }
-            RuleType::RequireNoLogForSensitive => {
-                findings.extend(self.check_no_log(rule, yaml, content));
+                findings.extend(self.check_hardcoded_credentials(rule, doc));
             }
-            RuleType::CheckPermissions { max_permissions } => {
-                findings.extend(self.check_permissions(rule, yaml, max_permissions, content));
+            RuleType::Pattern { .. } => {
+                if let Some(pattern) = self.patterns.get(&rule.id) {
+                    findings.extend(self.check_pattern(rule, doc, pattern));
+                }
             }
-            RuleType::CustomYamlPath {
-                path,
-                expected_value,
-            } => {
-                findings.extend(self.check_yaml_path(rule, yaml, path, expected_value, content));
+            RuleType::Manual => {}
+            _ => {
+                for document in doc.documents() {
+                    findings.extend(self.check_document(rule, document));
+                }
             }
         }
 
         Ok(findings)
     }
 
+    /// Check a rule on the parsed content of one document of a stream.
+    fn check_document(&self, rule: &Rule, doc: &Document) -> Vec<Finding> {
+        match &rule.rule_type {
+            RuleType::DisallowModule { modules } => self.check_disallow_module(rule, doc, modules),
+            RuleType::RequireVault { exceptions } => {
+                self.check_require_vault(rule, doc, exceptions)
+            }
+            RuleType::RequireNoLogForSensitive => self.check_no_log(rule, doc),
+            RuleType::CheckPermissions {
+                max_permissions,
+                state,
+            } => self.check_permissions(rule, doc, max_permissions, state.as_deref()),
+            RuleType::CustomYamlPath { path, .. } => match self.yaml_paths.get(&rule.id) {
+                Some(check) => self.check_yaml_path(rule, doc, path, check),
+                None => Vec::new(),
+            },
+            RuleType::Module { modules } => self.check_module(rule, doc, modules),
+            RuleType::Task(_) => match self.matchers.get(&rule.id) {
+                Some(matcher) => doc
+                    .tasks()
+                    .iter()
+                    .filter(|task| matcher.matches(task))
+                    .map(|task| self.task_finding(rule, doc, task))
+                    .collect(),
+                None => Vec::new(),
+            },
+            RuleType::DisallowHardcodedCredentials
+            | RuleType::Pattern { .. }
+            | RuleType::Manual => Vec::new(),
+        }
+    }
+
     fn check_disallow_module(
         &self,
         rule: &Rule,
-        yaml: &Value,
+        doc: &Document,
         modules: &[String],
-        content: &str,
     ) -> Vec<Finding> {
         let mut findings = Vec::new();
 
-        // Check for playbook with tasks
-        if let Some(tasks) = self.extract_tasks(yaml) {
-            for (idx, task) in tasks.iter().enumerate() {
-                if let Some(obj) = task.as_mapping() {
-                    for (key, _value) in obj {
-                        if let Some(module_name) = key.as_str() {
-                            if modules.iter().any(|m| m == module_name) {
-                                let line = self.find_line_number(content, module_name, idx);
-                                findings.push(Finding {
-                                    line,
-                                    column: 0,
-                                    severity: rule.severity.clone(),
-                                    rule_id: rule.id.clone(),
-                                    message: format!("Use of disallowed module: {}", module_name),
-                                    context: Some(format!(
-                                        "Module '{}' is restricted for security reasons",
-                                        module_name
-                                    )),
-                                });
-                            }
-                        }
-                    }
+        for task in doc.tasks() {
+            if let (Some(module_name), Some(fqcn)) =
+                (task.task.module.as_deref(), task.task.fqcn.as_deref())
+            {
+                if modules.iter().any(|m| modules::is_module(fqcn, m)) {
+                    let span = doc.module_span(task.task);
+                    findings.push(Finding {
+                        line: span.start.line,
+                        column: span.start.column,
+                        end_line: span.end.line,
+                        end_column: span.end.column,
+                        severity: rule.severity.clone(),
+                        rule_id: rule.id.clone(),
+                        message: format!("Use of disallowed module: {}", module_name),
+                        context: Some(format!(
+                            "Module '{}' is restricted for security reasons",
+                            module_name
+                        )),
+                        remediation: rule.remediation.clone(),
+                        suppression: None,
+                        baselined: false,
+                    });
                 }
             }
         }
@@ -112,37 +305,110 @@ impl PolicyEngine {
         findings
     }
 
+    fn check_module(&self, rule: &Rule, doc: &Document, modules: &[String]) -> Vec<Finding> {
+        let mut findings = Vec::new();
+
+        for task in doc.tasks() {
+            let Some(fqcn) = task.task.fqcn.as_deref() else {
+                continue;
+            };
+
+            if modules.iter().any(|m| modules::is_module(fqcn, m)) {
+                findings.push(self.task_finding(rule, doc, &task));
+            }
+        }
+
+        findings
+    }
+
+    /// A finding for a rule-defined task check, reported at the module.
+    fn task_finding(&self, rule: &Rule, doc: &Document, task: &TaskRef) -> Finding {
+        let span = doc.module_span(task.task);
+        Finding {
+            line: span.start.line,
+            column: span.start.column,
+            end_line: span.end.line,
+            end_column: span.end.column,
+            severity: rule.severity.clone(),
+            rule_id: rule.id.clone(),
+            message: rule.description.clone(),
+            context: task.task.name.clone().map(|name| format!("Task: {}", name)),
+            remediation: rule.remediation.clone(),
+            suppression: None,
+            baselined: false,
+        }
+    }
+
+    fn check_pattern(&self, rule: &Rule, doc: &Document, pattern: &Pattern) -> Vec<Finding> {
+        let mut findings = Vec::new();
+
+        for (line_num, line) in doc.content.lines().enumerate() {
+            if line.trim_start().starts_with('#') {
+                continue;
+            }
+
+            let Some(mat) = pattern.find(line) else {
+                continue;
+            };
+
+            let column = line[..mat.start].chars().count() + 1;
+            let span = Span::on_line(line_num + 1, column, column + mat.text.chars().count());
+            findings.push(Finding {
+                line: span.start.line,
+                column: span.start.column,
+                end_line: span.end.line,
+                end_column: span.end.column,
+                severity: rule.severity.clone(),
+                rule_id: rule.id.clone(),
+                message: rule.description.clone(),
+                // The matched line may itself hold a secret, so it is not echoed
+                context: None,
+                remediation: rule.remediation.clone(),
+                suppression: None,
+                baselined: false,
+            });
+        }
+
+        findings
+    }
+
     fn check_require_vault(
         &self,
         rule: &Rule,
-        yaml: &Value,
+        doc: &Document,
         exceptions: &[String],
-        content: &str,
     ) -> Vec<Finding> {
         let mut findings = Vec::new();
 
         // Check for variables that look like they should be vaulted
-        if let Some(vars) = yaml.get("vars").or_else(|| yaml.get("vars_files")) {
-            if let Some(obj) = vars.as_mapping() {
-                for (key, value) in obj {
-                    if let Some(key_str) = key.as_str() {
-                        // Check for sensitive variable
-                        if self.is_sensitive_var(key_str)
-                            && !exceptions.contains(&key_str.to_string())
-                        {
-                            // Check if value is vaulted
-                            if let Some(val_str) = value.as_str() {
-                                if !val_str.starts_with("$ANSIBLE_VAULT") {
-                                    let line = self.find_line_number(content, key_str, 0);
-                                    findings.push(Finding {
-                                        line,
-                                        column: 0,
-                                        severity: rule.severity.clone(),
-                                        rule_id: rule.id.clone(),
-                                        message: format!("Sensitive variable '{}' should be encrypted with Ansible Vault", key_str),
-                                        context: Some("Use ansible-vault to encrypt sensitive values".to_string()),
-                                    });
-                                }
+        for (path, obj) in doc.vars_scopes() {
+            for (key, value) in obj {
+                if let Some(key_str) = key.as_str() {
+                    // Check for sensitive variable
+                    if self.is_sensitive_var(key_str) && !exceptions.contains(&key_str.to_string())
+                    {
+                        // Check if value is vaulted
+                        if let Some(val_str) = value.as_str() {
+                            if !val_str.starts_with("$ANSIBLE_VAULT") {
+                                let span = doc.key_span(&yaml::key(&path, key_str));
+                                findings.push(Finding {
+                                    line: span.start.line,
+                                    column: span.start.column,
+                                    end_line: span.end.line,
+                                    end_column: span.end.column,
+                                    severity: rule.severity.clone(),
+                                    rule_id: rule.id.clone(),
+                                    message: format!(
+                                        "Sensitive variable '{}' should be encrypted with Ansible Vault",
+                                        key_str
+                                    ),
+                                    context: Some(
+                                        "Use ansible-vault to encrypt sensitive values".to_string(),
+                                    ),
+                                    remediation: rule.remediation.clone(),
+                                    suppression: None,
+                                    baselined: false,
+                                });
                             }
                         }
                     }
@@ -153,13 +419,9 @@ impl PolicyEngine {
         findings
     }
 
-    fn check_hardcoded_credentials(
-        &self,
-        rule: &Rule,
-        _yaml: &Value,
-        content: &str,
-    ) -> Vec<Finding> {
+    fn check_hardcoded_credentials(&self, rule: &Rule, doc: &Document) -> Vec<Finding> {
         let mut findings = Vec::new();
+        let content = &doc.content;
 
         // Look for patterns like password: "plaintext"
         let sensitive_keywords = [
@@ -188,9 +450,12 @@ impl PolicyEngine {
                                 && !value.starts_with("\"{{")
                                 && value.len() > 3
                             {
+                                let span = Span::of_line(line_num + 1, line);
                                 findings.push(Finding {
-                                    line: line_num + 1,
-                                    column: 0,
+                                    line: span.start.line,
+                                    column: span.start.column,
+                                    end_line: span.end.line,
+                                    end_column: span.end.column,
                                     severity: rule.severity.clone(),
                                     rule_id: rule.id.clone(),
                                     message: format!(
@@ -201,6 +466,9 @@ impl PolicyEngine {
                                         "Use Ansible Vault or variables for sensitive data"
                                             .to_string(),
                                     ),
+                                    remediation: rule.remediation.clone(),
+                                    suppression: None,
+                                    baselined: false,
                                 });
                                 break;
                             }
@@ -213,41 +481,33 @@ impl PolicyEngine {
         findings
     }
 
-    fn check_no_log(&self, rule: &Rule, yaml: &Value, content: &str) -> Vec<Finding> {
+    fn check_no_log(&self, rule: &Rule, doc: &Document) -> Vec<Finding> {
         let mut findings = Vec::new();
 
-        if let Some(tasks) = self.extract_tasks(yaml) {
-            for (idx, task) in tasks.iter().enumerate() {
-                if let Some(obj) = task.as_mapping() {
-                    // Check if task involves sensitive modules
-                    let has_sensitive_module = obj.keys().any(|k| {
-                        if let Some(key_str) = k.as_str() {
-                            self.is_sensitive_module(key_str)
-                        } else {
-                            false
-                        }
-                    });
-
-                    if has_sensitive_module {
-                        // Check for no_log
-                        let has_no_log =
-                            obj.get("no_log").and_then(|v| v.as_bool()).unwrap_or(false);
-
-                        if !has_no_log {
-                            let line = self.find_line_number(content, "name", idx);
-                            findings.push(Finding {
-                                line,
-                                column: 0,
-                                severity: rule.severity.clone(),
-                                rule_id: rule.id.clone(),
-                                message: "Sensitive task should have 'no_log: true'".to_string(),
-                                context: Some(
-                                    "Prevents sensitive data from being logged".to_string(),
-                                ),
-                            });
-                        }
-                    }
-                }
+        for task in doc.tasks() {
+            // Check if task involves sensitive modules
+            let has_sensitive_module = task
+                .task
+                .fqcn
+                .as_deref()
+                .is_some_and(|m| self.is_sensitive_module(m));
+
+            // no_log may be set on the task, an enclosing block or the play
+            if has_sensitive_module && !task.no_log() {
+                let span = doc.task_span(task.task);
+                findings.push(Finding {
+                    line: span.start.line,
+                    column: span.start.column,
+                    end_line: span.end.line,
+                    end_column: span.end.column,
+                    severity: rule.severity.clone(),
+                    rule_id: rule.id.clone(),
+                    message: "Sensitive task should have 'no_log: true'".to_string(),
+                    context: Some("Prevents sensitive data from being logged".to_string()),
+                    remediation: rule.remediation.clone(),
+                    suppression: None,
+                    baselined: false,
+                });
             }
         }
 
@@ -257,43 +517,55 @@ impl PolicyEngine {
     fn check_permissions(
         &self,
         rule: &Rule,
-        yaml: &Value,
+        doc: &Document,
         max_permissions: &str,
-        content: &str,
+        state: Option<&str>,
     ) -> Vec<Finding> {
         let mut findings = Vec::new();
 
-        if let Some(tasks) = self.extract_tasks(yaml) {
-            for (idx, task) in tasks.iter().enumerate() {
-                if let Some(obj) = task.as_mapping() {
-                    // Check file/copy/template modules
-                    for module in &["file", "copy", "template"] {
-                        if let Some(module_params) = obj.get(*module) {
-                            if let Some(params_obj) = module_params.as_mapping() {
-                                if let Some(mode) = params_obj.get("mode") {
-                                    if let Some(mode_str) = mode.as_str() {
-                                        if self.is_permission_too_open(mode_str, max_permissions) {
-                                            let line = self.find_line_number(content, "mode", idx);
-                                            findings.push(Finding {
-                                                line,
-                                                column: 0,
-                                                severity: rule.severity.clone(),
-                                                rule_id: rule.id.clone(),
-                                                message: format!(
-                                                    "File permissions '{}' are too permissive",
-                                                    mode_str
-                                                ),
-                                                context: Some(format!(
-                                                    "Consider using {} or more restrictive",
-                                                    max_permissions
-                                                )),
-                                            });
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
+        for task in doc.tasks() {
+            // Check file/copy/template modules
+            let fqcn = task.task.fqcn.as_deref().unwrap_or_default();
+            if !modules::is_any_module(fqcn, &["file", "copy", "template"]) {
+                continue;
+            }
+            if let Some(state) = state {
+                let actual = task.task.args.get("state").and_then(matcher::scalar_string);
+                if actual.as_deref() != Some(state) {
+                    continue;
+                }
+            }
+
+            if let Some(mode) = task.task.args.get("mode") {
+                // An unquoted mode is a number already: `0o777` is 511, and
+                // `644` is the decimal Ansible applies, 0o1204
+                let mode_str = match mode {
+                    Value::String(s) => s.clone(),
+                    Value::Number(n) => match n.as_u64() {
+                        Some(n) => format!("0o{:o}", n),
+                        None => continue,
+                    },
+                    _ => continue,
+                };
+
+                if self.is_permission_too_open(&mode_str, max_permissions) {
+                    let span = doc.arg_span(task.task, "mode");
+                    findings.push(Finding {
+                        line: span.start.line,
+                        column: span.start.column,
+                        end_line: span.end.line,
+                        end_column: span.end.column,
+                        severity: rule.severity.clone(),
+                        rule_id: rule.id.clone(),
+                        message: format!("File permissions '{}' are too permissive", mode_str),
+                        context: Some(format!(
+                            "Consider using {} or more restrictive",
+                            max_permissions
+                        )),
+                        remediation: rule.remediation.clone(),
+                        suppression: None,
+                        baselined: false,
+                    });
                 }
             }
         }
@@ -304,38 +576,127 @@ impl PolicyEngine {
     fn check_yaml_path(
         &self,
         rule: &Rule,
-        yaml: &Value,
+        doc: &Document,
         path: &str,
-        expected_value: &Option<String>,
-        content: &str,
+        check: &YamlPathCheck,
     ) -> Vec<Finding> {
         let mut findings = Vec::new();
 
-        // Simple YAML path traversal
-        let parts: Vec<&str> = path.split('.').collect();
-        let mut current = yaml;
+        let Some(ref root) = doc.root else {
+            return findings;
+        };
 
-        for part in &parts {
-            if let Some(next) = current.get(part) {
-                current = next;
-            } else {
-                return findings; // Path doesn't exist
-            }
-        }
+        let scopes: Vec<PathScopeNodes> = match check.scope {
+            PathScope::Document => vec![PathScopeNodes {
+                base: Vec::new(),
+                span: doc.value_span(&[]),
+                label: None,
+                selected: check.expr.select(root),
+            }],
+            PathScope::Play => doc
+                .plays
+                .iter()
+                .filter(|play| play.import_playbook.is_none())
+                .map(|play| PathScopeNodes {
+                    base: play.path.clone(),
+                    span: doc.play_span(play),
+                    label: play.name.as_ref().map(|name| format!("play '{}'", name)),
+                    selected: check.expr.select_in(&play.raw),
+                })
+                .collect(),
+            PathScope::Task => doc
+                .tasks()
+                .iter()
+                .map(|task| PathScopeNodes {
+                    base: task.task.path.clone(),
+                    span: doc.task_span(task.task),
+                    label: task
+                        .task
+                        .name
+                        .as_ref()
+                        .map(|name| format!("task '{}'", name)),
+                    selected: check.expr.select_in(&task.task.raw),
+                })
+                .collect(),
+        };
 
-        // If we have an expected value, check it
-        if let Some(expected) = expected_value {
-            if let Some(actual) = current.as_str() {
-                if actual != expected {
-                    let line = self.find_line_number(content, parts.last().unwrap(), 0);
-                    findings.push(Finding {
-                        line,
-                        column: 0,
-                        severity: rule.severity.clone(),
-                        rule_id: rule.id.clone(),
-                        message: format!("Value at '{}' should be '{}'", path, expected),
-                        context: Some(format!("Found: '{}'", actual)),
-                    });
+        let description = Some(rule.description.clone()).filter(|d| !d.is_empty());
+
+        for PathScopeNodes {
+            base,
+            span: scope_span,
+            label,
+            selected,
+        } in scopes
+        {
+            let mut report = |span: Span, message: String, context: Option<String>| {
+                findings.push(Finding {
+                    line: span.start.line,
+                    column: span.start.column,
+                    end_line: span.end.line,
+                    end_column: span.end.column,
+                    severity: rule.severity.clone(),
+                    rule_id: rule.id.clone(),
+                    message,
+                    context,
+                    remediation: rule.remediation.clone(),
+                    suppression: None,
+                    baselined: false,
+                });
+            };
+            let node_path = |relative: &NodePath| -> NodePath {
+                base.iter().chain(relative).cloned().collect()
+            };
+
+            match check.mode {
+                PathMode::Exists => {
+                    if selected.is_empty() {
+                        report(
+                            scope_span,
+                            match label {
+                                Some(ref label) => format!("'{}' must be set in {}", path, label),
+                                None => format!("'{}' must be set", path),
+                            },
+                            description.clone(),
+                        );
+                    }
+                }
+                PathMode::Absent => {
+                    for (relative, _) in &selected {
+                        report(
+                            doc.key_span(&node_path(relative)),
+                            format!("'{}' must not be set", path),
+                            description.clone(),
+                        );
+                    }
+                }
+                PathMode::Equals | PathMode::Matches => {
+                    for (relative, actual) in &selected {
+                        let found = matcher::scalar_string(actual);
+                        let (ok, message) = match (&check.expected, &check.regex) {
+                            (Some(expected), _) => (
+                                matcher::values_equal(actual, expected),
+                                format!(
+                                    "Value at '{}' should be '{}'",
+                                    path,
+                                    matcher::scalar_string(expected).unwrap_or_default()
+                                ),
+                            ),
+                            (None, Some(regex)) => (
+                                found.as_deref().is_some_and(|s| regex.find(s).is_some()),
+                                format!("Value at '{}' should match '{}'", path, regex.as_str()),
+                            ),
+                            (None, None) => continue,
+                        };
+
+                        if !ok {
+                            report(
+                                doc.value_span(&node_path(relative)),
+                                message,
+                                found.map(|found| format!("Found: '{}'", found)),
+                            );
+                        }
+                    }
                 }
             }
         }
@@ -343,15 +704,6 @@ impl PolicyEngine {
         findings
     }
 
-    fn extract_tasks<'a>(&self, yaml: &'a Value) -> Option<&'a Vec<Value>> {
-        // Try different locations for tasks
-        yaml.get("tasks")
-            .or_else(|| yaml.get("handlers"))
-            .or_else(|| yaml.get("pre_tasks"))
-            .or_else(|| yaml.get("post_tasks"))
-            .and_then(|v| v.as_sequence())
-    }
-
     fn is_sensitive_var(&self, var_name: &str) -> bool {
         let lower = var_name.to_lowercase();
         lower.contains("password")
@@ -361,38 +713,26 @@ impl PolicyEngine {
             || lower.contains("private_key")
     }
 
-    fn is_sensitive_module(&self, module_name: &str) -> bool {
-        matches!(
-            module_name,
-            "user" | "mysql_user" | "postgresql_user" | "uri" | "get_url"
+    fn is_sensitive_module(&self, fqcn: &str) -> bool {
+        modules::is_any_module(
+            fqcn,
+            &["user", "mysql_user", "postgresql_user", "uri", "get_url"],
         )
     }
 
+    /// A mode is too open when it sets any bit `max_mode` does not.
     fn is_permission_too_open(&self, mode: &str, max_mode: &str) -> bool {
-        // Simple octal comparison
-        let mode_clean = mode.trim_start_matches("0o").trim_start_matches('0');
-        let max_clean = max_mode.trim_start_matches("0o").trim_start_matches('0');
-
-        if let (Ok(mode_val), Ok(max_val)) = (
-            u32::from_str_radix(mode_clean, 8),
-            u32::from_str_radix(max_clean, 8),
-        ) {
-            mode_val > max_val
-        } else {
-            false
+        match (parse_mode(mode), parse_mode(max_mode)) {
+            (Some(mode_val), Some(max_val)) => mode_val & !max_val != 0,
+            _ => false,
         }
     }
+}
 
-    fn find_line_number(&self, content: &str, search_term: &str, occurrence: usize) -> usize {
-        let mut count = 0;
-        for (line_num, line) in content.lines().enumerate() {
-            if line.contains(search_term) {
-                if count == occurrence {
-                    return line_num + 1;
-                }
-                count += 1;
-            }
-        }
-        1 // Default to line 1 if not found
-    }
+/// An octal file mode written as text, such as `0644`, `644` or `0o644`.
+pub fn parse_mode(mode: &str) -> Option<u32> {
+    let digits = mode.trim().trim_start_matches("0o");
+    u32::from_str_radix(digits, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o7777)
 }
@@ -0,0 +1,124 @@
+use lazy_static::lazy_static;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::overrides::{OverrideSeverity, RuleOverride, RuleOverrides};
+
+/// The bundled `rules/profiles.yml`.
+const BUILTIN_PROFILES: &str = include_str!("../rules/profiles.yml");
+
+/// Profile used when none is selected.
+pub const DEFAULT_PROFILE: &str = "default";
+
+lazy_static! {
+    static ref BUILTIN: BTreeMap<String, Profile> =
+        serde_yaml::from_str::<ProfilesFile>(BUILTIN_PROFILES)
+            .expect("valid built-in profiles")
+            .profiles;
+}
+
+/// A named set of rule overrides, selected with `scan --profile` or
+/// `general.profile`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Profile whose settings this one starts from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    /// Rules below this severity are disabled unless a `rules:` entry
+    /// enables them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_severity: Option<OverrideSeverity>,
+    #[serde(skip_serializing_if = "RuleOverrides::is_empty")]
+    #[schemars(with = "BTreeMap<String, RuleOverride>")]
+    pub rules: RuleOverrides,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProfilesFile {
+    profiles: BTreeMap<String, Profile>,
+}
+
+/// `name` and the profiles it extends, outermost first. Errors name an
+/// unknown profile or a cycle of `extends:`.
+pub fn chain<'a>(
+    name: &str,
+    profiles: &'a BTreeMap<String, Profile>,
+) -> Result<Vec<(String, &'a Profile)>, String> {
+    let lookup = |name: &str| profiles.get(name).or_else(|| BUILTIN.get(name));
+
+    let mut chain: Vec<(String, &Profile)> = Vec::new();
+    let mut next = Some(name.to_string());
+    while let Some(name) = next {
+        if chain.iter().any(|(n, _)| *n == name) {
+            let names: Vec<&str> = chain.iter().map(|(n, _)| n.as_str()).collect();
+            return Err(format!(
+                "profile `extends:` cycle: {} -> {}",
+                names.join(" -> "),
+                name
+            ));
+        }
+        let Some(profile) = lookup(&name) else {
+            let mut available: Vec<&str> = BUILTIN
+                .keys()
+                .chain(profiles.keys())
+                .map(String::as_str)
+                .collect();
+            available.sort_unstable();
+            available.dedup();
+            return Err(format!(
+                "unknown profile `{}`; available: {}",
+                name,
+                available.join(", ")
+            ));
+        };
+        next = profile.extends.clone();
+        chain.push((name, profile));
+    }
+    Ok(chain)
+}
+
+/// The overrides of `name` and the profiles it extends, base profile
+/// first, so a profile's entries win over those it inherits.
+pub fn resolve(name: &str, profiles: &BTreeMap<String, Profile>) -> Result<RuleOverrides, String> {
+    let mut overrides = RuleOverrides::default();
+    for (_, profile) in chain(name, profiles)?.into_iter().rev() {
+        overrides.extend(profile.rules.clone());
+        if profile.min_severity.is_some() {
+            overrides.min_severity = profile.min_severity;
+        }
+    }
+    Ok(overrides)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_profiles() {
+        let names: Vec<&str> = BUILTIN.keys().map(String::as_str).collect();
+        assert_eq!(names, ["default", "minimal", "production", "strict"]);
+
+        let user = BTreeMap::new();
+        for name in names {
+            resolve(name, &user).unwrap();
+        }
+        let production = resolve("production", &user).unwrap();
+        assert_eq!(production.for_rule("LINT_001").enabled, Some(true));
+        assert_eq!(production.for_rule("LINT_006").enabled, Some(false));
+    }
+
+    #[test]
+    fn test_profile_errors() {
+        let profiles: BTreeMap<String, Profile> =
+            serde_yaml::from_str("a: {extends: b}\nb: {extends: a}\n").unwrap();
+        assert!(chain("a", &profiles).unwrap_err().contains("a -> b -> a"));
+        assert!(chain("nope", &profiles)
+            .unwrap_err()
+            .contains("available: a, b, default"));
+    }
+}
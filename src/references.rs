@@ -0,0 +1,123 @@
+use std::path::Path;
+
+use crate::config::Config;
+use crate::errors::Result;
+use crate::graph::{DependencyGraph, RefKind};
+use crate::overrides::RuleOverrides;
+use crate::rules::RuleListing;
+use crate::scanner::Finding;
+
+/// Every broken reference rule: ID, default severity and what it reports.
+pub const REFERENCE_RULES: &[(&str, &str, &str)] = &[
+    (
+        "REF_001",
+        "MEDIUM",
+        "Included or imported file does not exist",
+    ),
+    (
+        "REF_002",
+        "MEDIUM",
+        "Role or its tasks_from file does not exist",
+    ),
+    ("REF_003", "MEDIUM", "Template source does not exist"),
+    ("REF_004", "MEDIUM", "Copied file does not exist"),
+];
+
+/// Reports references of the dependency graph that resolve to nothing.
+/// Targets with Jinja expressions are only known at run time and skipped.
+pub struct ReferenceChecker {
+    rules: RuleOverrides,
+}
+
+impl ReferenceChecker {
+    pub fn new(config: &Config) -> Result<Self> {
+        Ok(Self {
+            rules: config.effective_rules()?,
+        })
+    }
+
+    /// A finding for each missing target named in `path`.
+    pub fn check(&self, graph: &DependencyGraph, path: &Path) -> Vec<Finding> {
+        let mut findings = Vec::new();
+
+        for reference in graph.references(path) {
+            if reference.resolved.is_some() || reference.is_templated() {
+                continue;
+            }
+
+            let (rule_id, message) = match reference.kind {
+                RefKind::ImportPlaybook | RefKind::IncludeTasks | RefKind::ImportTasks => {
+                    ("REF_001", format!("'{}' not found", reference.target))
+                }
+                RefKind::Template => (
+                    "REF_003",
+                    format!("Template '{}' not found", reference.target),
+                ),
+                RefKind::Copy => ("REF_004", format!("File '{}' not found", reference.target)),
+                _ => match reference.tasks_from {
+                    Some(ref tasks_from) => (
+                        "REF_002",
+                        format!(
+                            "Role '{}' or its tasks file '{}' not found",
+                            reference.target, tasks_from
+                        ),
+                    ),
+                    None => ("REF_002", format!("Role '{}' not found", reference.target)),
+                },
+            };
+
+            let Some(&(_, severity, _)) = REFERENCE_RULES.iter().find(|(id, _, _)| *id == rule_id)
+            else {
+                continue;
+            };
+            let mut severity = severity.to_string();
+            let mut enabled = true;
+            self.rules.apply(rule_id, &mut severity, &mut enabled);
+            if !enabled {
+                continue;
+            }
+
+            let span = reference.span;
+            findings.push(Finding {
+                line: span.start.line,
+                column: span.start.column,
+                end_line: span.end.line,
+                end_column: span.end.column,
+                severity,
+                rule_id: rule_id.to_string(),
+                message,
+                context: Some(format!("{}: {}", reference.kind.as_str(), reference.target)),
+                remediation: Some(
+                    "Fix the name, or add the missing file to the role or next to the playbook"
+                        .to_string(),
+                ),
+                suppression: None,
+                baselined: false,
+            });
+        }
+
+        findings
+    }
+
+    /// The reference rules as shown by `rules list`.
+    pub fn listings(&self) -> Vec<RuleListing> {
+        REFERENCE_RULES
+            .iter()
+            .map(|&(id, severity, name)| {
+                let mut severity = severity.to_string();
+                let mut enabled = true;
+                let overridden = self.rules.apply(id, &mut severity, &mut enabled);
+                RuleListing {
+                    id: id.to_string(),
+                    name: name.to_string(),
+                    severity,
+                    enabled,
+                    description: String::new(),
+                    remediation: None,
+                    manual: false,
+                    overridden,
+                }
+            })
+            .collect()
+    }
+}
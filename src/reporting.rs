@@ -3,7 +3,7 @@ use std::fs;
 use std::path::Path;
 
 use crate::errors::Result;
-use crate::scanner::ScanFindings;
+use crate::scanner::{FileFinding, Finding, ScanFindings};
 
 pub struct Reporter {
     format: OutputFormat,
@@ -76,6 +76,18 @@ impl Reporter {
             "   ├─ Total findings: {}\n",
             findings.total_findings().to_string().bold().white()
         ));
+        if findings.summary.suppressed > 0 {
+            output.push_str(&format!(
+                "   ├─ Suppressed: {}\n",
+                findings.summary.suppressed.to_string().dimmed()
+            ));
+        }
+        if findings.summary.baselined > 0 {
+            output.push_str(&format!(
+                "   ├─ In baseline: {}\n",
+                findings.summary.baselined.to_string().dimmed()
+            ));
+        }
         output.push_str(&format!(
             "   └─ Scan date: {}\n\n",
             chrono::Utc::now()
@@ -106,33 +118,24 @@ impl Reporter {
         output.push('\n');
 
         // Secrets section with enhanced formatting
-        if !findings.secrets.is_empty() {
+        let secrets_files =
+            self.active_findings(&findings.secrets, |f| f.rule_id.starts_with("SECRET"));
+        if !secrets_files.is_empty() {
             output.push_str(&format!("╭─{}─╮\n", "─".repeat(76)));
             output.push_str(&format!("│ 🔑 {:^73} │\n", "SECRETS DETECTED".bold().red()));
             output.push_str(&format!("╰─{}─╯\n", "─".repeat(76)));
 
-            for (idx, file_finding) in findings.secrets.iter().enumerate() {
-                output.push_str(&format!(
-                    "\n📄 {} {}\n",
-                    format!("[{}]", idx + 1).dimmed(),
-                    file_finding.file_path.display().to_string().bold().white()
-                ));
-
-                let secret_findings: Vec<_> = file_finding
-                    .findings
-                    .iter()
-                    .filter(|f| f.rule_id.starts_with("SECRET"))
-                    .collect();
+            for (idx, (file_finding, secret_findings)) in secrets_files.iter().enumerate() {
+                output.push_str(&self.file_header(idx, file_finding));
 
                 for (finding_idx, finding) in secret_findings.iter().enumerate() {
                     let is_last = finding_idx == secret_findings.len() - 1;
                     let prefix = if is_last { "└─" } else { "├─" };
 
                     output.push_str(&format!(
-                        "   {} Line {} Col {} │ {} │ {}\n",
+                        "   {} Line {} │ {} │ {}\n",
                         prefix,
-                        format!("{:>4}", finding.line).cyan(),
-                        format!("{:>3}", finding.column).cyan(),
+                        self.format_range(finding).cyan(),
                         self.colorize_severity_box(&finding.severity),
                         finding.message.white()
                     ));
@@ -156,7 +159,9 @@ impl Reporter {
         }
 
         // Policy Violations with enhanced formatting
-        if !findings.policy_violations.is_empty() {
+        let policy_violations_files =
+            self.active_findings(&findings.policy_violations, |f| f.is_policy());
+        if !policy_violations_files.is_empty() {
             output.push_str(&format!("╭─{}─╮\n", "─".repeat(76)));
             output.push_str(&format!(
                 "│ ⚠️  {:^73} │\n",
@@ -164,28 +169,18 @@ impl Reporter {
             ));
             output.push_str(&format!("╰─{}─╯\n", "─".repeat(76)));
 
-            for (idx, file_finding) in findings.policy_violations.iter().enumerate() {
-                output.push_str(&format!(
-                    "\n📄 {} {}\n",
-                    format!("[{}]", idx + 1).dimmed(),
-                    file_finding.file_path.display().to_string().bold().white()
-                ));
-
-                let policy_findings: Vec<_> = file_finding
-                    .findings
-                    .iter()
-                    .filter(|f| f.rule_id.starts_with("POLICY"))
-                    .collect();
+            for (idx, (file_finding, policy_findings)) in policy_violations_files.iter().enumerate()
+            {
+                output.push_str(&self.file_header(idx, file_finding));
 
                 for (finding_idx, finding) in policy_findings.iter().enumerate() {
                     let is_last = finding_idx == policy_findings.len() - 1;
                     let prefix = if is_last { "└─" } else { "├─" };
 
                     output.push_str(&format!(
-                        "   {} Line {} Col {} │ {} │ {}\n",
+                        "   {} Line {} │ {} │ {}\n",
                         prefix,
-                        format!("{:>4}", finding.line).cyan(),
-                        format!("{:>3}", finding.column).cyan(),
+                        self.format_range(finding).cyan(),
                         self.colorize_severity_box(&finding.severity),
                         finding.message.white()
                     ));
@@ -197,11 +192,11 @@ impl Reporter {
                         finding.rule_id.dimmed()
                     ));
 
-                    if let Some(ref context) = finding.context {
+                    if let Some(fix) = finding.remediation.as_ref().or(finding.context.as_ref()) {
                         output.push_str(&format!(
                             "   {}    ↳ Fix: {}\n",
                             context_prefix,
-                            context.bright_blue()
+                            fix.bright_blue()
                         ));
                     }
                 }
@@ -210,23 +205,15 @@ impl Reporter {
         }
 
         // Lint Issues with enhanced formatting
-        if !findings.lint_issues.is_empty() {
+        let lint_issues_files =
+            self.active_findings(&findings.lint_issues, |f| f.rule_id.starts_with("LINT"));
+        if !lint_issues_files.is_empty() {
             output.push_str(&format!("╭─{}─╮\n", "─".repeat(76)));
             output.push_str(&format!("│ 🧹 {:^73} │\n", "LINTING ISSUES".bold().blue()));
             output.push_str(&format!("╰─{}─╯\n", "─".repeat(76)));
 
-            for (idx, file_finding) in findings.lint_issues.iter().enumerate() {
-                output.push_str(&format!(
-                    "\n📄 {} {}\n",
-                    format!("[{}]", idx + 1).dimmed(),
-                    file_finding.file_path.display().to_string().bold().white()
-                ));
-
-                let lint_findings: Vec<_> = file_finding
-                    .findings
-                    .iter()
-                    .filter(|f| f.rule_id.starts_with("LINT"))
-                    .collect();
+            for (idx, (file_finding, lint_findings)) in lint_issues_files.iter().enumerate() {
+                output.push_str(&self.file_header(idx, file_finding));
 
                 for (finding_idx, finding) in lint_findings.iter().enumerate() {
                     let is_last = finding_idx == lint_findings.len() - 1;
@@ -235,7 +222,7 @@ impl Reporter {
                     output.push_str(&format!(
                         "   {} Line {} │ {} │ {}\n",
                         prefix,
-                        format!("{:>4}", finding.line).cyan(),
+                        self.format_range(finding).cyan(),
                         self.colorize_severity_box(&finding.severity),
                         finding.message.white()
                     ));
@@ -244,6 +231,33 @@ impl Reporter {
             output.push('\n');
         }
 
+        // Baseline entries that no longer occur
+        if !findings.fixed.is_empty() {
+            output.push_str(&format!("╭─{}─╮\n", "─".repeat(76)));
+            output.push_str(&format!(
+                "│ ✅ {:^73} │\n",
+                "FIXED SINCE BASELINE".bold().green()
+            ));
+            output.push_str(&format!("╰─{}─╯\n", "─".repeat(76)));
+
+            for (idx, entry) in findings.fixed.iter().enumerate() {
+                let prefix = if idx == findings.fixed.len() - 1 {
+                    "└─"
+                } else {
+                    "├─"
+                };
+                output.push_str(&format!(
+                    "   {} {}:{} │ {} │ {}\n",
+                    prefix,
+                    entry.file.white(),
+                    entry.line,
+                    entry.rule_id.dimmed(),
+                    entry.message
+                ));
+            }
+            output.push('\n');
+        }
+
         // Final summary with recommendations
         output.push_str(&format!("╔{}╗\n", "═".repeat(78)));
 
@@ -308,6 +322,70 @@ impl Reporter {
         output
     }
 
+    /// Files with active findings of one category, with those findings.
+    fn active_findings<'a>(
+        &self,
+        files: &'a [FileFinding],
+        category: fn(&Finding) -> bool,
+    ) -> Vec<(&'a FileFinding, Vec<&'a Finding>)> {
+        files
+            .iter()
+            .map(|file_finding| {
+                let active = file_finding
+                    .findings
+                    .iter()
+                    .filter(|f| category(f) && f.is_active())
+                    .collect::<Vec<_>>();
+                (file_finding, active)
+            })
+            .filter(|(_, active)| !active.is_empty())
+            .collect()
+    }
+
+    /// The numbered file path, with its kind, the config file that applied
+    /// to it and the chains of includes that reach it.
+    fn file_header(&self, idx: usize, file_finding: &FileFinding) -> String {
+        let config = match file_finding.config {
+            Some(ref config) => format!(" (config: {})", config.display())
+                .dimmed()
+                .to_string(),
+            None => String::new(),
+        };
+        let mut header = format!(
+            "\n📄 {} {} {}{}\n",
+            format!("[{}]", idx + 1).dimmed(),
+            file_finding.file_path.display().to_string().bold().white(),
+            format!("[{}]", file_finding.kind.as_str()).dimmed(),
+            config
+        );
+        for chain in &file_finding.chains {
+            let chain: Vec<String> = chain
+                .iter()
+                .map(|file| file.display().to_string())
+                .collect();
+            header.push_str(&format!(
+                "   {}\n",
+                format!("↳ via {}", chain.join(" → ")).dimmed()
+            ));
+        }
+        header
+    }
+
+    /// Source range as `line:col-col`, or `line:col-line:col` across lines.
+    fn format_range(&self, finding: &Finding) -> String {
+        let range = if finding.end_line == 0 || finding.end_column == 0 {
+            format!("{}:{}", finding.line, finding.column)
+        } else if finding.end_line == finding.line {
+            format!("{}:{}-{}", finding.line, finding.column, finding.end_column)
+        } else {
+            format!(
+                "{}:{}-{}:{}",
+                finding.line, finding.column, finding.end_line, finding.end_column
+            )
+        };
+        format!("{:<12}", range)
+    }
+
     fn format_severity_bar(&self, severity: &str, count: usize, total: f32) -> String {
         let percentage = (count as f32 / total * 100.0) as usize;
         let bar_width = 40;
@@ -357,31 +435,56 @@ impl Reporter {
         let mut results = Vec::new();
 
         // Convert findings to SARIF results
-        for file_finding in findings
-            .secrets
-            .iter()
-            .chain(findings.policy_violations.iter())
-            .chain(findings.lint_issues.iter())
-        {
+        for file_finding in findings.files() {
             for finding in &file_finding.findings {
-                results.push(serde_json::json!({
+                let mut result = serde_json::json!({
                     "ruleId": finding.rule_id,
                     "level": self.severity_to_sarif_level(&finding.severity),
                     "message": {
                         "text": finding.message
                     },
+                    "baselineState": if finding.baselined { "unchanged" } else { "new" },
                     "locations": [{
                         "physicalLocation": {
                             "artifactLocation": {
                                 "uri": file_finding.file_path.to_string_lossy()
                             },
-                            "region": {
-                                "startLine": finding.line,
-                                "startColumn": finding.column
-                            }
+                            "region": self.sarif_region(finding)
                         }
                     }]
-                }));
+                });
+
+                let mut properties = serde_json::Map::new();
+                properties.insert(
+                    "kind".to_string(),
+                    serde_json::json!(file_finding.kind.as_str()),
+                );
+                if let Some(ref remediation) = finding.remediation {
+                    properties.insert("remediation".to_string(), serde_json::json!(remediation));
+                }
+                if let Some(ref config) = file_finding.config {
+                    properties.insert(
+                        "config".to_string(),
+                        serde_json::json!(config.to_string_lossy()),
+                    );
+                }
+                if !file_finding.chains.is_empty() {
+                    properties.insert("chains".to_string(), serde_json::json!(file_finding.chains));
+                }
+                result["properties"] = serde_json::Value::Object(properties);
+
+                if let Some(ref suppression) = finding.suppression {
+                    let mut entry = serde_json::json!({ "kind": "inSource" });
+                    if let Some(ref reason) = suppression.reason {
+                        entry["justification"] = serde_json::json!(reason);
+                    }
+                    if let Some(until) = suppression.until {
+                        entry["properties"] = serde_json::json!({ "until": until.to_string() });
+                    }
+                    result["suppressions"] = serde_json::json!([entry]);
+                }
+
+                results.push(result);
             }
         }
 
@@ -435,6 +538,21 @@ impl Reporter {
         }
     }
 
+    fn sarif_region(&self, finding: &Finding) -> serde_json::Value {
+        let mut region = serde_json::json!({
+            "startLine": finding.line,
+            "startColumn": finding.column.max(1)
+        });
+
+        // Findings deserialized from older reports have no end position
+        if finding.end_line >= finding.line && finding.end_column > 0 {
+            region["endLine"] = serde_json::json!(finding.end_line);
+            region["endColumn"] = serde_json::json!(finding.end_column);
+        }
+
+        region
+    }
+
     fn severity_to_sarif_level(&self, severity: &str) -> &str {
         match severity {
             "CRITICAL" | "HIGH" => "error",
@@ -0,0 +1,540 @@
+use serde_yaml::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::errors::Result;
+use crate::graph::{self, DependencyGraph, RefKind, Reference};
+use crate::model::{as_ansible_bool, Document, FileKind};
+use crate::overrides::RuleOverrides;
+use crate::rules::RuleListing;
+use crate::scanner::Finding;
+use crate::yaml::{self, PathSegment, Span};
+
+/// Every role analysis rule: ID, default severity and what it reports.
+pub const ROLE_RULES: &[(&str, &str, &str)] = &[
+    ("ROLE_001", "HIGH", "Circular role dependency"),
+    (
+        "ROLE_002",
+        "MEDIUM",
+        "Role applied more than once with conflicting parameters",
+    ),
+    (
+        "ROLE_003",
+        "LOW",
+        "Role declared in requirements but never used",
+    ),
+    (
+        "ROLE_004",
+        "MEDIUM",
+        "External role used but not declared in requirements",
+    ),
+];
+
+/// A problem with how roles depend on each other or are declared, found
+/// once for the whole graph and reported in the file it is in.
+#[derive(Debug, Clone)]
+pub struct RoleIssue {
+    pub rule_id: &'static str,
+    pub span: Span,
+    pub message: String,
+    pub context: String,
+}
+
+/// A role entry of a `requirements.yml`.
+#[derive(Debug, Clone)]
+pub struct Requirement {
+    /// The name the role is installed under
+    pub name: String,
+    pub span: Span,
+}
+
+/// Role dependency cycles, roles applied twice in a play with different
+/// parameters, and roles that `requirements.yml` files declare but nothing
+/// uses or that are used but not declared.
+#[derive(Debug, Default)]
+pub struct RoleAnalysis {
+    issues: BTreeMap<PathBuf, Vec<RoleIssue>>,
+}
+
+impl RoleAnalysis {
+    /// Analyze the roles of a scan of `root`.
+    pub fn new(graph: &DependencyGraph, root: &Path) -> Self {
+        let mut analysis = Self::default();
+        analysis.find_cycles(graph);
+        analysis.find_duplicates(graph);
+        analysis.check_requirements(graph, root);
+        for issues in analysis.issues.values_mut() {
+            issues.sort_by_key(|issue| (issue.span.start, issue.rule_id));
+            issues.dedup_by(|a, b| a.rule_id == b.rule_id && a.span == b.span);
+        }
+        analysis
+    }
+
+    /// The issues found in `path`.
+    pub fn issues(&self, path: &Path) -> &[RoleIssue] {
+        self.issues
+            .get(&graph::normalize(path))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    fn push(&mut self, file: &Path, issue: RoleIssue) {
+        self.issues
+            .entry(file.to_path_buf())
+            .or_default()
+            .push(issue);
+    }
+
+    /// Report each cycle of `dependencies:` once, on the dependency of its
+    /// first role (by path) that starts it.
+    fn find_cycles(&mut self, graph: &DependencyGraph) {
+        // Role directory -> (meta file, dependency) for each resolved dependency
+        let mut edges: BTreeMap<PathBuf, Vec<(&Path, &Reference)>> = BTreeMap::new();
+        for (file, kind) in graph.files() {
+            if kind != FileKind::RoleMeta {
+                continue;
+            }
+            let Some(role) = graph::role_root(file) else {
+                continue;
+            };
+            for reference in graph.references(file) {
+                if reference.kind == RefKind::RoleDependency && reference.resolved.is_some() {
+                    edges
+                        .entry(role.clone())
+                        .or_default()
+                        .push((file, reference));
+                }
+            }
+        }
+
+        let mut cycles = BTreeSet::new();
+        for start in edges.keys() {
+            let mut stack = vec![start.clone()];
+            find_cycles_from(&edges, &mut stack, &mut cycles);
+        }
+
+        for cycle in cycles {
+            let next = &cycle[1 % cycle.len()];
+            let Some(&(meta, reference)) = edges[&cycle[0]]
+                .iter()
+                .find(|(_, reference)| reference.resolved.as_ref() == Some(next))
+            else {
+                continue;
+            };
+            let names: Vec<String> = cycle
+                .iter()
+                .chain(cycle.first())
+                .map(|role| role_name(role))
+                .collect();
+            self.push(
+                meta,
+                RoleIssue {
+                    rule_id: "ROLE_001",
+                    span: reference.span,
+                    message: format!("Role dependency cycle: {}", names.join(" -> ")),
+                    context: format!("dependencies: {}", reference.target),
+                },
+            );
+        }
+    }
+
+    /// Ansible runs a role once per play unless it is applied again with
+    /// different parameters; report those repeats, following `roles:` into
+    /// their dependencies. Roles with `allow_duplicates: true` are meant to
+    /// run more than once.
+    fn find_duplicates(&mut self, graph: &DependencyGraph) {
+        for (file, kind) in graph.files() {
+            if kind != FileKind::Playbook {
+                continue;
+            }
+            let mut plays: BTreeMap<usize, Vec<&Reference>> = BTreeMap::new();
+            for reference in graph.references(file) {
+                if let (RefKind::Role, Some(play)) = (reference.kind, reference.play) {
+                    plays.entry(play).or_default().push(reference);
+                }
+            }
+
+            for roles in plays.values() {
+                let mut applied: Vec<(&Path, &Reference)> = Vec::new();
+                let mut visited = BTreeSet::new();
+                for reference in roles {
+                    collect_applied(graph, file, reference, &mut applied, &mut visited);
+                }
+
+                let mut first: BTreeMap<&Path, (&Path, &Reference)> = BTreeMap::new();
+                for (from, reference) in applied {
+                    let Some(role) = reference.resolved.as_deref() else {
+                        continue;
+                    };
+                    let Some(&(first_file, first_ref)) = first.get(role) else {
+                        first.insert(role, (from, reference));
+                        continue;
+                    };
+                    if first_ref.params == reference.params || allows_duplicates(reference) {
+                        continue;
+                    }
+                    self.push(
+                        from,
+                        RoleIssue {
+                            rule_id: "ROLE_002",
+                            span: reference.span,
+                            message: format!(
+                                "Role '{}' is applied again with different parameters (first at {}:{}) and will run twice",
+                                reference.target,
+                                first_file.display(),
+                                first_ref.span.start.line
+                            ),
+                            context: format!("{}: {}", reference.kind.as_str(), reference.target),
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    /// Compare the roles `requirements.yml` files declare with the roles
+    /// used. Undeclared roles are only reported when a requirements file
+    /// exists, and only external ones: roles not found under `root` or
+    /// named `namespace.role`.
+    fn check_requirements(&mut self, graph: &DependencyGraph, root: &Path) {
+        let mut declared: BTreeMap<PathBuf, Vec<Requirement>> = BTreeMap::new();
+        let mut collections = BTreeSet::new();
+        for (file, kind) in graph.files() {
+            if kind != FileKind::Requirements {
+                continue;
+            }
+            let Ok(content) = fs::read_to_string(file) else {
+                continue;
+            };
+            let file_doc = Document::parse_as(file, &content, kind);
+            let requirements = declared.entry(file.to_path_buf()).or_default();
+            for doc in file_doc.documents() {
+                let (roles, declared_collections) = parse_requirements(doc);
+                requirements.extend(roles);
+                collections.extend(declared_collections);
+            }
+        }
+        if declared.is_empty() {
+            return;
+        }
+
+        let root = graph::normalize(if root.is_file() {
+            root.parent().unwrap_or(Path::new(""))
+        } else {
+            root
+        });
+        let mut used: BTreeSet<String> = BTreeSet::new();
+        let mut undeclared: Vec<(&Path, &Reference)> = Vec::new();
+        let is_declared = |name: &str| {
+            declared.values().flatten().any(|req| req.name == name)
+                || name
+                    .rsplit_once('.')
+                    .is_some_and(|(collection, _)| collections.contains(collection))
+        };
+        for (file, _) in graph.files() {
+            for reference in graph.references(file) {
+                if !reference.kind.is_role() || reference.is_templated() {
+                    continue;
+                }
+                used.insert(reference.target.clone());
+                if let Some(ref dir) = reference.resolved {
+                    used.insert(role_name(dir));
+                }
+
+                let external = match reference.resolved {
+                    Some(ref dir) => !dir.starts_with(&root) || reference.target.contains('.'),
+                    None => true,
+                };
+                let name = reference
+                    .resolved
+                    .as_deref()
+                    .filter(|_| reference.tasks_from.is_none())
+                    .map(role_name);
+                if external
+                    && !is_declared(&reference.target)
+                    && !name.as_deref().is_some_and(is_declared)
+                {
+                    undeclared.push((file, reference));
+                }
+            }
+        }
+
+        for (file, reference) in undeclared {
+            self.push(
+                file,
+                RoleIssue {
+                    rule_id: "ROLE_004",
+                    span: reference.span,
+                    message: format!(
+                        "Role '{}' is not declared in requirements.yml",
+                        reference.target
+                    ),
+                    context: format!("{}: {}", reference.kind.as_str(), reference.target),
+                },
+            );
+        }
+        for (file, requirements) in &declared {
+            for requirement in requirements {
+                if used.contains(&requirement.name) {
+                    continue;
+                }
+                self.push(
+                    file,
+                    RoleIssue {
+                        rule_id: "ROLE_003",
+                        span: requirement.span,
+                        message: format!(
+                            "Role '{}' is declared but no playbook or role uses it",
+                            requirement.name
+                        ),
+                        context: format!("roles: {}", requirement.name),
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// Depth-first search for cycles through the roles on `stack`. Each cycle
+/// is recorded rotated to start at its smallest role.
+fn find_cycles_from(
+    edges: &BTreeMap<PathBuf, Vec<(&Path, &Reference)>>,
+    stack: &mut Vec<PathBuf>,
+    cycles: &mut BTreeSet<Vec<PathBuf>>,
+) {
+    let Some(current) = stack.last().cloned() else {
+        return;
+    };
+    for (_, reference) in edges.get(&current).into_iter().flatten() {
+        let Some(ref next) = reference.resolved else {
+            continue;
+        };
+        if let Some(pos) = stack.iter().position(|role| role == next) {
+            let mut cycle = stack[pos..].to_vec();
+            if let Some(min) = (0..cycle.len()).min_by_key(|&idx| &cycle[idx]) {
+                cycle.rotate_left(min);
+            }
+            cycles.insert(cycle);
+        } else if edges.contains_key(next) {
+            stack.push(next.clone());
+            find_cycles_from(edges, stack, cycles);
+            stack.pop();
+        }
+    }
+}
+
+/// The role `reference` applies and, recursively, its dependencies, each
+/// with the file it is named in.
+fn collect_applied<'a>(
+    graph: &'a DependencyGraph,
+    from: &'a Path,
+    reference: &'a Reference,
+    applied: &mut Vec<(&'a Path, &'a Reference)>,
+    visited: &mut BTreeSet<&'a Path>,
+) {
+    // Dependencies run before the role that needs them
+    if let Some(meta) = meta_file(reference) {
+        if visited.insert(meta) {
+            for dependency in graph.references(meta) {
+                if dependency.kind == RefKind::RoleDependency {
+                    collect_applied(graph, meta, dependency, applied, visited);
+                }
+            }
+        }
+    }
+    applied.push((from, reference));
+}
+
+/// The `meta/main.yml` of the role a reference applies.
+fn meta_file(reference: &Reference) -> Option<&Path> {
+    reference
+        .files
+        .iter()
+        .find(|file| {
+            file.parent()
+                .and_then(Path::file_name)
+                .is_some_and(|dir| dir == "meta")
+        })
+        .map(PathBuf::as_path)
+}
+
+/// Whether the role's `meta/main.yml` sets `allow_duplicates: true`.
+fn allows_duplicates(reference: &Reference) -> bool {
+    meta_file(reference)
+        .and_then(|meta| fs::read_to_string(meta).ok())
+        .and_then(|content| serde_yaml::from_str::<Value>(&content).ok())
+        .and_then(|meta| meta.get("allow_duplicates").and_then(as_ansible_bool))
+        .unwrap_or(false)
+}
+
+fn role_name(dir: &Path) -> String {
+    dir.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// The roles and collection names one document of a requirements file
+/// declares, in either the `roles:`/`collections:` form or the older bare
+/// list of roles.
+pub fn parse_requirements(doc: &Document) -> (Vec<Requirement>, Vec<String>) {
+    let (roles, roles_path) = match doc.root {
+        Some(Value::Sequence(ref roles)) => (Some(roles), Vec::new()),
+        Some(ref root) => (
+            root.get("roles").and_then(Value::as_sequence),
+            yaml::key(&[], "roles"),
+        ),
+        None => (None, Vec::new()),
+    };
+
+    let requirements = roles
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .filter_map(|(idx, entry)| {
+            let name = match entry {
+                Value::String(src) => requirement_name(None, src),
+                Value::Mapping(obj) => {
+                    let get = |key: &str| obj.get(key).and_then(Value::as_str);
+                    requirement_name(get("name"), get("src")?)
+                }
+                _ => return None,
+            };
+            let path = yaml::child(&roles_path, PathSegment::Index(idx));
+            Some(Requirement {
+                name,
+                span: doc.value_span(&path),
+            })
+        })
+        .collect();
+
+    let collections = doc
+        .root
+        .as_ref()
+        .and_then(|root| root.get("collections"))
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| match entry {
+            Value::String(name) => Some(name.clone()),
+            Value::Mapping(obj) => obj.get("name").and_then(Value::as_str).map(str::to_string),
+            _ => None,
+        })
+        .collect();
+
+    (requirements, collections)
+}
+
+/// The name a role is installed under: `name`, or derived from `src` as
+/// `ansible-galaxy` does, e.g. `https://github.com/x/role.git,v1` is `role`.
+fn requirement_name(name: Option<&str>, src: &str) -> String {
+    if let Some(name) = name {
+        return name.to_string();
+    }
+    let src = src.split(',').next().unwrap_or(src).trim();
+    if !src.contains("://") && !src.contains('@') {
+        return src.to_string();
+    }
+    let last = src.trim_end_matches('/').rsplit('/').next().unwrap_or(src);
+    last.trim_end_matches(".tar.gz")
+        .trim_end_matches(".git")
+        .to_string()
+}
+
+/// Reports the issues of a [`RoleAnalysis`] as findings.
+pub struct RoleChecker {
+    rules: RuleOverrides,
+}
+
+impl RoleChecker {
+    pub fn new(config: &Config) -> Result<Self> {
+        Ok(Self {
+            rules: config.effective_rules()?,
+        })
+    }
+
+    /// A finding for each issue found in `path`.
+    pub fn check(&self, analysis: &RoleAnalysis, path: &Path) -> Vec<Finding> {
+        let mut findings = Vec::new();
+
+        for issue in analysis.issues(path) {
+            let Some(&(_, severity, _)) = ROLE_RULES.iter().find(|(id, _, _)| *id == issue.rule_id)
+            else {
+                continue;
+            };
+            let mut severity = severity.to_string();
+            let mut enabled = true;
+            self.rules.apply(issue.rule_id, &mut severity, &mut enabled);
+            if !enabled {
+                continue;
+            }
+
+            let remediation = match issue.rule_id {
+                "ROLE_001" => "Remove one of the dependencies that close the cycle",
+                "ROLE_002" => {
+                    "Pass the same parameters each time, or set allow_duplicates: true in the role's meta/main.yml if it should run twice"
+                }
+                "ROLE_003" => "Remove the role from requirements.yml",
+                _ => "Add the role to requirements.yml so ansible-galaxy installs it",
+            };
+            let span = issue.span;
+            findings.push(Finding {
+                line: span.start.line,
+                column: span.start.column,
+                end_line: span.end.line,
+                end_column: span.end.column,
+                severity,
+                rule_id: issue.rule_id.to_string(),
+                message: issue.message.clone(),
+                context: Some(issue.context.clone()),
+                remediation: Some(remediation.to_string()),
+                suppression: None,
+                baselined: false,
+            });
+        }
+
+        findings
+    }
+
+    /// The role rules as shown by `rules list`.
+    pub fn listings(&self) -> Vec<RuleListing> {
+        ROLE_RULES
+            .iter()
+            .map(|&(id, severity, name)| {
+                let mut severity = severity.to_string();
+                let mut enabled = true;
+                let overridden = self.rules.apply(id, &mut severity, &mut enabled);
+                RuleListing {
+                    id: id.to_string(),
+                    name: name.to_string(),
+                    severity,
+                    enabled,
+                    description: String::new(),
+                    remediation: None,
+                    manual: false,
+                    overridden,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requirement_name() {
+        assert_eq!(
+            requirement_name(None, "geerlingguy.nginx"),
+            "geerlingguy.nginx"
+        );
+        assert_eq!(
+            requirement_name(None, "https://github.com/acme/ansible-web.git,v1.2"),
+            "ansible-web"
+        );
+        assert_eq!(requirement_name(None, "git@github.com:acme/db.git"), "db");
+        assert_eq!(requirement_name(Some("web"), "acme.web"), "web");
+    }
+}
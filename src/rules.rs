@@ -1,10 +1,24 @@
+use log::warn;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
+use crate::config::{Config, PoliciesConfig, DEFAULT_POLICY_RULES};
 use crate::errors::{AnsibleSecError, Result};
+use crate::matcher::TaskSpec;
+use crate::modules;
+use crate::overrides::RuleOverrides;
+use crate::pattern::Pattern;
+use crate::schema;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The bundled `rules/policies.yml`, used when ansiblesec runs outside a
+/// checkout that has the file on disk.
+const SHIPPED_POLICY_RULES: &str = include_str!("../rules/policies.yml");
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(try_from = "RawRule")]
 pub struct Rule {
     pub id: String,
     pub name: String,
@@ -12,10 +26,109 @@ pub struct Rule {
     pub severity: String,
     pub enabled: bool,
     pub rule_type: RuleType,
+    /// How to fix a violation, shown with every finding of the rule
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remediation: Option<String>,
+    #[serde(default, skip_serializing_if = "RuleExamples::is_empty")]
+    pub examples: RuleExamples,
+    /// Globs of the files the rule applies to, relative to the directory of
+    /// the config file naming the rules file; empty applies to every file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+}
+
+/// Snippets a rule must and must not fire on, checked by `rules test`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct RuleExamples {
+    #[serde(default, rename = "match", skip_serializing_if = "Vec::is_empty")]
+    pub matches: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub no_match: Vec<String>,
+}
+
+impl RuleExamples {
+    pub fn is_empty(&self) -> bool {
+        self.matches.is_empty() && self.no_match.is_empty()
+    }
+}
+
+/// Rules as written in a rules file. Besides a tagged `rule_type`, a rule may
+/// use the `module:` and `pattern:` shorthands of the bundled policies.
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct RawRule {
+    id: String,
+    name: String,
+    #[serde(default)]
+    description: String,
+    severity: String,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+    rule_type: Option<RuleType>,
+    module: Option<OneOrMany>,
+    pattern: Option<String>,
+    remediation: Option<String>,
+    #[serde(default)]
+    examples: RuleExamples,
+    #[serde(default)]
+    paths: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl TryFrom<RawRule> for Rule {
+    type Error = String;
+
+    fn try_from(raw: RawRule) -> std::result::Result<Self, Self::Error> {
+        let rule_type = match (raw.rule_type, raw.module, raw.pattern) {
+            (Some(rule_type), None, None) => rule_type,
+            (None, Some(modules), None) => RuleType::Module {
+                modules: match modules {
+                    OneOrMany::One(module) => vec![module],
+                    OneOrMany::Many(modules) => modules,
+                },
+            },
+            (None, None, Some(pattern)) => RuleType::Pattern { pattern },
+            (None, None, None) => {
+                return Err(format!(
+                    "rule {} has no check: set one of `rule_type`, `module` or `pattern`",
+                    raw.id
+                ))
+            }
+            _ => {
+                return Err(format!(
+                    "rule {} sets more than one of `rule_type`, `module` and `pattern`",
+                    raw.id
+                ))
+            }
+        };
+
+        Ok(Self {
+            id: raw.id,
+            name: raw.name,
+            description: raw.description,
+            severity: raw.severity,
+            enabled: raw.enabled,
+            rule_type,
+            remediation: raw.remediation,
+            examples: raw.examples,
+            paths: raw.paths,
+        })
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "type")]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", deny_unknown_fields)]
 pub enum RuleType {
     DisallowModule {
         modules: Vec<String>,
@@ -27,42 +140,387 @@ pub enum RuleType {
     RequireNoLogForSensitive,
     CheckPermissions {
         max_permissions: String,
+        /// Only tasks whose `state` argument has this value, e.g. `directory`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        state: Option<String>,
     },
+    /// A path expression checked in the document, each play or each task
     CustomYamlPath {
         path: String,
+        #[serde(default)]
+        scope: PathScope,
+        /// Defaults to `equals` with an `expected_value`, else `exists`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mode: Option<PathMode>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         expected_value: Option<String>,
+        /// Regular expression for `matches`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        regex: Option<String>,
+    },
+    /// Any use of one of the modules (FQCN-aware)
+    Module {
+        modules: Vec<String>,
     },
+    /// A regular expression matched against each non-comment line
+    Pattern {
+        pattern: String,
+    },
+    /// Tasks selected by module and a condition on their arguments and keywords
+    Task(TaskSpec),
+    /// Guidance that needs manual review; listed but never reported
+    Manual,
+}
+
+/// What a `CustomYamlPath` path is evaluated against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PathScope {
+    /// The document root
+    #[default]
+    Document,
+    /// Every play of a playbook
+    Play,
+    /// Every task, including tasks in blocks, handlers and role task files
+    Task,
+}
+
+/// How a `CustomYamlPath` rule judges the nodes its path selects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PathMode {
+    /// Fires when the path selects nothing
+    Exists,
+    /// Fires for every node the path selects
+    Absent,
+    /// Fires for every selected value that differs from `expected_value`
+    Equals,
+    /// Fires for every selected value that does not match `regex`
+    Matches,
+}
+
+/// Which engine a rules file is written for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RulesFileKind {
+    Policy,
+    Secrets,
+}
+
+impl RulesFileKind {
+    /// The file's `kind:` when it sets one. Otherwise a file whose rules all
+    /// have the shape of secrets patterns, a `pattern` and a `description`
+    /// and none of the keys only policy rules take, holds secrets patterns;
+    /// anything else is read as policy rules.
+    pub fn detect(content: &str) -> Self {
+        let root: serde_yaml::Value = serde_yaml::from_str(content).unwrap_or_default();
+        if let Some(kind) = root
+            .get("kind")
+            .and_then(|kind| serde_yaml::from_value(kind.clone()).ok())
+        {
+            return kind;
+        }
+
+        let rules: Vec<_> = root
+            .get("rules")
+            .and_then(|rules| rules.as_sequence())
+            .map(|rules| rules.iter().collect())
+            .unwrap_or_default();
+        let secret_keys = schema::secret_rule_keys();
+        let policy_only: Vec<_> = schema::policy_rule_keys()
+            .into_iter()
+            .filter(|key| !secret_keys.contains(key))
+            .collect();
+        let is_secret = |rule: &&serde_yaml::Value| {
+            rule.get("pattern").is_some()
+                && rule.get("description").is_some()
+                && policy_only
+                    .iter()
+                    .all(|key| rule.get(key.as_str()).is_none())
+        };
+        if !rules.is_empty() && rules.iter().all(is_secret) {
+            RulesFileKind::Secrets
+        } else {
+            RulesFileKind::Policy
+        }
+    }
+}
+
+/// Whether `content` is an ansiblesec rules file rather than Ansible YAML:
+/// a mapping with a `kind:` of `policy` or `secrets`, or a non-empty
+/// `rules:` list whose entries all have an `id` and a `severity`.
+pub fn is_rules_file(content: &str) -> bool {
+    let Ok(root) = serde_yaml::from_str::<serde_yaml::Value>(content) else {
+        return false;
+    };
+    if root
+        .get("kind")
+        .is_some_and(|kind| serde_yaml::from_value::<RulesFileKind>(kind.clone()).is_ok())
+    {
+        return true;
+    }
+
+    root.get("rules")
+        .and_then(|rules| rules.as_sequence())
+        .is_some_and(|rules| {
+            !rules.is_empty()
+                && rules
+                    .iter()
+                    .all(|rule| rule.get("id").is_some() && rule.get("severity").is_some())
+        })
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The format of a policy rules file.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RulesFile {
+    /// Set when the rules alone do not tell a policy file from a secrets one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<RulesFileKind>,
     pub rules: Vec<Rule>,
 }
 
 pub struct RulesEngine {
     rules: Vec<Rule>,
+    /// What the configuration's `rules:` section changed, by rule ID
+    overridden: HashMap<String, Vec<String>>,
+}
+
+/// A rule as shown by `rules list`.
+pub struct RuleListing {
+    pub id: String,
+    pub name: String,
+    pub severity: String,
+    pub enabled: bool,
+    pub description: String,
+    pub remediation: Option<String>,
+    pub manual: bool,
+    /// What the configuration's `rules:` section changed
+    pub overridden: Vec<String>,
+}
+
+/// Print rules under a heading, one entry per rule.
+pub fn print_rules(title: &str, rules: &[RuleListing]) {
+    println!("{}", title);
+    println!("{:-<80}", "");
+
+    for rule in rules {
+        let status = if rule.enabled { "✓" } else { "✗" };
+        let manual = if rule.manual { " [manual review]" } else { "" };
+        println!(
+            "[{}] {} - {} ({}){}",
+            status, rule.id, rule.name, rule.severity, manual
+        );
+        if !rule.description.is_empty() {
+            println!("    {}", rule.description);
+        }
+        if let Some(ref remediation) = rule.remediation {
+            println!("    Remediation: {}", remediation);
+        }
+        if !rule.overridden.is_empty() {
+            println!("    Overridden: {}", rule.overridden.join(", "));
+        }
+        println!();
+    }
 }
 
 impl Default for RulesEngine {
     fn default() -> Self {
-        Self {
-            rules: Self::default_rules(),
-        }
+        let mut engine = Self::from_rules(Self::default_rules());
+        engine.apply_config(&PoliciesConfig::default());
+        engine
     }
 }
 
 impl RulesEngine {
+    /// Read and validate a policy rules file; duplicate IDs and invalid
+    /// severities or patterns are errors.
     pub fn load(path: &Path) -> Result<Self> {
-        let content = fs::read_to_string(path)?;
-        let rules_file: RulesFile = serde_yaml::from_str(&content)?;
+        let in_file =
+            |e: String| AnsibleSecError::InvalidRuleFormat(format!("{}: {}", path.display(), e));
+        let content = fs::read_to_string(path).map_err(|e| in_file(e.to_string()))?;
 
-        Ok(Self {
-            rules: rules_file.rules,
-        })
+        let engine = Self::parse(&content).map_err(|e| in_file(e.to_string()))?;
+        engine.validate().map_err(|e| in_file(e.to_string()))?;
+        Ok(engine)
+    }
+
+    /// The policies bundled with ansiblesec.
+    pub fn shipped() -> Result<Self> {
+        Self::parse(SHIPPED_POLICY_RULES)
+    }
+
+    /// Rules for a configuration. A configured rules file that cannot be
+    /// read or parsed is an error, never a silent fallback; only the default
+    /// path falls back to the bundled copy when it does not exist.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let mut engine = match config.policies.rules_file.as_deref() {
+            Some(DEFAULT_POLICY_RULES) if !Path::new(DEFAULT_POLICY_RULES).exists() => {
+                Self::shipped()?
+            }
+            Some(rules_file) => Self::load(Path::new(rules_file))?,
+            None => Self::from_rules(Self::default_rules()),
+        };
+        engine.apply_config(&config.policies);
+        engine.apply_overrides(&config.effective_rules()?)?;
+        Ok(engine)
+    }
+
+    /// Apply `disallow_modules` and `require_vault` on top of the loaded
+    /// rules. Disallowed modules no enabled `Module` or `DisallowModule` rule
+    /// reports yet get a generated `POLICY_001`; `require_vault` adds
+    /// `POLICY_002` when no `RequireVault` rule is enabled, and when turned
+    /// off disables every `RequireVault` rule.
+    pub fn apply_config(&mut self, config: &PoliciesConfig) {
+        let mut generated = Vec::new();
+
+        let uncovered: Vec<String> = config
+            .disallow_modules
+            .iter()
+            .filter(|module| {
+                !self
+                    .get_enabled_rules()
+                    .iter()
+                    .any(|rule| match rule.rule_type {
+                        RuleType::Module { ref modules }
+                        | RuleType::DisallowModule { ref modules } => {
+                            modules.iter().any(|m| modules::is_module(m, module))
+                        }
+                        _ => false,
+                    })
+            })
+            .cloned()
+            .collect();
+        if !uncovered.is_empty() {
+            generated.push(Rule {
+                id: "POLICY_001".to_string(),
+                name: "Disallow Risky Modules".to_string(),
+                description: format!(
+                    "Prevents use of modules disallowed by the configuration: {}",
+                    uncovered.join(", ")
+                ),
+                severity: "HIGH".to_string(),
+                enabled: true,
+                rule_type: RuleType::DisallowModule { modules: uncovered },
+                remediation: Some(
+                    "Use purpose-built modules instead of disallowed ones".to_string(),
+                ),
+                examples: RuleExamples::default(),
+                paths: Vec::new(),
+            });
+        }
+
+        let is_vault_rule = |rule: &Rule| matches!(rule.rule_type, RuleType::RequireVault { .. });
+        if !config.require_vault {
+            for rule in self.rules.iter_mut().filter(|rule| is_vault_rule(rule)) {
+                rule.enabled = false;
+            }
+        } else if !self.get_enabled_rules().into_iter().any(is_vault_rule) {
+            generated.push(Rule {
+                id: "POLICY_002".to_string(),
+                name: "Require Ansible Vault".to_string(),
+                description: "Ensures sensitive variables are encrypted with Ansible Vault"
+                    .to_string(),
+                severity: "CRITICAL".to_string(),
+                enabled: true,
+                rule_type: RuleType::RequireVault {
+                    exceptions: vec!["ansible_connection".to_string()],
+                },
+                remediation: Some("Encrypt sensitive values with ansible-vault".to_string()),
+                examples: RuleExamples::default(),
+                paths: Vec::new(),
+            });
+        }
+
+        generated.retain(|rule| {
+            let defined = self.rules.iter().any(|r| r.id == rule.id);
+            if defined {
+                warn!(
+                    "Rule {} is already defined; not generating it from the configuration",
+                    rule.id
+                );
+            }
+            !defined
+        });
+        self.rules.splice(0..0, generated);
+    }
+
+    /// Apply the severity, `enabled` and `params` overrides of the profile
+    /// and the configuration's `rules:` section. `params` replace fields of the
+    /// rule's `rule_type`; naming a field the rule type does not have is an
+    /// error.
+    pub fn apply_overrides(&mut self, overrides: &RuleOverrides) -> Result<()> {
+        for rule in &mut self.rules {
+            let mut notes = overrides.apply(&rule.id, &mut rule.severity, &mut rule.enabled);
+            let params = overrides.for_rule(&rule.id).params;
+            if !params.is_empty() {
+                notes.extend(Self::apply_params(rule, &params)?);
+            }
+            if !notes.is_empty() {
+                self.overridden.insert(rule.id.clone(), notes);
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_params(rule: &mut Rule, params: &serde_yaml::Mapping) -> Result<Vec<String>> {
+        let invalid = |message: String| {
+            AnsibleSecError::ConfigError(format!("rules: {}: {}", rule.id, message))
+        };
+
+        let mut value = serde_yaml::to_value(&rule.rule_type)?;
+        let fields = value
+            .as_mapping_mut()
+            .ok_or_else(|| invalid("rule type has no parameters".to_string()))?;
+        let mut notes = Vec::new();
+        for (key, new) in params {
+            let name = key.as_str().unwrap_or_default();
+            if name == "type" {
+                return Err(invalid("params cannot change the rule type".to_string()));
+            }
+            let old = fields
+                .insert(key.clone(), new.clone())
+                .map(|old| serde_json::to_string(&old))
+                .transpose()?;
+            notes.push(format!(
+                "{} was {}",
+                name,
+                old.as_deref().unwrap_or("unset")
+            ));
+        }
+
+        let rule_type: RuleType =
+            serde_yaml::from_value(value).map_err(|e| invalid(e.to_string()))?;
+        rule.rule_type = rule_type;
+        Ok(notes)
+    }
+
+    pub fn from_rules(rules: Vec<Rule>) -> Self {
+        Self {
+            rules,
+            overridden: HashMap::new(),
+        }
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    fn parse(content: &str) -> Result<Self> {
+        let rules_file: RulesFile = serde_yaml::from_str(content)?;
+
+        Ok(Self::from_rules(rules_file.rules))
     }
 
     pub fn validate(&self) -> Result<()> {
+        let mut seen = std::collections::HashSet::new();
         for rule in &self.rules {
+            if !seen.insert(rule.id.as_str()) {
+                return Err(AnsibleSecError::RuleValidationError(format!(
+                    "Duplicate rule ID {}",
+                    rule.id
+                )));
+            }
+
             // Validate rule structure
             if rule.id.is_empty() {
                 return Err(AnsibleSecError::RuleValidationError(
@@ -86,58 +544,45 @@ impl RulesEngine {
                     )))
                 }
             }
+
+            if let RuleType::Pattern { ref pattern } = rule.rule_type {
+                Pattern::new(pattern).map_err(|e| {
+                    AnsibleSecError::RuleValidationError(format!("Rule {}: {}", rule.id, e))
+                })?;
+            }
         }
 
         Ok(())
     }
 
     pub fn list_rules(&self) {
-        println!("Available Rules:");
-        println!("{:-<80}", "");
+        print_rules("Available Rules:", &self.listings());
+    }
 
-        for rule in &self.rules {
-            let status = if rule.enabled { "✓" } else { "✗" };
-            println!(
-                "[{}] {} - {} ({})",
-                status, rule.id, rule.name, rule.severity
-            );
-            println!("    {}", rule.description);
-            println!();
-        }
+    pub fn listings(&self) -> Vec<RuleListing> {
+        self.rules
+            .iter()
+            .map(|rule| RuleListing {
+                id: rule.id.clone(),
+                name: rule.name.clone(),
+                severity: rule.severity.clone(),
+                enabled: rule.enabled,
+                description: rule.description.clone(),
+                remediation: rule.remediation.clone(),
+                manual: matches!(rule.rule_type, RuleType::Manual),
+                overridden: self.overridden.get(&rule.id).cloned().unwrap_or_default(),
+            })
+            .collect()
     }
 
     pub fn get_enabled_rules(&self) -> Vec<&Rule> {
         self.rules.iter().filter(|r| r.enabled).collect()
     }
 
+    /// Built-in rules used when no rules file is configured. `POLICY_001`
+    /// and `POLICY_002` are generated from the configuration.
     fn default_rules() -> Vec<Rule> {
         vec![
-            Rule {
-                id: "POLICY_001".to_string(),
-                name: "Disallow Risky Modules".to_string(),
-                description: "Prevents use of risky modules like shell, command, and raw"
-                    .to_string(),
-                severity: "HIGH".to_string(),
-                enabled: true,
-                rule_type: RuleType::DisallowModule {
-                    modules: vec![
-                        "shell".to_string(),
-                        "command".to_string(),
-                        "raw".to_string(),
-                    ],
-                },
-            },
-            Rule {
-                id: "POLICY_002".to_string(),
-                name: "Require Ansible Vault".to_string(),
-                description: "Ensures sensitive variables are encrypted with Ansible Vault"
-                    .to_string(),
-                severity: "CRITICAL".to_string(),
-                enabled: true,
-                rule_type: RuleType::RequireVault {
-                    exceptions: vec!["ansible_connection".to_string()],
-                },
-            },
             Rule {
                 id: "POLICY_003".to_string(),
                 name: "Disallow Hardcoded Credentials".to_string(),
@@ -146,6 +591,9 @@ impl RulesEngine {
                 severity: "CRITICAL".to_string(),
                 enabled: true,
                 rule_type: RuleType::DisallowHardcodedCredentials,
+                remediation: Some("Use Ansible Vault or variables for sensitive data".to_string()),
+                examples: RuleExamples::default(),
+                paths: Vec::new(),
             },
             Rule {
                 id: "POLICY_004".to_string(),
@@ -154,6 +602,9 @@ impl RulesEngine {
                 severity: "HIGH".to_string(),
                 enabled: true,
                 rule_type: RuleType::RequireNoLogForSensitive,
+                remediation: Some("Add no_log: true to tasks handling credentials".to_string()),
+                examples: RuleExamples::default(),
+                paths: Vec::new(),
             },
             Rule {
                 id: "POLICY_005".to_string(),
@@ -164,7 +615,11 @@ impl RulesEngine {
                 enabled: true,
                 rule_type: RuleType::CheckPermissions {
                     max_permissions: "0644".to_string(),
+                    state: None,
                 },
+                remediation: Some("Use 0644 or more restrictive permissions".to_string()),
+                examples: RuleExamples::default(),
+                paths: Vec::new(),
             },
         ]
     }
@@ -180,6 +635,66 @@ mod tests {
         assert!(engine.validate().is_ok());
     }
 
+    #[test]
+    fn test_shipped_rules_load() {
+        let engine = RulesEngine::shipped().unwrap();
+        assert!(engine.validate().is_ok());
+        assert_eq!(engine.rules.len(), 54);
+
+        let shell = engine
+            .rules
+            .iter()
+            .find(|r| r.id == "POLICY_DANGEROUS_SHELL")
+            .unwrap();
+        assert!(matches!(shell.rule_type, RuleType::Module { .. }));
+        assert!(shell.remediation.is_some());
+    }
+
+    #[test]
+    fn test_rule_without_check_is_an_error() {
+        let content = "rules:\n  - id: X\n    name: x\n    severity: LOW\n";
+        assert!(RulesEngine::parse(content).is_err());
+
+        let content =
+            "rules:\n  - id: X\n    name: x\n    severity: LOW\n    module: a\n    pattern: b\n";
+        assert!(RulesEngine::parse(content).is_err());
+    }
+
+    #[test]
+    fn test_duplicate_ids_fail_validation() {
+        let content = "rules:\n  - id: X\n    name: x\n    severity: LOW\n    module: a\n  - id: X\n    name: y\n    severity: LOW\n    module: b\n";
+        assert!(RulesEngine::parse(content).unwrap().validate().is_err());
+    }
+
+    #[test]
+    fn test_is_rules_file() {
+        assert!(is_rules_file(SHIPPED_POLICY_RULES));
+        assert!(is_rules_file("kind: secrets\nrules: []\n"));
+        assert!(is_rules_file(
+            "rules:\n  - id: X\n    name: x\n    severity: LOW\n    module: shell\n"
+        ));
+        // Ansible vars that happen to use the key
+        assert!(!is_rules_file(
+            "rules:\n  - port: 22\n    source: 10.0.0.0/8\n"
+        ));
+        assert!(!is_rules_file("- hosts: all\n  tasks: []\n"));
+    }
+
+    #[test]
+    fn test_detect_rules_file_kind() {
+        // Detected by shape, whatever the IDs
+        let secrets = "rules:\n  - id: VENDOR_TOKEN\n    name: Vendor token\n    pattern: vt_[0-9a-f]{32}\n    severity: HIGH\n    description: Vendor API token\n";
+        assert_eq!(RulesFileKind::detect(secrets), RulesFileKind::Secrets);
+        let policy = format!("{}    remediation: Use a vault variable\n", secrets);
+        assert_eq!(RulesFileKind::detect(&policy), RulesFileKind::Policy);
+        let policy = format!("kind: policy\n{}", secrets);
+        assert_eq!(RulesFileKind::detect(&policy), RulesFileKind::Policy);
+        assert_eq!(
+            RulesFileKind::detect(SHIPPED_POLICY_RULES),
+            RulesFileKind::Policy
+        );
+    }
+
     #[test]
     fn test_get_enabled_rules() {
         let engine = RulesEngine::default();
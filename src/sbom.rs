@@ -1,3 +1,4 @@
+use serde::Deserialize;
 use serde_yaml::Value;
 use std::path::Path;
 use walkdir::WalkDir;
@@ -18,6 +19,15 @@ struct Component {
     cves: Vec<String>,
 }
 
+/// Every document of a `---` separated stream; none if the file is not
+/// valid YAML.
+fn documents(content: &str) -> Vec<Value> {
+    serde_yaml::Deserializer::from_str(content)
+        .map(Value::deserialize)
+        .collect::<std::result::Result<_, _>>()
+        .unwrap_or_default()
+}
+
 impl SbomGenerator {
     pub fn new(include_cve: bool) -> Self {
         Self { include_cve }
@@ -47,7 +57,7 @@ impl SbomGenerator {
         {
             if entry.file_name() == "requirements.yml" || entry.file_name() == "requirements.yaml" {
                 let content = std::fs::read_to_string(entry.path())?;
-                if let Ok(yaml) = serde_yaml::from_str::<Value>(&content) {
+                for yaml in documents(&content) {
                     components.extend(self.parse_requirements(&yaml)?);
                 }
             }
@@ -55,7 +65,7 @@ impl SbomGenerator {
             // Also check for galaxy.yml (collection metadata)
             if entry.file_name() == "galaxy.yml" {
                 let content = std::fs::read_to_string(entry.path())?;
-                if let Ok(yaml) = serde_yaml::from_str::<Value>(&content) {
+                for yaml in documents(&content) {
                     if let Some(component) = self.parse_galaxy_metadata(&yaml)? {
                         components.push(component);
                     }
@@ -1,22 +1,52 @@
 use log::{debug, info, warn};
 use rayon::prelude::*;
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use walkdir::WalkDir;
 
+use crate::baseline::BaselineEntry;
 use crate::cache::Cache;
-use crate::config::Config;
+use crate::config::{ResolvedConfig, PROJECT_CONFIG};
+use crate::discovery::{self, DiscoveredFile, IgnoreRules, SkipReason};
 use crate::errors::Result;
-use crate::linter::Linter;
+use crate::graph::DependencyGraph;
+use crate::linter::{Linter, LINT_RULES};
+use crate::model::{Document, FileKind};
+use crate::pattern::DEFAULT_BACKTRACK_LIMIT;
 use crate::policy::PolicyEngine;
-use crate::secrets::SecretDetector;
+use crate::references::{ReferenceChecker, REFERENCE_RULES};
+use crate::roles::{RoleAnalysis, RoleChecker, ROLE_RULES};
+use crate::secrets::{SecretDetector, HIGH_ENTROPY_RULE};
+use crate::suppression::{self, Suppression};
 
 pub struct Scanner {
-    config: Config,
+    /// Analyzers for the configuration of the scanned path
+    root: Arc<Analyzers>,
+    enable_cache: bool,
+}
+
+/// A path discovery looked at: its kind from the project layout, if it is
+/// a file of Ansible content, and its analyzers or why it is skipped.
+struct Candidate {
+    path: PathBuf,
+    kind: Option<FileKind>,
+    analyzers: std::result::Result<Arc<Analyzers>, SkipReason>,
+}
+
+/// The analyzers for one configuration: the scanned path's, or a
+/// subdirectory's with its own `.ansiblesec.yml` merged in.
+struct Analyzers {
+    resolved: ResolvedConfig,
     secret_detector: SecretDetector,
     policy_engine: PolicyEngine,
     linter: Linter,
+    reference_checker: ReferenceChecker,
+    role_checker: RoleChecker,
     cache: Option<Cache>,
+    /// Settings that have no effect, printed once per config
+    warnings: Vec<String>,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -26,22 +56,65 @@ pub struct ScanFindings {
     pub policy_violations: Vec<FileFinding>,
     pub lint_issues: Vec<FileFinding>,
     pub summary: Summary,
+    /// Baseline entries no longer found
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fixed: Vec<BaselineEntry>,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct FileFinding {
     pub file_path: PathBuf,
+    #[serde(default)]
+    pub kind: FileKind,
     pub findings: Vec<Finding>,
+    /// The innermost config file that applied to the file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<PathBuf>,
+    /// How entry-point playbooks reach the file through imports, includes
+    /// and roles: one chain per entry point, entry point first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chains: Vec<Vec<PathBuf>>,
 }
 
+/// A single issue. Lines and columns are 1-based; the end position is
+/// exclusive.
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Finding {
     pub line: usize,
     pub column: usize,
+    #[serde(default)]
+    pub end_line: usize,
+    #[serde(default)]
+    pub end_column: usize,
     pub severity: String,
     pub rule_id: String,
     pub message: String,
     pub context: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remediation: Option<String>,
+    /// Set when an inline comment suppresses the finding
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suppression: Option<Suppression>,
+    /// Set when the finding is recorded in the baseline
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub baselined: bool,
+}
+
+impl Finding {
+    /// Policy findings are everything that is neither a secret nor a lint
+    /// issue; rules files may use any ID.
+    pub fn is_policy(&self) -> bool {
+        !self.rule_id.starts_with("SECRET") && !self.rule_id.starts_with("LINT")
+    }
+
+    pub fn is_suppressed(&self) -> bool {
+        self.suppression.is_some()
+    }
+
+    /// Whether the finding counts: neither suppressed nor in the baseline.
+    pub fn is_active(&self) -> bool {
+        !self.is_suppressed() && !self.baselined
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -51,35 +124,65 @@ pub struct Summary {
     pub medium: usize,
     pub low: usize,
     pub info: usize,
+    /// Findings silenced by inline suppression comments; not counted above
+    #[serde(default)]
+    pub suppressed: usize,
+    /// Findings already recorded in the baseline; not counted above
+    #[serde(default)]
+    pub baselined: usize,
 }
 
-impl Scanner {
-    pub fn new(config: Config, threads: usize, enable_cache: bool) -> Self {
-        // Load secrets detector from file or use defaults
-        let secret_detector = if let Some(ref rules_file) = config.secrets.rules_file {
-            SecretDetector::from_file(rules_file, config.secrets.entropy_threshold).unwrap_or_else(
-                |e| {
-                    eprintln!(
-                        "Warning: Failed to load secrets rules from {}: {}",
-                        rules_file, e
-                    );
-                    eprintln!("Falling back to default rules");
-                    SecretDetector::with_defaults(config.secrets.entropy_threshold)
-                },
-            )
-        } else {
-            SecretDetector::with_defaults(config.secrets.entropy_threshold)
+impl Summary {
+    pub fn count<'a>(files: impl IntoIterator<Item = &'a FileFinding>) -> Self {
+        let mut summary = Summary {
+            critical: 0,
+            high: 0,
+            medium: 0,
+            low: 0,
+            info: 0,
+            suppressed: 0,
+            baselined: 0,
         };
 
-        let policy_engine = PolicyEngine::new(config.clone());
-        let linter = Linter::new(config.clone());
+        for finding in files.into_iter().flat_map(|f| &f.findings) {
+            if finding.is_suppressed() {
+                summary.suppressed += 1;
+                continue;
+            }
+            if finding.baselined {
+                summary.baselined += 1;
+                continue;
+            }
+            match finding.severity.as_str() {
+                "CRITICAL" => summary.critical += 1,
+                "HIGH" => summary.high += 1,
+                "MEDIUM" => summary.medium += 1,
+                "LOW" => summary.low += 1,
+                "INFO" => summary.info += 1,
+                _ => {}
+            }
+        }
+
+        summary
+    }
+}
 
-        let cache = if enable_cache {
-            Some(Cache::new(config.general.cache_dir.clone()))
+impl Scanner {
+    /// A scanner for a configuration, plain or resolved. Nested
+    /// `.ansiblesec.yml` files found while scanning are merged onto it for
+    /// their subtree.
+    pub fn new(
+        config: impl Into<ResolvedConfig>,
+        threads: usize,
+        enable_cache: bool,
+    ) -> Result<Self> {
+        let resolved = config.into();
+        // An explicit thread count wins over `general.parallel_jobs`
+        let threads = if threads > 0 {
+            threads
         } else {
-            None
+            resolved.config.general.parallel_jobs
         };
-
         if threads > 0 {
             rayon::ThreadPoolBuilder::new()
                 .num_threads(threads)
@@ -87,17 +190,21 @@ impl Scanner {
                 .ok();
         }
 
-        Self {
-            config,
-            secret_detector,
-            policy_engine,
-            linter,
-            cache,
+        let root = Analyzers::new(resolved, enable_cache)?;
+        for warning in &root.warnings {
+            eprintln!("Warning: {}", warning);
         }
+
+        Ok(Self {
+            root: Arc::new(root),
+            enable_cache,
+        })
     }
 
     pub fn scan(&self, path: &Path) -> Result<ScanFindings> {
         let files = self.collect_files(path)?;
+        let graph = build_graph(path, &files);
+        let roles = RoleAnalysis::new(&graph, path);
 
         // Print scan start message
         eprintln!("🔍 Starting security scan...");
@@ -109,10 +216,11 @@ impl Scanner {
 
         info!("Scanning {} files", files.len());
 
+        let today = chrono::Local::now().date_naive();
         let results: Vec<_> = files
             .par_iter()
             .enumerate()
-            .filter_map(|(idx, file)| {
+            .filter_map(|(idx, (file, kind, analyzers))| {
                 // Progress indicator
                 if files.len() > 1 {
                     let progress = (idx + 1) as f32 / files.len() as f32 * 100.0;
@@ -124,25 +232,32 @@ impl Scanner {
                     );
                 }
 
-                if let Some(ref cache) = self.cache {
-                    if let Ok(cached) = cache.get(file) {
-                        debug!("Using cached results for {:?}", file);
-                        return Some(cached);
-                    }
-                }
-
-                match self.scan_file(file) {
-                    Ok(finding) => {
-                        if let Some(ref cache) = self.cache {
-                            cache.set(file, &finding).ok();
+                let cached = analyzers.cache.as_ref().and_then(|cache| {
+                    let cached = cache.get(file).ok()?;
+                    debug!("Using cached results for {:?}", file);
+                    Some(cached)
+                });
+                let mut finding = match cached {
+                    Some(cached) => cached,
+                    None => match analyzers.scan_file(file, *kind) {
+                        Ok(finding) => {
+                            if let Some(ref cache) = analyzers.cache {
+                                cache.set(file, &finding).ok();
+                            }
+                            finding
                         }
-                        Some(finding)
-                    }
-                    Err(e) => {
-                        warn!("Error scanning {:?}: {}", file, e);
-                        None
-                    }
-                }
+                        Err(e) => {
+                            warn!("Error scanning {:?}: {}", file, e);
+                            return None;
+                        }
+                    },
+                };
+                finding.config = analyzers.config_file();
+                finding.chains = graph.chains(file).to_vec();
+                // Not cached: whether a target exists depends on other files
+                analyzers.check_references(&graph, &roles, &mut finding);
+                analyzers.review_suppressions(&mut finding, today);
+                Some(finding)
             })
             .collect();
 
@@ -155,158 +270,235 @@ impl Scanner {
             eprintln!("✅ Scan complete!");
         }
         eprintln!();
+        Self::report_gave_up(&files);
 
         Ok(self.aggregate_findings(results, files.len()))
     }
 
+    /// Warn about rules whose patterns hit their backtracking limit, since
+    /// the lines they gave up on were not checked by them.
+    fn report_gave_up(files: &[(PathBuf, FileKind, Arc<Analyzers>)]) {
+        let mut analyzers: Vec<&Arc<Analyzers>> = Vec::new();
+        for (_, _, file_analyzers) in files {
+            if !analyzers.iter().any(|a| Arc::ptr_eq(a, file_analyzers)) {
+                analyzers.push(file_analyzers);
+            }
+        }
+
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        for analyzers in analyzers {
+            let gave_up = analyzers
+                .secret_detector
+                .gave_up()
+                .into_iter()
+                .chain(analyzers.policy_engine.gave_up());
+            for (rule_id, count) in gave_up {
+                *counts.entry(rule_id).or_default() += count;
+            }
+        }
+        for (rule_id, count) in counts {
+            eprintln!(
+                "Warning: rule {} skipped {} line{}: its pattern exceeded {} backtracking steps",
+                rule_id,
+                count,
+                if count == 1 { "" } else { "s" },
+                DEFAULT_BACKTRACK_LIMIT
+            );
+        }
+    }
+
     pub fn lint(&self, path: &Path) -> Result<ScanFindings> {
         let files = self.collect_files(path)?;
+        let graph = build_graph(path, &files);
         info!("Linting {} files", files.len());
 
+        let today = chrono::Local::now().date_naive();
         let results: Vec<_> = files
             .par_iter()
-            .filter_map(|file| match self.lint_file(file) {
-                Ok(finding) => Some(finding),
-                Err(e) => {
-                    warn!("Error linting {:?}: {}", file, e);
-                    None
-                }
-            })
+            .filter_map(
+                |(file, kind, analyzers)| match analyzers.lint_file(file, *kind) {
+                    Ok(mut finding) => {
+                        finding.chains = graph.chains(file).to_vec();
+                        analyzers.review_suppressions(&mut finding, today);
+                        Some(finding)
+                    }
+                    Err(e) => {
+                        warn!("Error linting {:?}: {}", file, e);
+                        None
+                    }
+                },
+            )
             .collect();
 
         Ok(self.aggregate_findings(results, files.len()))
     }
 
-    fn scan_file(&self, file_path: &Path) -> Result<FileFinding> {
-        let content = fs::read_to_string(file_path)?;
-        let mut findings = Vec::new();
-
-        // Secrets detection
-        if self.config.secrets.enabled {
-            let secret_findings = self.secret_detector.scan_content(&content);
-            for sf in secret_findings {
-                findings.push(Finding {
-                    line: sf.line,
-                    column: sf.column,
-                    severity: sf.severity.as_str().to_string(),
-                    rule_id: sf.rule_id,
-                    message: sf.message,
-                    context: Some(sf.context),
-                });
-            }
-        }
-
-        // Policy enforcement
-        if self.config.policies.enabled {
-            let policy_findings = self.policy_engine.check_policies(file_path, &content)?;
-            findings.extend(policy_findings);
-        }
-
-        Ok(FileFinding {
-            file_path: file_path.to_path_buf(),
-            findings,
-        })
+    /// The include, import and role graph of the files under `path`.
+    pub fn graph(&self, path: &Path) -> Result<DependencyGraph> {
+        Ok(build_graph(path, &self.collect_files(path)?))
     }
 
-    fn lint_file(&self, file_path: &Path) -> Result<FileFinding> {
-        let content = fs::read_to_string(file_path)?;
-        let findings = self.linter.lint(&content)?;
+    /// The files to scan, each with its kind from the project layout and
+    /// the analyzers of the innermost config file above it.
+    fn collect_files(&self, path: &Path) -> Result<Vec<(PathBuf, FileKind, Arc<Analyzers>)>> {
+        Ok(self
+            .discover(path)?
+            .into_iter()
+            .filter_map(|found| {
+                let kind = found.kind?;
+                found
+                    .analyzers
+                    .ok()
+                    .map(|analyzers| (found.path, kind, analyzers))
+            })
+            .collect())
+    }
 
-        Ok(FileFinding {
-            file_path: file_path.to_path_buf(),
-            findings,
-        })
+    /// Every file and directory discovery looks at under `path`, with the
+    /// reason it is skipped if it is.
+    pub fn list_files(&self, path: &Path) -> Result<Vec<DiscoveredFile>> {
+        Ok(self
+            .discover(path)?
+            .into_iter()
+            .map(|found| DiscoveredFile {
+                path: found.path,
+                kind: found.kind,
+                skipped: found.analyzers.err(),
+            })
+            .collect())
     }
 
-    fn collect_files(&self, path: &Path) -> Result<Vec<PathBuf>> {
-        let mut files = Vec::new();
+    /// Walk `path`, pairing each file to scan with its analyzers. Ignore
+    /// rules and the list of other tools' files do not apply to a single
+    /// file given directly.
+    fn discover(&self, path: &Path) -> Result<Vec<Candidate>> {
+        let mut found = Vec::new();
 
         if path.is_file() {
-            if self.is_ansible_file(path) {
-                files.push(path.to_path_buf());
-            }
-        } else {
-            for entry in WalkDir::new(path)
-                .follow_links(true)
-                .into_iter()
-                .filter_entry(|e| !self.is_excluded(e.path()))
+            let kind = discovery::file_kind(path);
+            found.push(Candidate {
+                path: path.to_path_buf(),
+                kind,
+                analyzers: match kind {
+                    Some(_) => Ok(self.root.clone()),
+                    None => Err(SkipReason::NotAnsible),
+                },
+            });
+            return Ok(found);
+        }
+
+        // Directories with their own config or ignore files, outermost first
+        let root_rules =
+            IgnoreRules::default().with_config(path, &self.root.resolved.config.general)?;
+        let mut nested: Vec<(PathBuf, Arc<Analyzers>, IgnoreRules)> = Vec::new();
+        let mut walker = WalkDir::new(path)
+            .follow_links(true)
+            .sort_by_file_name()
+            .max_depth(self.root.resolved.config.general.max_depth)
+            .into_iter();
+
+        while let Some(entry) = walker.next() {
+            let entry = entry.map_err(std::io::Error::other)?;
+            let entry_path = entry.path();
+            let is_dir = entry.file_type().is_dir();
+
+            while nested
+                .last()
+                .is_some_and(|(dir, _, _)| !entry_path.starts_with(dir))
             {
-                let entry = entry.map_err(std::io::Error::other)?;
-                if entry.file_type().is_file() && self.is_ansible_file(entry.path()) {
-                    // Check file size
-                    if let Ok(metadata) = entry.metadata() {
-                        if metadata.len() <= self.config.general.max_file_size as u64 {
-                            files.push(entry.path().to_path_buf());
-                        }
+                nested.pop();
+            }
+            let (analyzers, rules) = match nested.last() {
+                Some((_, analyzers, rules)) => (analyzers.clone(), rules),
+                None => (self.root.clone(), &root_rules),
+            };
+
+            if entry.depth() > 0 {
+                if let Some(reason) = rules.matched(entry_path, is_dir) {
+                    if is_dir {
+                        walker.skip_current_dir();
                     }
+                    found.push(Candidate {
+                        path: entry_path.to_path_buf(),
+                        kind: None,
+                        analyzers: Err(reason),
+                    });
+                    continue;
                 }
             }
-        }
 
-        Ok(files)
-    }
-
-    fn is_ansible_file(&self, path: &Path) -> bool {
-        if let Some(ext) = path.extension() {
-            let ext_str = ext.to_string_lossy();
-            if ext_str == "yml" || ext_str == "yaml" {
-                return true;
-            }
-        }
-
-        // Check for common Ansible file names
-        if let Some(name) = path.file_name() {
-            let name_str = name.to_string_lossy();
-            if name_str.contains("playbook")
-                || name_str.contains("tasks")
-                || name_str.contains("handlers")
-                || name_str == "site.yml"
-                || name_str == "main.yml"
-            {
-                return true;
+            if is_dir {
+                let config_file = entry_path.join(PROJECT_CONFIG);
+                let mut pushed = None;
+                if entry.depth() > 0 && config_file.is_file() {
+                    let resolved = analyzers.resolved.merge_file(&config_file)?;
+                    let merged = Analyzers::new(resolved, self.enable_cache)?;
+                    for warning in &merged.warnings {
+                        if !analyzers.warnings.contains(warning) {
+                            eprintln!("Warning: {}", warning);
+                        }
+                    }
+                    let rules = rules.with_config(entry_path, &merged.resolved.config.general)?;
+                    pushed = Some((Arc::new(merged), rules));
+                }
+                let base = pushed.as_ref().map_or(rules, |(_, rules)| rules);
+                if let Some(rules) = base.with_dir(entry_path) {
+                    let analyzers = pushed.map_or(analyzers, |(analyzers, _)| analyzers);
+                    pushed = Some((analyzers, rules));
+                }
+                if let Some((analyzers, rules)) = pushed {
+                    nested.push((entry_path.to_path_buf(), analyzers, rules));
+                }
+            } else if entry.file_type().is_file() {
+                let kind = discovery::file_kind(entry_path);
+                let name = entry.file_name();
+                let analyzers = if (entry.depth() > 0 && name == PROJECT_CONFIG)
+                    || discovery::IGNORE_FILES.iter().any(|file| name == *file)
+                {
+                    Err(SkipReason::ConfigFile)
+                } else if kind.is_none() {
+                    Err(SkipReason::NotAnsible)
+                } else if discovery::is_other_tool(entry_path) {
+                    Err(SkipReason::OtherTool)
+                } else {
+                    match entry.metadata() {
+                        Ok(metadata)
+                            if metadata.len()
+                                > analyzers.resolved.config.general.max_file_size as u64 =>
+                        {
+                            Err(SkipReason::TooLarge(metadata.len()))
+                        }
+                        // Only content tells a rules file from a vars file
+                        Ok(_)
+                            if kind == Some(FileKind::Unknown)
+                                && fs::read_to_string(entry_path)
+                                    .is_ok_and(|content| crate::rules::is_rules_file(&content)) =>
+                        {
+                            Err(SkipReason::RulesFile)
+                        }
+                        Ok(_) => Ok(analyzers),
+                        Err(_) => continue,
+                    }
+                };
+                found.push(Candidate {
+                    path: entry_path.to_path_buf(),
+                    kind,
+                    analyzers,
+                });
             }
         }
 
-        false
-    }
-
-    fn is_excluded(&self, path: &Path) -> bool {
-        let path_str = path.to_string_lossy();
-        self.config
-            .general
-            .exclude_paths
-            .iter()
-            .any(|exclude| path_str.contains(exclude))
+        Ok(found)
     }
 
     fn aggregate_findings(&self, results: Vec<FileFinding>, files_scanned: usize) -> ScanFindings {
-        let mut summary = Summary {
-            critical: 0,
-            high: 0,
-            medium: 0,
-            low: 0,
-            info: 0,
-        };
-
         let mut secrets = Vec::new();
         let mut policy_violations = Vec::new();
         let mut lint_issues = Vec::new();
 
+        let summary = Summary::count(&results);
         for file_finding in results {
-            let has_findings = !file_finding.findings.is_empty();
-
-            for finding in &file_finding.findings {
-                match finding.severity.as_str() {
-                    "CRITICAL" => summary.critical += 1,
-                    "HIGH" => summary.high += 1,
-                    "MEDIUM" => summary.medium += 1,
-                    "LOW" => summary.low += 1,
-                    "INFO" => summary.info += 1,
-                    _ => {}
-                }
-            }
-
-            if has_findings {
+            if !file_finding.findings.is_empty() {
                 if file_finding
                     .findings
                     .iter()
@@ -314,11 +506,7 @@ impl Scanner {
                 {
                     secrets.push(file_finding.clone());
                 }
-                if file_finding
-                    .findings
-                    .iter()
-                    .any(|f| f.rule_id.starts_with("POLICY"))
-                {
+                if file_finding.findings.iter().any(Finding::is_policy) {
                     policy_violations.push(file_finding.clone());
                 }
                 if file_finding
@@ -337,11 +525,261 @@ impl Scanner {
             policy_violations,
             lint_issues,
             summary,
+            fixed: Vec::new(),
+        }
+    }
+}
+
+/// The dependency graph of discovered files.
+fn build_graph(path: &Path, files: &[(PathBuf, FileKind, Arc<Analyzers>)]) -> DependencyGraph {
+    let files: Vec<(PathBuf, FileKind)> = files
+        .iter()
+        .map(|(file, kind, _)| (file.clone(), *kind))
+        .collect();
+    DependencyGraph::build(path, &files)
+}
+
+impl Analyzers {
+    fn new(resolved: ResolvedConfig, enable_cache: bool) -> Result<Self> {
+        let config = &resolved.config;
+
+        let secret_patterns = SecretDetector::configured_patterns(config)?;
+        let mut rule_ids: Vec<String> = secret_patterns
+            .iter()
+            .map(|pattern| pattern.id.clone())
+            .collect();
+        let mut secret_detector = SecretDetector::from_config(secret_patterns, config)?;
+        // Rule `paths` are relative to the config file naming the rules file
+        if let Some(dir) = resolved.file_dir("secrets.rules_file") {
+            secret_detector = secret_detector.relative_to(dir);
+        }
+
+        // Checked here so a bad pattern fails before any scanning
+        IgnoreRules::default().with_config(Path::new("."), &config.general)?;
+
+        let mut policy_engine = PolicyEngine::new(config.clone())?;
+        if let Some(dir) = resolved.file_dir("policies.rules_file") {
+            policy_engine = policy_engine.relative_to(dir);
+        }
+        let linter = Linter::new(config.clone())?;
+        let reference_checker = ReferenceChecker::new(config)?;
+        let role_checker = RoleChecker::new(config)?;
+
+        let rules = config.effective_rules()?;
+        let mut warnings = resolved.warnings();
+        rule_ids.push(HIGH_ENTROPY_RULE.to_string());
+        rule_ids.extend(LINT_RULES.iter().map(|(id, _, _)| id.to_string()));
+        rule_ids.extend(REFERENCE_RULES.iter().map(|(id, _, _)| id.to_string()));
+        rule_ids.extend(ROLE_RULES.iter().map(|(id, _, _)| id.to_string()));
+        for rule_id in &rule_ids {
+            if !rules.for_rule(rule_id).params.is_empty() {
+                warnings.push(format!(
+                    "rules: params for {} have no effect: only policy rules take params",
+                    rule_id
+                ));
+            }
+        }
+        rule_ids.extend(policy_engine.rules().iter().map(|rule| rule.id.clone()));
+        let rule_ids: Vec<&str> = rule_ids.iter().map(String::as_str).collect();
+        for pattern in config.rules.unmatched(&rule_ids) {
+            warnings.push(format!("rules.{} matches no rule", pattern));
+        }
+
+        let cache = if enable_cache && config.general.cache_enabled {
+            let key = Cache::key(config)?;
+            Some(Cache::new(config.general.cache_dir.clone(), &key))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            secret_detector,
+            policy_engine,
+            linter,
+            reference_checker,
+            role_checker,
+            cache,
+            warnings,
+            resolved,
+        })
+    }
+
+    /// The innermost config file in effect, reported with each finding.
+    fn config_file(&self) -> Option<PathBuf> {
+        self.resolved.files.last().cloned()
+    }
+
+    fn scan_file(&self, file_path: &Path, kind: FileKind) -> Result<FileFinding> {
+        let content = fs::read_to_string(file_path)?;
+        let doc = Document::parse_as(file_path, &content, kind);
+        let mut findings = Vec::new();
+
+        // Secrets detection
+        if self.resolved.config.secrets.enabled {
+            let secret_findings = self.secret_detector.scan_document(&doc);
+            for sf in secret_findings {
+                findings.push(Finding {
+                    line: sf.line,
+                    column: sf.column,
+                    end_line: sf.line,
+                    end_column: sf.end_column,
+                    severity: sf.severity.as_str().to_string(),
+                    rule_id: sf.rule_id,
+                    message: sf.message,
+                    context: Some(sf.context),
+                    remediation: None,
+                    suppression: None,
+                    baselined: false,
+                });
+            }
+        }
+
+        // Policy enforcement
+        if self.resolved.config.policies.enabled && doc.is_yaml() {
+            let policy_findings = self.policy_engine.check_policies(&doc)?;
+            findings.extend(policy_findings);
+        }
+
+        suppression::apply(&doc, &mut findings);
+        findings.extend(suppression::invalid(&doc));
+
+        Ok(FileFinding {
+            file_path: file_path.to_path_buf(),
+            kind: doc.kind,
+            findings,
+            config: self.config_file(),
+            chains: Vec::new(),
+        })
+    }
+
+    fn lint_file(&self, file_path: &Path, kind: FileKind) -> Result<FileFinding> {
+        let content = fs::read_to_string(file_path)?;
+        let doc = Document::parse_as(file_path, &content, kind);
+        let mut findings = if doc.is_yaml() {
+            self.linter.lint(&doc)?
+        } else {
+            Vec::new()
+        };
+        suppression::apply(&doc, &mut findings);
+        findings.extend(suppression::invalid(&doc));
+
+        Ok(FileFinding {
+            file_path: file_path.to_path_buf(),
+            kind: doc.kind,
+            findings,
+            config: self.config_file(),
+            chains: Vec::new(),
+        })
+    }
+
+    /// Add a finding for each include, role, template or copied file the
+    /// file names that does not exist, and for each role issue in it,
+    /// suppressed by its comments.
+    fn check_references(
+        &self,
+        graph: &DependencyGraph,
+        roles: &RoleAnalysis,
+        file_finding: &mut FileFinding,
+    ) {
+        let mut findings = self.reference_checker.check(graph, &file_finding.file_path);
+        findings.extend(self.role_checker.check(roles, &file_finding.file_path));
+        if findings.is_empty() {
+            return;
+        }
+        if let Ok(content) = fs::read_to_string(&file_finding.file_path) {
+            let doc = Document::parse_as(&file_finding.file_path, &content, file_finding.kind);
+            suppression::apply(&doc, &mut findings);
+        }
+        file_finding.findings.extend(findings);
+    }
+
+    /// Lift and report expired suppressions and, if configured, report
+    /// suppressions without a reason. Done after caching since both depend
+    /// on more than the file's content.
+    fn review_suppressions(&self, file_finding: &mut FileFinding, today: chrono::NaiveDate) {
+        let mut expired: Vec<Suppression> = Vec::new();
+        let mut unexplained: Vec<Suppression> = Vec::new();
+
+        for finding in &mut file_finding.findings {
+            let Some(ref suppression) = finding.suppression else {
+                continue;
+            };
+
+            if suppression.is_expired(today) {
+                if !expired.iter().any(|s| s.line == suppression.line) {
+                    expired.push(suppression.clone());
+                }
+                finding.suppression = None;
+            } else if self.resolved.config.general.require_suppression_reason
+                && suppression.reason.is_none()
+                && !unexplained.iter().any(|s| s.line == suppression.line)
+            {
+                unexplained.push(suppression.clone());
+            }
+        }
+
+        for suppression in expired {
+            file_finding.findings.push(Finding {
+                line: suppression.line,
+                column: suppression.column,
+                end_line: suppression.line,
+                end_column: suppression.end_column,
+                severity: "LOW".to_string(),
+                rule_id: "SUPPRESSION_EXPIRED".to_string(),
+                message: format!(
+                    "Suppression expired on {}",
+                    suppression.until.unwrap_or_default()
+                ),
+                context: Some(
+                    "The findings it covered are reported again; fix them or extend until="
+                        .to_string(),
+                ),
+                remediation: None,
+                suppression: None,
+                baselined: false,
+            });
+        }
+
+        for suppression in unexplained {
+            file_finding.findings.push(Finding {
+                line: suppression.line,
+                column: suppression.column,
+                end_line: suppression.line,
+                end_column: suppression.end_column,
+                severity: "LOW".to_string(),
+                rule_id: "SUPPRESSION_NO_REASON".to_string(),
+                message: "Suppression comment has no reason".to_string(),
+                context: Some(
+                    "Add reason=\"...\" to explain why the finding is accepted".to_string(),
+                ),
+                remediation: None,
+                suppression: None,
+                baselined: false,
+            });
         }
     }
 }
 
 impl ScanFindings {
+    /// Every file with findings, once, however many categories it is in.
+    pub fn files(&self) -> Vec<&FileFinding> {
+        let mut seen = HashSet::new();
+        self.secrets
+            .iter()
+            .chain(&self.policy_violations)
+            .chain(&self.lint_issues)
+            .filter(|file_finding| seen.insert(&file_finding.file_path))
+            .collect()
+    }
+
+    /// Every copy of every file, for changes that must be applied to each.
+    pub fn files_mut(&mut self) -> impl Iterator<Item = &mut FileFinding> {
+        self.secrets
+            .iter_mut()
+            .chain(&mut self.policy_violations)
+            .chain(&mut self.lint_issues)
+    }
+
     pub fn has_critical(&self) -> bool {
         self.summary.critical > 0
     }
@@ -0,0 +1,52 @@
+use clap::ValueEnum;
+use schemars::schema_for;
+
+use crate::config::Config;
+use crate::errors::Result;
+use crate::rules::{Rule, RulesFile};
+use crate::secrets::{SecretPattern, SecretsRulesFile};
+
+/// File formats with a published JSON Schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SchemaKind {
+    /// `.ansiblesec.yml`
+    Config,
+    /// Policy rules files such as `rules/policies.yml`
+    PolicyRules,
+    /// Secrets rules files such as `rules/secrets.yml`
+    SecretRules,
+}
+
+/// The JSON Schema of a file format, derived from the types it is read into.
+pub fn json_schema(kind: SchemaKind) -> serde_json::Value {
+    let schema = match kind {
+        SchemaKind::Config => schema_for!(Config),
+        SchemaKind::PolicyRules => schema_for!(RulesFile),
+        SchemaKind::SecretRules => schema_for!(SecretsRulesFile),
+    };
+    schema.to_value()
+}
+
+/// The schema as pretty-printed JSON.
+pub fn render(kind: SchemaKind) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&json_schema(kind))?)
+}
+
+/// The properties of an object schema.
+pub fn properties(schema: &serde_json::Value) -> Vec<String> {
+    schema
+        .get("properties")
+        .and_then(serde_json::Value::as_object)
+        .map(|properties| properties.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Keys a policy rule takes.
+pub fn policy_rule_keys() -> Vec<String> {
+    properties(&schema_for!(Rule).to_value())
+}
+
+/// Keys a secrets rule takes.
+pub fn secret_rule_keys() -> Vec<String> {
+    properties(&schema_for!(SecretPattern).to_value())
+}
@@ -1,12 +1,24 @@
+use lazy_static::lazy_static;
 use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
-use crate::errors::Result;
+use crate::config::{Config, DEFAULT_SECRETS_RULES};
+use crate::errors::{AnsibleSecError, Result};
+use crate::model::Document;
+use crate::overrides::RuleOverrides;
+use crate::pattern::{Globs, Pattern};
+use crate::rules::{RuleExamples, RuleListing, RulesFileKind};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The bundled `rules/secrets.yml`, for scans run from a directory without
+/// it.
+const SHIPPED_SECRETS_RULES: &str = include_str!("../rules/secrets.yml");
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct SecretPattern {
     pub id: String,
     pub name: String,
@@ -15,15 +27,24 @@ pub struct SecretPattern {
     pub description: String,
     #[serde(default = "default_enabled")]
     pub enabled: bool,
+    #[serde(default, skip_serializing_if = "RuleExamples::is_empty")]
+    pub examples: RuleExamples,
+    /// Globs of the files the rule applies to; empty applies to every file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
 }
 
 fn default_enabled() -> bool {
     true
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct SecretsRulesFile {
-    rules: Vec<SecretPattern>,
+/// The format of a secrets rules file.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SecretsRulesFile {
+    /// Set when the rules alone do not tell a policy file from a secrets one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<RulesFileKind>,
+    pub rules: Vec<SecretPattern>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -59,88 +80,99 @@ impl Severity {
     }
 }
 
+/// Shortest string checked for entropy unless configured otherwise.
+pub const DEFAULT_MIN_ENTROPY_LENGTH: usize = 20;
+
+/// Rule ID of high-entropy string findings, which no pattern defines.
+pub const HIGH_ENTROPY_RULE: &str = "SECRET_HIGH_ENTROPY";
+
+lazy_static! {
+    static ref QUOTED: Regex = Regex::new(r#"["']([^"']+)["']"#).expect("valid regex");
+    static ref YAML_VALUE: Regex = Regex::new(r":\s*([a-zA-Z0-9+/=_-]+)").expect("valid regex");
+}
+
 #[derive(Debug, Clone)]
 pub struct SecretDetector {
     patterns: Vec<CompiledPattern>,
     entropy_threshold: f64,
+    min_entropy_length: usize,
+    /// Severity of high-entropy findings; `None` when the rule is disabled
+    entropy_severity: Option<Severity>,
 }
 
 #[derive(Debug, Clone)]
 struct CompiledPattern {
     id: String,
-    #[allow(dead_code)]
-    name: String,
-    regex: Regex,
+    regex: Pattern,
     severity: Severity,
     description: String,
+    /// Files the pattern applies to; `None` applies to every file
+    paths: Option<Globs>,
+}
+
+impl CompiledPattern {
+    /// An invalid pattern is an error naming the rule, so a broken rules
+    /// file is never loaded with rules silently missing.
+    fn compile(pattern: SecretPattern) -> Result<Self> {
+        let regex = Pattern::new(&pattern.pattern).map_err(|e| e.in_rule(&pattern.id))?;
+        let paths = if pattern.paths.is_empty() {
+            None
+        } else {
+            Some(
+                Globs::new(&pattern.paths)
+                    .map_err(|e| AnsibleSecError::InvalidRuleFormat(e).in_rule(&pattern.id))?,
+            )
+        };
+
+        Ok(Self {
+            id: pattern.id,
+            regex,
+            severity: Severity::from_str(&pattern.severity),
+            description: pattern.description,
+            paths,
+        })
+    }
 }
 
 impl SecretDetector {
     /// Create with custom patterns
-    #[allow(dead_code)]
     pub fn new(patterns: Vec<SecretPattern>, entropy_threshold: f64) -> Result<Self> {
         let mut compiled_patterns = Vec::new();
 
         for pattern in patterns {
-            match Regex::new(&pattern.pattern) {
-                Ok(regex) => {
-                    compiled_patterns.push(CompiledPattern {
-                        id: pattern.id,
-                        name: pattern.name,
-                        regex,
-                        severity: Severity::from_str(&pattern.severity),
-                        description: pattern.description,
-                    });
-                }
-                Err(e) => {
-                    eprintln!("Warning: Invalid regex pattern for {}: {}", pattern.name, e);
-                }
-            }
+            compiled_patterns.push(CompiledPattern::compile(pattern)?);
         }
 
         Ok(Self {
             patterns: compiled_patterns,
             entropy_threshold,
+            min_entropy_length: DEFAULT_MIN_ENTROPY_LENGTH,
+            entropy_severity: Some(Severity::Medium),
         })
     }
 
-    /// Create from external rules file
-    pub fn from_file<P: AsRef<Path>>(path: P, entropy_threshold: f64) -> Result<Self> {
-        let content = fs::read_to_string(path.as_ref())?;
-        let rules_file: SecretsRulesFile = serde_yaml::from_str(&content)?;
-
-        let mut patterns = Vec::new();
-        for rule in rules_file.rules {
-            if !rule.enabled {
-                continue;
-            }
+    /// Read the rules of a secrets rules file.
+    pub fn load_patterns<P: AsRef<Path>>(path: P) -> Result<Vec<SecretPattern>> {
+        let path = path.as_ref();
+        let in_file =
+            |e: String| AnsibleSecError::InvalidRuleFormat(format!("{}: {}", path.display(), e));
+        let content = fs::read_to_string(path).map_err(|e| in_file(e.to_string()))?;
+        Self::parse_patterns(&content).map_err(|e| in_file(e.to_string()))
+    }
 
-            match Regex::new(&rule.pattern) {
-                Ok(regex) => {
-                    patterns.push(CompiledPattern {
-                        id: rule.id,
-                        name: rule.name,
-                        regex,
-                        severity: Severity::from_str(&rule.severity),
-                        description: rule.description,
-                    });
-                }
-                Err(e) => {
-                    eprintln!("Warning: Invalid regex pattern for rule {}: {}", rule.id, e);
-                }
-            }
-        }
+    fn parse_patterns(content: &str) -> Result<Vec<SecretPattern>> {
+        let rules_file: SecretsRulesFile = serde_yaml::from_str(content)?;
+        Ok(rules_file.rules)
+    }
 
-        Ok(Self {
-            patterns,
-            entropy_threshold,
-        })
+    /// The secrets rules bundled with ansiblesec.
+    pub fn shipped_patterns() -> Result<Vec<SecretPattern>> {
+        Self::parse_patterns(SHIPPED_SECRETS_RULES)
     }
 
-    /// Fallback with minimal defaults
-    pub fn with_defaults(entropy_threshold: f64) -> Self {
-        let mut patterns = Vec::new();
-        let defaults = vec![
+    /// Patterns used when no secrets rules file is configured.
+    pub fn default_patterns() -> Vec<SecretPattern> {
+        let defaults = [
             (
                 "SECRET_AWS_ACCESS_KEY",
                 "AWS Access Key",
@@ -161,49 +193,189 @@ impl SecretDetector {
             ),
         ];
 
-        for (id, name, pattern, sev) in defaults {
-            if let Ok(regex) = Regex::new(pattern) {
-                patterns.push(CompiledPattern {
-                    id: id.to_string(),
-                    name: name.to_string(),
-                    regex,
-                    severity: Severity::from_str(sev),
-                    description: format!("{} detected", name),
-                });
+        defaults
+            .into_iter()
+            .map(|(id, name, pattern, severity)| SecretPattern {
+                id: id.to_string(),
+                name: name.to_string(),
+                pattern: pattern.to_string(),
+                severity: severity.to_string(),
+                description: format!("{} detected", name),
+                enabled: true,
+                examples: RuleExamples::default(),
+                paths: Vec::new(),
+            })
+            .collect()
+    }
+
+    /// The rules of the configured secrets rules file. A configured file
+    /// that cannot be read or parsed, or has a pattern that does not
+    /// compile, is an error; only the default path falls back to the
+    /// bundled copy when it does not exist.
+    pub fn configured_patterns(config: &Config) -> Result<Vec<SecretPattern>> {
+        let patterns = match config.secrets.rules_file.as_deref() {
+            Some(DEFAULT_SECRETS_RULES) if !Path::new(DEFAULT_SECRETS_RULES).exists() => {
+                return Self::shipped_patterns();
             }
+            Some(rules_file) => Self::load_patterns(rules_file)?,
+            None => return Ok(Self::default_patterns()),
+        };
+
+        for pattern in &patterns {
+            CompiledPattern::compile(pattern.clone()).map_err(|e| {
+                AnsibleSecError::InvalidRuleFormat(format!(
+                    "{}: {}",
+                    config.secrets.rules_file.as_deref().unwrap_or_default(),
+                    e
+                ))
+            })?;
         }
+        Ok(patterns)
+    }
 
-        Self {
-            patterns,
-            entropy_threshold,
+    /// A detector for `patterns` with the secrets settings, the profile and
+    /// the `rules:` overrides of `config` applied.
+    pub fn from_config(patterns: Vec<SecretPattern>, config: &Config) -> Result<Self> {
+        let rules = config.effective_rules()?;
+        let patterns = patterns
+            .into_iter()
+            .filter_map(|mut pattern| {
+                rules.apply(&pattern.id, &mut pattern.severity, &mut pattern.enabled);
+                pattern.enabled.then_some(pattern)
+            })
+            .collect();
+        let (severity, enabled, _) = Self::entropy_rule(&rules);
+        let mut detector = Self::new(patterns, config.secrets.entropy_threshold)?
+            .with_min_entropy_length(config.secrets.min_entropy_length);
+        detector.entropy_severity = enabled.then(|| Severity::from_str(&severity));
+        Ok(detector)
+    }
+
+    /// Severity and state of the high-entropy rule after overrides, with
+    /// what the overrides changed.
+    fn entropy_rule(rules: &RuleOverrides) -> (String, bool, Vec<String>) {
+        let mut severity = Severity::Medium.as_str().to_string();
+        let mut enabled = true;
+        let notes = rules.apply(HIGH_ENTROPY_RULE, &mut severity, &mut enabled);
+        (severity, enabled, notes)
+    }
+
+    /// The configured secrets rules as shown by `rules list`.
+    pub fn listings(config: &Config) -> Result<Vec<RuleListing>> {
+        let rules = config.effective_rules()?;
+        let mut listings: Vec<RuleListing> = Self::configured_patterns(config)?
+            .into_iter()
+            .map(|mut pattern| {
+                let overridden =
+                    rules.apply(&pattern.id, &mut pattern.severity, &mut pattern.enabled);
+                RuleListing {
+                    id: pattern.id,
+                    name: pattern.name,
+                    severity: pattern.severity,
+                    enabled: pattern.enabled,
+                    description: pattern.description,
+                    remediation: None,
+                    manual: false,
+                    overridden,
+                }
+            })
+            .collect();
+
+        let (severity, enabled, overridden) = Self::entropy_rule(&rules);
+        listings.push(RuleListing {
+            id: HIGH_ENTROPY_RULE.to_string(),
+            name: "High Entropy String".to_string(),
+            severity,
+            enabled,
+            description: format!(
+                "Strings of at least {} characters with entropy above {}",
+                config.secrets.min_entropy_length, config.secrets.entropy_threshold
+            ),
+            remediation: None,
+            manual: false,
+            overridden,
+        });
+        Ok(listings)
+    }
+
+    /// Only check strings of at least `length` characters for entropy.
+    pub fn with_min_entropy_length(mut self, length: usize) -> Self {
+        self.min_entropy_length = length;
+        self
+    }
+
+    /// This detector with rule `paths` matched relative to `root` instead of
+    /// the working directory.
+    pub fn relative_to(mut self, root: &Path) -> Self {
+        for pattern in &mut self.patterns {
+            pattern.paths = pattern.paths.take().map(|globs| globs.relative_to(root));
         }
+        self
+    }
+
+    /// Scan a parsed document. Secrets are matched on the raw text so that
+    /// comments and non-YAML content are covered too.
+    pub fn scan_document(&self, doc: &Document) -> Vec<SecretFinding> {
+        self.scan(&doc.content, Some(&doc.path))
+    }
+
+    /// Lines each rule's pattern gave up on so far, for the rules that gave
+    /// up on any.
+    pub fn gave_up(&self) -> Vec<(String, usize)> {
+        self.patterns
+            .iter()
+            .map(|pattern| (pattern.id.clone(), pattern.regex.gave_up()))
+            .filter(|(_, count)| *count > 0)
+            .collect()
     }
 
     pub fn scan_content(&self, content: &str) -> Vec<SecretFinding> {
+        self.scan(content, None)
+    }
+
+    /// Scan `content`; patterns scoped by `paths` only run when the file's
+    /// `path` is known and matches.
+    fn scan(&self, content: &str, path: Option<&Path>) -> Vec<SecretFinding> {
         let mut findings = Vec::new();
+        let patterns: Vec<&CompiledPattern> = self
+            .patterns
+            .iter()
+            .filter(|pattern| match (&pattern.paths, path) {
+                (None, _) => true,
+                (Some(globs), Some(path)) => globs.is_match(path),
+                (Some(_), None) => false,
+            })
+            .collect();
 
         for (line_num, line) in content.lines().enumerate() {
-            for pattern in &self.patterns {
+            for pattern in &patterns {
                 for mat in pattern.regex.find_iter(line) {
+                    let column = char_column(line, mat.start);
                     findings.push(SecretFinding {
                         line: line_num + 1,
-                        column: mat.start(),
+                        column,
+                        end_column: column + mat.text.chars().count(),
                         severity: pattern.severity,
                         rule_id: pattern.id.clone(),
                         message: pattern.description.clone(),
-                        context: Self::redact_secret(mat.as_str()),
+                        context: Self::redact_secret(mat.text),
                     });
                 }
             }
 
+            let Some(entropy_severity) = self.entropy_severity else {
+                continue;
+            };
             for (pos, potential) in self.extract_potential_secrets(line) {
                 let entropy = self.calculate_entropy(potential);
-                if entropy >= self.entropy_threshold && potential.len() >= 20 {
+                if entropy >= self.entropy_threshold {
+                    let column = char_column(line, pos);
                     findings.push(SecretFinding {
                         line: line_num + 1,
-                        column: pos,
-                        severity: Severity::Medium,
-                        rule_id: "SECRET_HIGH_ENTROPY".to_string(),
+                        column,
+                        end_column: column + potential.chars().count(),
+                        severity: entropy_severity,
+                        rule_id: HIGH_ENTROPY_RULE.to_string(),
                         message: format!("High entropy string detected (entropy: {:.2})", entropy),
                         context: Self::redact_secret(potential),
                     });
@@ -217,17 +389,13 @@ impl SecretDetector {
     fn extract_potential_secrets<'a>(&self, line: &'a str) -> Vec<(usize, &'a str)> {
         let mut results = Vec::new();
 
-        let quote_regex = Regex::new(r#"["']([^"']{20,})["']"#).unwrap();
-        for captures in quote_regex.captures_iter(line) {
-            if let Some(m) = captures.get(1) {
-                results.push((m.start(), m.as_str()));
-            }
-        }
-
-        let yaml_regex = Regex::new(r":\s*([a-zA-Z0-9+/=_-]{20,})").unwrap();
-        for captures in yaml_regex.captures_iter(line) {
-            if let Some(m) = captures.get(1) {
-                results.push((m.start(), m.as_str()));
+        for regex in [&*QUOTED, &*YAML_VALUE] {
+            for captures in regex.captures_iter(line) {
+                if let Some(m) = captures.get(1) {
+                    if m.as_str().chars().count() >= self.min_entropy_length {
+                        results.push((m.start(), m.as_str()));
+                    }
+                }
             }
         }
 
@@ -266,9 +434,16 @@ impl SecretDetector {
 #[derive(Debug, Clone, Serialize)]
 pub struct SecretFinding {
     pub line: usize,
+    /// 1-based character column
     pub column: usize,
+    pub end_column: usize,
     pub severity: Severity,
     pub rule_id: String,
     pub message: String,
     pub context: String,
 }
+
+/// 1-based character column of a byte offset within a line.
+fn char_column(line: &str, byte_offset: usize) -> usize {
+    line[..byte_offset].chars().count() + 1
+}
@@ -0,0 +1,110 @@
+use std::fs;
+use std::path::Path;
+
+use crate::errors::Result;
+use crate::model::Document;
+use crate::policy::PolicyEngine;
+use crate::rules::{Rule, RuleExamples, RulesEngine, RulesFileKind};
+use crate::secrets::{SecretDetector, SecretPattern};
+
+/// Outcome of running one rule against its examples.
+#[derive(Debug, Clone)]
+pub struct RuleTestResult {
+    pub rule_id: String,
+    /// Number of examples run
+    pub examples: usize,
+    pub failures: Vec<String>,
+}
+
+impl RuleTestResult {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Run the examples of every rule in a rules file.
+pub fn test_rules_file(path: &Path) -> Result<Vec<RuleTestResult>> {
+    let content = fs::read_to_string(path)?;
+    match RulesFileKind::detect(&content) {
+        RulesFileKind::Secrets => test_secret_rules(&SecretDetector::load_patterns(path)?),
+        RulesFileKind::Policy => test_policy_rules(RulesEngine::load(path)?.rules()),
+    }
+}
+
+pub fn test_policy_rules(rules: &[Rule]) -> Result<Vec<RuleTestResult>> {
+    let mut results = Vec::new();
+
+    for rule in rules.iter().filter(|r| !r.examples.is_empty()) {
+        // Disabled rules are tested too, on their own and wherever they are scoped
+        let mut rule = rule.clone();
+        rule.enabled = true;
+        rule.paths.clear();
+        let rule_id = rule.id.clone();
+        let examples = rule.examples.clone();
+        let engine = PolicyEngine::with_rules(RulesEngine::from_rules(vec![rule]))?;
+
+        results.push(run_examples(&rule_id, &examples, |snippet| {
+            let doc = Document::parse(Path::new("example.yml"), snippet);
+            Ok(engine
+                .check_policies(&doc)?
+                .iter()
+                .filter(|f| f.rule_id == rule_id)
+                .map(|f| f.line)
+                .collect())
+        })?);
+    }
+
+    Ok(results)
+}
+
+pub fn test_secret_rules(patterns: &[SecretPattern]) -> Result<Vec<RuleTestResult>> {
+    let mut results = Vec::new();
+
+    for pattern in patterns.iter().filter(|p| !p.examples.is_empty()) {
+        let mut pattern = pattern.clone();
+        pattern.enabled = true;
+        pattern.paths.clear();
+        let detector = SecretDetector::new(vec![pattern.clone()], f64::INFINITY)?;
+
+        results.push(run_examples(&pattern.id, &pattern.examples, |snippet| {
+            Ok(detector
+                .scan_content(snippet)
+                .iter()
+                .filter(|f| f.rule_id == pattern.id)
+                .map(|f| f.line)
+                .collect())
+        })?);
+    }
+
+    Ok(results)
+}
+
+/// `fire` returns the lines the rule reported in a snippet.
+fn run_examples(
+    rule_id: &str,
+    examples: &RuleExamples,
+    fire: impl Fn(&str) -> Result<Vec<usize>>,
+) -> Result<RuleTestResult> {
+    let mut failures = Vec::new();
+
+    for (idx, snippet) in examples.matches.iter().enumerate() {
+        if fire(snippet)?.is_empty() {
+            failures.push(format!("match example {} did not fire", idx + 1));
+        }
+    }
+    for (idx, snippet) in examples.no_match.iter().enumerate() {
+        if let Some(line) = fire(snippet)?.first() {
+            failures.push(format!(
+                "no_match example {} fired on line {}",
+                idx + 1,
+                line
+            ));
+        }
+    }
+
+    Ok(RuleTestResult {
+        rule_id: rule_id.to_string(),
+        examples: examples.matches.len() + examples.no_match.len(),
+        failures,
+    })
+}
@@ -0,0 +1,188 @@
+use chrono::NaiveDate;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::model::Document;
+use crate::scanner::Finding;
+use crate::yaml::Span;
+
+lazy_static! {
+    static ref DIRECTIVE: Regex =
+        Regex::new(r"(?:^|\s)#\s*ansiblesec:ignore\[([^\]]*)\](.*)$").expect("valid regex");
+    static ref REASON: Regex = Regex::new(r#"\breason\s*=\s*"([^"]*)""#).expect("valid regex");
+    static ref UNTIL: Regex = Regex::new(r"\buntil\s*=\s*(\S+)").expect("valid regex");
+}
+
+/// An `# ansiblesec:ignore[RULE_ID] reason="..." until=YYYY-MM-DD` comment
+/// that silenced a finding.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Suppression {
+    /// Line of the comment
+    pub line: usize,
+    /// Columns of the comment, from `#` to the end of the line
+    #[serde(default)]
+    pub column: usize,
+    #[serde(default)]
+    pub end_column: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Last day the suppression applies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<NaiveDate>,
+}
+
+impl Suppression {
+    pub fn is_expired(&self, today: NaiveDate) -> bool {
+        self.until.is_some_and(|until| until < today)
+    }
+}
+
+/// A parsed suppression comment and the lines it covers.
+#[derive(Debug, Clone)]
+struct Directive {
+    rule_ids: Vec<String>,
+    first_line: usize,
+    last_line: usize,
+    suppression: Suppression,
+}
+
+/// Mark findings covered by suppression comments. A comment at the end of
+/// a line covers that line; a comment on its own line covers the next line.
+/// When the covered line starts a play, block or task, the whole node is
+/// covered.
+pub fn apply(doc: &Document, findings: &mut [Finding]) {
+    let (directives, _) = directives(doc);
+    if directives.is_empty() {
+        return;
+    }
+
+    for finding in findings.iter_mut() {
+        if let Some(directive) = directives.iter().find(|d| {
+            (d.first_line..=d.last_line).contains(&finding.line)
+                && d.rule_ids.contains(&finding.rule_id)
+        }) {
+            finding.suppression = Some(directive.suppression.clone());
+        }
+    }
+}
+
+/// A `SUPPRESSION_INVALID` finding for each suppression comment that names
+/// no rule or has an `until` date that does not parse. Such a comment
+/// suppresses nothing.
+pub fn invalid(doc: &Document) -> Vec<Finding> {
+    directives(doc).1
+}
+
+fn directives(doc: &Document) -> (Vec<Directive>, Vec<Finding>) {
+    let lines: Vec<&str> = doc.content.lines().collect();
+    let nodes = node_spans(doc);
+    let mut directives = Vec::new();
+    let mut invalid = Vec::new();
+
+    for (idx, line) in lines.iter().enumerate() {
+        let Some(caps) = DIRECTIVE.captures(line) else {
+            continue;
+        };
+        let line_num = idx + 1;
+        let comment = caps.get(0).expect("whole match");
+        let hash = comment.start() + comment.as_str().find('#').unwrap_or(0);
+        let column = line[..hash].chars().count() + 1;
+        let end_column = line.trim_end().chars().count() + 1;
+
+        let rule_ids: Vec<String> = caps[1]
+            .split(',')
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+            .collect();
+        let invalid_finding = |message: String| Finding {
+            line: line_num,
+            column,
+            end_line: line_num,
+            end_column,
+            severity: "LOW".to_string(),
+            rule_id: "SUPPRESSION_INVALID".to_string(),
+            message,
+            context: Some(comment.as_str().trim().to_string()),
+            remediation: Some(
+                "Write # ansiblesec:ignore[RULE_ID] reason=\"...\" until=YYYY-MM-DD".to_string(),
+            ),
+            suppression: None,
+            baselined: false,
+        };
+        if rule_ids.is_empty() {
+            invalid.push(invalid_finding(
+                "Suppression comment names no rule".to_string(),
+            ));
+            continue;
+        }
+
+        let attributes = &caps[2];
+        let until = match UNTIL.captures(attributes) {
+            Some(until) => match NaiveDate::parse_from_str(&until[1], "%Y-%m-%d") {
+                Ok(date) => Some(date),
+                Err(_) => {
+                    invalid.push(invalid_finding(format!(
+                        "Suppression date '{}' is not YYYY-MM-DD; the comment suppresses nothing",
+                        &until[1]
+                    )));
+                    continue;
+                }
+            },
+            None => None,
+        };
+        let reason = REASON
+            .captures(attributes)
+            .map(|reason| reason[1].trim().to_string())
+            .filter(|reason| !reason.is_empty());
+
+        // A comment on its own line applies to the next line with content
+        let target = if line.trim_start().starts_with('#') {
+            match lines[idx + 1..].iter().position(|l| {
+                let l = l.trim();
+                !l.is_empty() && !l.starts_with('#')
+            }) {
+                Some(offset) => line_num + offset + 1,
+                None => continue,
+            }
+        } else {
+            line_num
+        };
+
+        let last_line = nodes
+            .iter()
+            .filter(|span| span.start.line == target)
+            .map(|span| span.end.line)
+            .max()
+            .unwrap_or(target);
+
+        directives.push(Directive {
+            rule_ids,
+            first_line: target,
+            last_line,
+            suppression: Suppression {
+                line: line_num,
+                column,
+                end_column,
+                reason,
+                until,
+            },
+        });
+    }
+
+    (directives, invalid)
+}
+
+/// Spans of every play, block and task in each document of the file.
+fn node_spans(doc: &Document) -> Vec<Span> {
+    let mut spans = Vec::new();
+
+    for doc in doc.documents() {
+        spans.extend(doc.plays.iter().map(|play| doc.value_span(&play.path)));
+        for task in doc.tasks() {
+            spans.push(doc.value_span(&task.task.path));
+            spans.extend(task.blocks.iter().map(|block| doc.value_span(&block.path)));
+        }
+    }
+
+    spans
+}
@@ -0,0 +1,756 @@
+use lazy_static::lazy_static;
+use schemars::schema_for;
+use serde_yaml::{Mapping, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::errors::{AnsibleSecError, Result};
+use crate::modules;
+use crate::pattern::{Globs, Pattern};
+use crate::policy::{self, PolicyEngine};
+use crate::rules::{Rule, RuleType, RulesEngine, RulesFileKind};
+use crate::schema;
+use crate::secrets::SecretPattern;
+use crate::yaml::{self, NodePath, PathExpr, PathSegment, Position, SpanIndex};
+
+const SEVERITIES: &[&str] = &["CRITICAL", "HIGH", "MEDIUM", "LOW", "INFO"];
+
+lazy_static! {
+    /// Keys of a policy rule, read from the schema of the type rules load
+    /// into so the two cannot drift apart.
+    static ref POLICY_RULE_KEYS: Vec<String> = schema::policy_rule_keys();
+    static ref SECRET_RULE_KEYS: Vec<String> = schema::secret_rule_keys();
+    /// Each `rule_type` with the parameters it accepts besides `type`.
+    static ref RULE_TYPES: Vec<(String, Vec<String>)> = schema_for!(RuleType)
+        .to_value()
+        .get("oneOf")
+        .and_then(serde_json::Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|variant| {
+            let kind = variant.pointer("/properties/type/const")?.as_str()?;
+            let keys = schema::properties(variant)
+                .into_iter()
+                .filter(|key| key != "type")
+                .collect();
+            Some((kind.to_string(), keys))
+        })
+        .collect();
+}
+
+/// Operators of a `Task` rule field condition.
+const FIELD_OPERATORS: &[&str] = &[
+    "equals", "in", "regex", "exists", "absent", "gt", "ge", "lt", "le", "bits",
+];
+
+/// A problem found in a rules file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub position: Position,
+    /// The rule the problem is in, when it has an ID
+    pub rule_id: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: ", self.position.line, self.position.column)?;
+        if let Some(ref rule_id) = self.rule_id {
+            write!(f, "[{}] ", rule_id)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Check a policy or secrets rules file and report every problem in it.
+pub fn validate_rules_file(path: &Path) -> Result<Vec<Diagnostic>> {
+    let content = fs::read_to_string(path)?;
+    Ok(validate_rules(&content))
+}
+
+/// Problems in rules file content, in source order. An empty list means the
+/// file loads and every rule compiles.
+pub fn validate_rules(content: &str) -> Vec<Diagnostic> {
+    let root: Value = match serde_yaml::from_str(content) {
+        Ok(root) => root,
+        Err(e) => {
+            let position = e
+                .location()
+                .map(|location| Position {
+                    line: location.line(),
+                    column: location.column(),
+                })
+                .unwrap_or(Position { line: 1, column: 1 });
+            return vec![Diagnostic {
+                position,
+                rule_id: None,
+                message: e.to_string(),
+            }];
+        }
+    };
+
+    let mut validator = Validator {
+        kind: RulesFileKind::detect(content),
+        spans: SpanIndex::build(content),
+        diagnostics: Vec::new(),
+    };
+    validator.check_file(&root);
+
+    let mut diagnostics = validator.diagnostics;
+    diagnostics.sort_by_key(|d| d.position);
+    diagnostics
+}
+
+/// A rule that passed every check, kept for the shadowing check.
+struct Checked {
+    id: String,
+    line: usize,
+    path: NodePath,
+    enabled: bool,
+    check: Check,
+    /// The rule's `paths` globs
+    scope: Vec<String>,
+}
+
+/// What a rule reports, reduced to what is needed to compare rules.
+enum Check {
+    Modules(Vec<String>),
+    Other(Value),
+    Manual,
+}
+
+struct Validator {
+    kind: RulesFileKind,
+    spans: SpanIndex,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Validator {
+    fn report(&mut self, path: &[PathSegment], rule_id: Option<&str>, message: String) {
+        let position = self.spans.value(path).unwrap_or_default().start;
+        self.push(position, rule_id, message);
+    }
+
+    fn report_key(&mut self, path: &[PathSegment], rule_id: Option<&str>, message: String) {
+        let position = self.spans.key(path).unwrap_or_default().start;
+        self.push(position, rule_id, message);
+    }
+
+    fn push(&mut self, position: Position, rule_id: Option<&str>, message: String) {
+        let position = if position.line == 0 {
+            Position { line: 1, column: 1 }
+        } else {
+            position
+        };
+        self.diagnostics.push(Diagnostic {
+            position,
+            rule_id: rule_id.map(str::to_string),
+            message,
+        });
+    }
+
+    fn check_file(&mut self, root: &Value) {
+        let Some(mapping) = root.as_mapping() else {
+            self.report(
+                &[],
+                None,
+                "expected a mapping with a `rules` list".to_string(),
+            );
+            return;
+        };
+        self.check_keys(&[], mapping, &["kind", "rules"], None, "the rules file");
+        if let Some(kind) = mapping.get("kind") {
+            if serde_yaml::from_value::<RulesFileKind>(kind.clone()).is_err() {
+                self.report(
+                    &yaml::key(&[], "kind"),
+                    None,
+                    format!(
+                        "invalid kind `{}`, expected policy or secrets",
+                        scalar_text(kind)
+                    ),
+                );
+            }
+        }
+
+        let rules_path = yaml::key(&[], "rules");
+        let Some(rules) = mapping.get("rules").and_then(Value::as_sequence) else {
+            self.report(&[], None, "expected a `rules` list".to_string());
+            return;
+        };
+
+        let mut first_seen: HashMap<String, usize> = HashMap::new();
+        let mut checked = Vec::new();
+        for (idx, rule) in rules.iter().enumerate() {
+            let path = yaml::child(&rules_path, PathSegment::Index(idx));
+            if let Some(rule) = self.check_rule(&path, rule, &mut first_seen) {
+                checked.push(rule);
+            }
+        }
+
+        self.check_shadowing(&checked);
+    }
+
+    fn check_rule(
+        &mut self,
+        path: &[PathSegment],
+        rule: &Value,
+        first_seen: &mut HashMap<String, usize>,
+    ) -> Option<Checked> {
+        let Some(mapping) = rule.as_mapping() else {
+            self.report(path, None, "rule must be a mapping".to_string());
+            return None;
+        };
+        let before = self.diagnostics.len();
+
+        let id = mapping
+            .get("id")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let rule_id = id.as_deref();
+        let line = self.spans.value(path).unwrap_or_default().start.line;
+
+        let keys: &[String] = match self.kind {
+            RulesFileKind::Policy => &POLICY_RULE_KEYS,
+            RulesFileKind::Secrets => &SECRET_RULE_KEYS,
+        };
+        let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+        self.check_keys(path, mapping, &keys, rule_id, "a rule");
+
+        let required: &[&str] = match self.kind {
+            RulesFileKind::Policy => &["id", "name", "severity"],
+            RulesFileKind::Secrets => &["id", "name", "pattern", "severity", "description"],
+        };
+        for key in required {
+            if !mapping.contains_key(*key) {
+                self.report(path, rule_id, format!("missing required key `{}`", key));
+            }
+        }
+        for key in ["id", "name", "description", "remediation", "pattern"] {
+            self.check_string(path, mapping, key, rule_id);
+        }
+
+        if let Some(ref id) = id {
+            let id_path = yaml::key(path, "id");
+            match first_seen.get(id) {
+                Some(first) => self.report(
+                    &id_path,
+                    rule_id,
+                    format!("duplicate rule ID, first defined on line {}", first),
+                ),
+                None => {
+                    first_seen.insert(id.clone(), line);
+                }
+            }
+        }
+
+        if let Some(severity) = mapping.get("severity").and_then(Value::as_str) {
+            if !SEVERITIES.contains(&severity.to_uppercase().as_str()) {
+                self.report(
+                    &yaml::key(path, "severity"),
+                    rule_id,
+                    format!(
+                        "invalid severity `{}`, expected one of {}",
+                        severity,
+                        SEVERITIES.join(", ")
+                    ),
+                );
+            }
+        }
+
+        if mapping
+            .get("enabled")
+            .is_some_and(|enabled| !enabled.is_bool())
+        {
+            self.report(
+                &yaml::key(path, "enabled"),
+                rule_id,
+                "`enabled` must be true or false".to_string(),
+            );
+        }
+
+        if let Some(pattern) = mapping.get("pattern").and_then(Value::as_str) {
+            self.check_pattern(&yaml::key(path, "pattern"), pattern, rule_id);
+        }
+
+        if let Some(examples) = mapping.get("examples") {
+            self.check_examples(&yaml::key(path, "examples"), examples, rule_id);
+        }
+
+        self.check_string_list(path, mapping, "paths", false, rule_id);
+        if let Some(Value::Sequence(globs)) = mapping.get("paths") {
+            for (idx, glob) in globs.iter().enumerate() {
+                let Some(glob) = glob.as_str() else { continue };
+                if let Err(e) = Globs::new(&[glob.to_string()]) {
+                    let glob_path = yaml::child(&yaml::key(path, "paths"), PathSegment::Index(idx));
+                    self.report(&glob_path, rule_id, e);
+                }
+            }
+        }
+
+        if self.kind == RulesFileKind::Policy {
+            self.check_policy_check(path, mapping, rule_id);
+        }
+
+        // Anything the checks above miss surfaces when the rule is loaded
+        // and compiled the way a scan would
+        if self.diagnostics.len() > before {
+            return None;
+        }
+        let check = match self.kind {
+            RulesFileKind::Policy => self.compile_policy_rule(path, rule, rule_id)?,
+            RulesFileKind::Secrets => self.compile_secret_rule(path, rule, rule_id)?,
+        };
+
+        Some(Checked {
+            id: id.unwrap_or_default(),
+            line,
+            path: path.to_vec(),
+            enabled: mapping
+                .get("enabled")
+                .and_then(Value::as_bool)
+                .unwrap_or(true),
+            check,
+            scope: serde_yaml::from_value(mapping.get("paths").cloned().unwrap_or_default())
+                .unwrap_or_default(),
+        })
+    }
+
+    /// The `rule_type`, `module` or `pattern` that says what a policy rule checks.
+    fn check_policy_check(&mut self, path: &[PathSegment], rule: &Mapping, rule_id: Option<&str>) {
+        let checks: Vec<_> = ["rule_type", "module", "pattern"]
+            .into_iter()
+            .filter(|key| rule.contains_key(*key))
+            .collect();
+        match checks.len() {
+            0 => self.report(
+                path,
+                rule_id,
+                "rule has no check: set one of `rule_type`, `module` or `pattern`".to_string(),
+            ),
+            1 => {}
+            _ => self.report_key(
+                &yaml::key(path, checks[1]),
+                rule_id,
+                format!("`{}` conflicts with `{}`", checks[1], checks[0]),
+            ),
+        }
+
+        if let Some(module) = rule.get("module") {
+            if !is_string_or_strings(module) {
+                self.report(
+                    &yaml::key(path, "module"),
+                    rule_id,
+                    "`module` must be a module name or a list of names".to_string(),
+                );
+            }
+        }
+
+        if let Some(rule_type) = rule.get("rule_type") {
+            self.check_rule_type(&yaml::key(path, "rule_type"), rule_type, rule_id);
+        }
+    }
+
+    fn check_rule_type(&mut self, path: &[PathSegment], rule_type: &Value, rule_id: Option<&str>) {
+        let Some(mapping) = rule_type.as_mapping() else {
+            self.report(
+                path,
+                rule_id,
+                "`rule_type` must be a mapping with a `type`".to_string(),
+            );
+            return;
+        };
+        let Some(kind) = mapping.get("type").and_then(Value::as_str) else {
+            self.report(path, rule_id, "`rule_type` is missing `type`".to_string());
+            return;
+        };
+        let Some((_, params)) = RULE_TYPES.iter().find(|(name, _)| name == kind) else {
+            let kinds: Vec<&str> = RULE_TYPES.iter().map(|(name, _)| name.as_str()).collect();
+            self.report(
+                &yaml::key(path, "type"),
+                rule_id,
+                format!(
+                    "unknown rule type `{}`, expected one of {}",
+                    kind,
+                    kinds.join(", ")
+                ),
+            );
+            return;
+        };
+
+        let mut keys = vec!["type"];
+        keys.extend(params.iter().map(String::as_str));
+        self.check_keys(path, mapping, &keys, rule_id, &format!("a {} rule", kind));
+
+        match kind {
+            "DisallowModule" | "Module" => {
+                self.check_string_list(path, mapping, "modules", true, rule_id)
+            }
+            "RequireVault" => self.check_string_list(path, mapping, "exceptions", true, rule_id),
+            "Task" => {
+                self.check_string_list(path, mapping, "modules", false, rule_id);
+                if let Some(condition) = mapping.get("match") {
+                    self.check_condition(&yaml::key(path, "match"), condition, rule_id);
+                }
+            }
+            "Pattern" => match mapping.get("pattern").and_then(Value::as_str) {
+                Some(pattern) => self.check_pattern(&yaml::key(path, "pattern"), pattern, rule_id),
+                None => self.check_string(path, mapping, "pattern", rule_id),
+            },
+            "CheckPermissions" => {
+                let mode_path = yaml::key(path, "max_permissions");
+                match mapping.get("max_permissions") {
+                    Some(Value::String(mode)) if policy::parse_mode(mode).is_some() => {}
+                    Some(mode) => self.report(
+                        &mode_path,
+                        rule_id,
+                        format!(
+                            "`max_permissions` must be a quoted octal file mode such as \"0644\", found `{}`",
+                            scalar_text(mode)
+                        ),
+                    ),
+                    None => self.report(
+                        path,
+                        rule_id,
+                        "missing required key `max_permissions`".to_string(),
+                    ),
+                }
+                self.check_string(path, mapping, "state", rule_id);
+            }
+            "CustomYamlPath" => self.check_yaml_path(path, mapping, rule_id),
+            _ => {}
+        }
+    }
+
+    fn check_yaml_path(
+        &mut self,
+        path: &[PathSegment],
+        rule_type: &Mapping,
+        rule_id: Option<&str>,
+    ) {
+        match rule_type.get("path") {
+            Some(Value::String(expr)) => {
+                if let Err(e) = PathExpr::parse(expr) {
+                    self.report(&yaml::key(path, "path"), rule_id, e);
+                }
+            }
+            Some(_) => self.check_string(path, rule_type, "path", rule_id),
+            None => self.report(path, rule_id, "missing required key `path`".to_string()),
+        }
+
+        for (key, allowed) in [
+            ("scope", &["document", "play", "task"][..]),
+            ("mode", &["exists", "absent", "equals", "matches"][..]),
+        ] {
+            if let Some(value) = rule_type.get(key) {
+                if !value.as_str().is_some_and(|value| allowed.contains(&value)) {
+                    self.report(
+                        &yaml::key(path, key),
+                        rule_id,
+                        format!(
+                            "invalid {} `{}`, expected one of {}",
+                            key,
+                            scalar_text(value),
+                            allowed.join(", ")
+                        ),
+                    );
+                }
+            }
+        }
+
+        match rule_type.get("regex") {
+            Some(Value::String(regex)) => {
+                self.check_pattern(&yaml::key(path, "regex"), regex, rule_id)
+            }
+            Some(_) => self.check_string(path, rule_type, "regex", rule_id),
+            None => {}
+        }
+    }
+
+    /// A `Task` rule condition: `all`, `any`, `not` or a field test.
+    fn check_condition(&mut self, path: &[PathSegment], condition: &Value, rule_id: Option<&str>) {
+        let Some(mapping) = condition.as_mapping() else {
+            self.report(path, rule_id, "a condition must be a mapping".to_string());
+            return;
+        };
+
+        for combinator in ["all", "any", "not"] {
+            let Some(inner) = mapping.get(combinator) else {
+                continue;
+            };
+            self.check_keys(path, mapping, &[combinator], rule_id, "a condition");
+            let inner_path = yaml::key(path, combinator);
+            match (combinator, inner) {
+                ("not", inner) => self.check_condition(&inner_path, inner, rule_id),
+                (_, Value::Sequence(conditions)) => {
+                    for (idx, condition) in conditions.iter().enumerate() {
+                        let path = yaml::child(&inner_path, PathSegment::Index(idx));
+                        self.check_condition(&path, condition, rule_id);
+                    }
+                }
+                _ => self.report(
+                    &inner_path,
+                    rule_id,
+                    format!("`{}` must be a list of conditions", combinator),
+                ),
+            }
+            return;
+        }
+
+        let mut keys = vec!["field", "octal"];
+        keys.extend_from_slice(FIELD_OPERATORS);
+        self.check_keys(path, mapping, &keys, rule_id, "a field condition");
+
+        match mapping.get("field") {
+            Some(Value::String(_)) => {}
+            Some(_) => self.check_string(path, mapping, "field", rule_id),
+            None => self.report(
+                path,
+                rule_id,
+                "a condition needs `field`, `all`, `any` or `not`".to_string(),
+            ),
+        }
+
+        let operators: Vec<_> = FIELD_OPERATORS
+            .iter()
+            .filter(|op| mapping.contains_key(**op))
+            .collect();
+        if operators.len() != 1 {
+            self.report(
+                path,
+                rule_id,
+                format!(
+                    "a field condition needs exactly one of {}",
+                    FIELD_OPERATORS.join(", ")
+                ),
+            );
+        }
+
+        for key in ["exists", "absent", "octal"] {
+            if mapping.get(key).is_some_and(|value| !value.is_bool()) {
+                self.report(
+                    &yaml::key(path, key),
+                    rule_id,
+                    format!("`{}` must be true or false", key),
+                );
+            }
+        }
+        if mapping.get("in").is_some_and(|value| !value.is_sequence()) {
+            self.report(
+                &yaml::key(path, "in"),
+                rule_id,
+                "`in` must be a list".to_string(),
+            );
+        }
+        match mapping.get("regex") {
+            Some(Value::String(regex)) => {
+                self.check_pattern(&yaml::key(path, "regex"), regex, rule_id)
+            }
+            Some(_) => self.check_string(path, mapping, "regex", rule_id),
+            None => {}
+        }
+        match mapping.get("bits") {
+            Some(Value::String(bits)) if policy::parse_mode(bits).is_some() => {}
+            Some(bits) => self.report(
+                &yaml::key(path, "bits"),
+                rule_id,
+                format!(
+                    "`bits` must be a quoted octal file mode such as \"0002\", found `{}`",
+                    scalar_text(bits)
+                ),
+            ),
+            None => {}
+        }
+    }
+
+    fn check_examples(&mut self, path: &[PathSegment], examples: &Value, rule_id: Option<&str>) {
+        let Some(mapping) = examples.as_mapping() else {
+            self.report(
+                path,
+                rule_id,
+                "`examples` must be a mapping with `match` and `no_match` lists".to_string(),
+            );
+            return;
+        };
+        self.check_keys(path, mapping, &["match", "no_match"], rule_id, "`examples`");
+        for key in ["match", "no_match"] {
+            self.check_string_list(path, mapping, key, false, rule_id);
+        }
+    }
+
+    fn check_pattern(&mut self, path: &[PathSegment], pattern: &str, rule_id: Option<&str>) {
+        if let Err(e) = Pattern::new(pattern) {
+            self.report(path, rule_id, error_message(e));
+        }
+    }
+
+    fn check_keys(
+        &mut self,
+        path: &[PathSegment],
+        mapping: &Mapping,
+        allowed: &[&str],
+        rule_id: Option<&str>,
+        what: &str,
+    ) {
+        for key in mapping.keys() {
+            let name = scalar_text(key);
+            if !allowed.contains(&name.as_str()) {
+                self.report_key(
+                    &yaml::key(path, &name),
+                    rule_id,
+                    format!(
+                        "unknown key `{}` in {}, expected one of {}",
+                        name,
+                        what,
+                        allowed.join(", ")
+                    ),
+                );
+            }
+        }
+    }
+
+    fn check_string(
+        &mut self,
+        path: &[PathSegment],
+        mapping: &Mapping,
+        key: &str,
+        rule_id: Option<&str>,
+    ) {
+        if mapping.get(key).is_some_and(|value| !value.is_string()) {
+            self.report(
+                &yaml::key(path, key),
+                rule_id,
+                format!("`{}` must be a string", key),
+            );
+        }
+    }
+
+    fn check_string_list(
+        &mut self,
+        path: &[PathSegment],
+        mapping: &Mapping,
+        key: &str,
+        required: bool,
+        rule_id: Option<&str>,
+    ) {
+        match mapping.get(key) {
+            Some(Value::Sequence(items)) if items.iter().all(Value::is_string) => {}
+            Some(_) => self.report(
+                &yaml::key(path, key),
+                rule_id,
+                format!("`{}` must be a list of strings", key),
+            ),
+            None if required => {
+                self.report(path, rule_id, format!("missing required key `{}`", key))
+            }
+            None => {}
+        }
+    }
+
+    fn compile_policy_rule(
+        &mut self,
+        path: &[PathSegment],
+        rule: &Value,
+        rule_id: Option<&str>,
+    ) -> Option<Check> {
+        let mut rule: Rule = match serde_yaml::from_value(rule.clone()) {
+            Ok(rule) => rule,
+            Err(e) => {
+                self.report(path, rule_id, e.to_string());
+                return None;
+            }
+        };
+
+        // Disabled rules are compiled too; enabling one must not break a scan
+        rule.enabled = true;
+        let check = match rule.rule_type {
+            RuleType::Module { ref modules } | RuleType::DisallowModule { ref modules } => {
+                Check::Modules(modules.iter().map(|m| modules::canonical_name(m)).collect())
+            }
+            RuleType::Manual => Check::Manual,
+            ref rule_type => Check::Other(serde_yaml::to_value(rule_type).unwrap_or_default()),
+        };
+
+        let engine = RulesEngine::from_rules(vec![rule]);
+        if let Err(e) = PolicyEngine::with_rules(engine) {
+            let message = error_message(e);
+            let prefix = format!("rule {}: ", rule_id.unwrap_or_default());
+            let message = message
+                .strip_prefix(&prefix)
+                .unwrap_or(&message)
+                .to_string();
+            self.report(&yaml::key(path, "rule_type"), rule_id, message);
+            return None;
+        }
+
+        Some(check)
+    }
+
+    fn compile_secret_rule(
+        &mut self,
+        path: &[PathSegment],
+        rule: &Value,
+        rule_id: Option<&str>,
+    ) -> Option<Check> {
+        match serde_yaml::from_value::<SecretPattern>(rule.clone()) {
+            Ok(pattern) => Some(Check::Other(Value::String(pattern.pattern))),
+            Err(e) => {
+                self.report(path, rule_id, e.to_string());
+                None
+            }
+        }
+    }
+
+    /// An enabled rule is shadowed when an earlier enabled rule already
+    /// reports everything it would, in every file it applies to.
+    fn check_shadowing(&mut self, rules: &[Checked]) {
+        let enabled: Vec<&Checked> = rules.iter().filter(|rule| rule.enabled).collect();
+
+        for (idx, rule) in enabled.iter().enumerate() {
+            let shadowed_by = enabled[..idx]
+                .iter()
+                .filter(|earlier| earlier.scope.is_empty() || earlier.scope == rule.scope)
+                .find(|earlier| match (&earlier.check, &rule.check) {
+                    (Check::Modules(earlier), Check::Modules(later)) => {
+                        later.iter().all(|module| earlier.contains(module))
+                    }
+                    (Check::Other(earlier), Check::Other(later)) => earlier == later,
+                    _ => false,
+                });
+
+            if let Some(earlier) = shadowed_by {
+                let message = format!(
+                    "rule is shadowed by {} on line {}, which already reports everything it does",
+                    earlier.id, earlier.line
+                );
+                self.report(&yaml::key(&rule.path, "id"), Some(&rule.id), message);
+            }
+        }
+    }
+}
+
+fn is_string_or_strings(value: &Value) -> bool {
+    match value {
+        Value::String(_) => true,
+        Value::Sequence(items) => items.iter().all(Value::is_string),
+        _ => false,
+    }
+}
+
+fn scalar_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => serde_yaml::to_string(other)
+            .map(|s| s.trim_end().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// The message of a rule error without the error kind prefix.
+fn error_message(error: AnsibleSecError) -> String {
+    match error {
+        AnsibleSecError::InvalidRuleFormat(message) => message,
+        other => other.to_string(),
+    }
+}
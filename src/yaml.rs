@@ -0,0 +1,719 @@
+use std::collections::HashMap;
+
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::{Marker, TScalarStyle};
+
+/// A position in a source file. Lines and columns are 1-based; columns count
+/// characters, not bytes.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A source range. `end` points just past the last character, as in SARIF.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    /// Span covering a single line from `start` to `end` (1-based, exclusive end).
+    pub fn on_line(line: usize, start: usize, end: usize) -> Self {
+        Self {
+            start: Position {
+                line,
+                column: start,
+            },
+            end: Position { line, column: end },
+        }
+    }
+
+    /// Span covering the text of a line, without surrounding whitespace.
+    pub fn of_line(line: usize, text: &str) -> Self {
+        let indent = text.chars().take_while(|c| c.is_whitespace()).count();
+        let len = text.trim().chars().count();
+        Self::on_line(line, indent + 1, indent + len + 1)
+    }
+}
+
+/// One step in the path from a document root to a node.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+pub type NodePath = Vec<PathSegment>;
+
+/// Append a segment to a path.
+pub fn child(path: &[PathSegment], segment: PathSegment) -> NodePath {
+    let mut path = path.to_vec();
+    path.push(segment);
+    path
+}
+
+/// Append a mapping key to a path.
+pub fn key(path: &[PathSegment], key: &str) -> NodePath {
+    child(path, PathSegment::Key(key.to_string()))
+}
+
+/// A path expression selecting nodes in a YAML value: dotted keys, `[N]`
+/// list indices and `*` for every key or item, e.g. `vars.*`,
+/// `tasks[0].name` or `[*].hosts`. A numeric key also indexes a list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathExpr {
+    selectors: Vec<Selector>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Selector {
+    Key(String),
+    Index(usize),
+    Any,
+}
+
+impl PathExpr {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let mut selectors = Vec::new();
+
+        for part in expr.split('.') {
+            let (name, mut rest) = part.split_at(part.find('[').unwrap_or(part.len()));
+            match name {
+                "" if rest.is_empty() => return Err(format!("empty segment in '{}'", expr)),
+                "" => {}
+                "*" => selectors.push(Selector::Any),
+                _ => selectors.push(Selector::Key(name.to_string())),
+            }
+
+            while !rest.is_empty() {
+                let close = rest
+                    .find(']')
+                    .filter(|_| rest.starts_with('['))
+                    .ok_or_else(|| format!("malformed index in '{}'", expr))?;
+                let selector = match &rest[1..close] {
+                    "*" => Selector::Any,
+                    index => Selector::Index(
+                        index
+                            .parse()
+                            .map_err(|_| format!("invalid index '{}' in '{}'", index, expr))?,
+                    ),
+                };
+                selectors.push(selector);
+                rest = &rest[close + 1..];
+            }
+        }
+
+        Ok(Self { selectors })
+    }
+
+    /// Every node under `root` the expression reaches, with its path
+    /// relative to `root`.
+    pub fn select<'a>(
+        &self,
+        root: &'a serde_yaml::Value,
+    ) -> Vec<(NodePath, &'a serde_yaml::Value)> {
+        let mut matches = Vec::new();
+        select(root, &self.selectors, Vec::new(), &mut matches);
+        matches
+    }
+
+    /// Like `select`, starting from a mapping such as a play or a task.
+    pub fn select_in<'a>(
+        &self,
+        obj: &'a serde_yaml::Mapping,
+    ) -> Vec<(NodePath, &'a serde_yaml::Value)> {
+        let mut matches = Vec::new();
+        if let Some((selector, rest)) = self.selectors.split_first() {
+            select_mapping(obj, selector, rest, Vec::new(), &mut matches);
+        }
+        matches
+    }
+}
+
+fn select<'a>(
+    value: &'a serde_yaml::Value,
+    selectors: &[Selector],
+    path: NodePath,
+    matches: &mut Vec<(NodePath, &'a serde_yaml::Value)>,
+) {
+    use serde_yaml::Value;
+
+    let Some((selector, rest)) = selectors.split_first() else {
+        matches.push((path, value));
+        return;
+    };
+
+    match (selector, value) {
+        (_, Value::Mapping(obj)) => select_mapping(obj, selector, rest, path, matches),
+        (Selector::Key(key), Value::Sequence(items)) => {
+            if let Some((index, next)) = key
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| items.get(i).map(|item| (i, item)))
+            {
+                select(next, rest, child(&path, PathSegment::Index(index)), matches);
+            }
+        }
+        (Selector::Index(index), Value::Sequence(items)) => {
+            if let Some(next) = items.get(*index) {
+                select(
+                    next,
+                    rest,
+                    child(&path, PathSegment::Index(*index)),
+                    matches,
+                );
+            }
+        }
+        (Selector::Any, Value::Sequence(items)) => {
+            for (index, next) in items.iter().enumerate() {
+                select(next, rest, child(&path, PathSegment::Index(index)), matches);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn select_mapping<'a>(
+    obj: &'a serde_yaml::Mapping,
+    selector: &Selector,
+    rest: &[Selector],
+    path: NodePath,
+    matches: &mut Vec<(NodePath, &'a serde_yaml::Value)>,
+) {
+    match selector {
+        Selector::Key(key) => {
+            if let Some(next) = obj.get(key.as_str()) {
+                select(next, rest, self::key(&path, key), matches);
+            }
+        }
+        Selector::Any => {
+            for (key, next) in obj {
+                if let Some(key) = key.as_str() {
+                    select(next, rest, self::key(&path, key), matches);
+                }
+            }
+        }
+        Selector::Index(_) => {}
+    }
+}
+
+/// Source spans of every node in a YAML document, keyed by node path.
+///
+/// Built from the same text `serde_yaml` parses, so paths into a
+/// `serde_yaml::Value` can be mapped back to exact source positions.
+#[derive(Debug, Clone, Default)]
+pub struct SpanIndex {
+    values: HashMap<NodePath, Span>,
+    keys: HashMap<NodePath, Span>,
+}
+
+impl SpanIndex {
+    /// Index the first document in `content`. Returns an empty index if the
+    /// content cannot be parsed.
+    pub fn build(content: &str) -> Self {
+        Self::load(content, false)
+            .into_iter()
+            .next()
+            .unwrap_or_default()
+    }
+
+    /// Index every document of a `---` separated stream, in order. Spans
+    /// are positions in the whole of `content`. Stops at the first document
+    /// that cannot be parsed.
+    pub fn build_stream(content: &str) -> Vec<Self> {
+        Self::load(content, true)
+    }
+
+    fn load(content: &str, multi: bool) -> Vec<Self> {
+        let mut builder = SpanBuilder::new(content);
+        // Documents completed before a syntax error are kept
+        let _ = Parser::new_from_str(content).load(&mut builder, multi);
+        builder.documents
+    }
+
+    /// Span of the node at `path`, or of its nearest indexed ancestor (e.g.
+    /// for nodes reached through an alias).
+    pub fn value(&self, path: &[PathSegment]) -> Option<Span> {
+        (0..=path.len())
+            .rev()
+            .find_map(|len| self.values.get(&path[..len]).copied())
+    }
+
+    /// Span of the mapping key whose value is at `path`, falling back to the
+    /// value span.
+    pub fn key(&self, path: &[PathSegment]) -> Option<Span> {
+        self.keys.get(path).copied().or_else(|| self.value(path))
+    }
+}
+
+enum Frame {
+    Mapping {
+        path: NodePath,
+        start: Position,
+        end: Position,
+        pending_key: Option<String>,
+        /// Set while a non-scalar key is being skipped
+        complex_key: bool,
+    },
+    Sequence {
+        path: NodePath,
+        start: Position,
+        end: Position,
+        next_index: usize,
+    },
+    /// A complex (non-scalar) mapping key; its contents are not indexed.
+    Ignored { depth: usize },
+}
+
+struct SpanBuilder {
+    chars: Vec<char>,
+    /// Index of the document being read
+    index: SpanIndex,
+    /// Indexes of the documents read so far
+    documents: Vec<SpanIndex>,
+    stack: Vec<Frame>,
+    done: bool,
+}
+
+impl SpanBuilder {
+    fn new(content: &str) -> Self {
+        Self {
+            chars: content.chars().collect(),
+            index: SpanIndex::default(),
+            documents: Vec::new(),
+            stack: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// Determine where the next node goes. Returns `None` if the node is a
+    /// mapping key (already recorded) or is inside an ignored subtree.
+    fn next_node_path(&mut self, key_span: Option<(&str, Span)>) -> Option<NodePath> {
+        match self.stack.last_mut() {
+            None => Some(Vec::new()),
+            Some(Frame::Sequence {
+                path, next_index, ..
+            }) => {
+                let node = child(path, PathSegment::Index(*next_index));
+                *next_index += 1;
+                Some(node)
+            }
+            Some(Frame::Mapping {
+                path,
+                pending_key,
+                complex_key,
+                ..
+            }) => {
+                if let Some(key) = pending_key.take() {
+                    return Some(child(path, PathSegment::Key(key)));
+                }
+
+                match key_span {
+                    Some((key, span)) => {
+                        let node = child(path, PathSegment::Key(key.to_string()));
+                        self.index.keys.insert(node, span);
+                        *pending_key = Some(key.to_string());
+                    }
+                    None => *complex_key = true,
+                }
+                None
+            }
+            Some(Frame::Ignored { .. }) => None,
+        }
+    }
+
+    /// Grow the parent container to cover a completed child node. Block
+    /// mapping start events are reported at their first value, so the start
+    /// can move back as well.
+    fn extend_parent(&mut self, span: Span) {
+        if let Some(Frame::Mapping { start, end, .. }) | Some(Frame::Sequence { start, end, .. }) =
+            self.stack.last_mut()
+        {
+            *start = span.start.min(*start);
+            *end = span.end.max(*end);
+        }
+    }
+
+    fn start_container(&mut self, mark: Marker, is_mapping: bool) {
+        if let Some(Frame::Ignored { depth }) = self.stack.last_mut() {
+            *depth += 1;
+            return;
+        }
+
+        let is_key = matches!(
+            self.stack.last(),
+            Some(Frame::Mapping {
+                pending_key: None,
+                ..
+            })
+        );
+        if is_key {
+            self.next_node_path(None);
+            self.stack.push(Frame::Ignored { depth: 0 });
+            return;
+        }
+
+        let path = self.next_node_path(None).unwrap_or_default();
+        let start = position(mark);
+        self.stack.push(if is_mapping {
+            Frame::Mapping {
+                path,
+                start,
+                end: start,
+                pending_key: None,
+                complex_key: false,
+            }
+        } else {
+            Frame::Sequence {
+                path,
+                start,
+                end: start,
+                next_index: 0,
+            }
+        });
+    }
+
+    fn end_container(&mut self) {
+        match self.stack.pop() {
+            Some(Frame::Ignored { depth }) if depth > 0 => {
+                self.stack.push(Frame::Ignored { depth: depth - 1 });
+            }
+            Some(Frame::Ignored { .. }) => {
+                // The complex key is done; its value comes next
+                if let Some(Frame::Mapping {
+                    pending_key,
+                    complex_key,
+                    ..
+                }) = self.stack.last_mut()
+                {
+                    *complex_key = false;
+                    *pending_key = Some(String::new());
+                }
+            }
+            Some(Frame::Mapping {
+                path, start, end, ..
+            })
+            | Some(Frame::Sequence {
+                path, start, end, ..
+            }) => {
+                let span = Span { start, end };
+                self.index.values.insert(path, span);
+                self.extend_parent(span);
+                if self.stack.is_empty() {
+                    self.done = true;
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn scalar(&mut self, value: &str, style: TScalarStyle, mark: Marker) {
+        if matches!(self.stack.last(), Some(Frame::Ignored { .. })) {
+            return;
+        }
+
+        let span = Span {
+            start: position(mark),
+            end: self.scalar_end(value, style, mark),
+        };
+
+        if let Some(path) = self.next_node_path(Some((value, span))) {
+            self.index.values.insert(path, span);
+            if self.stack.is_empty() {
+                self.done = true;
+            }
+        }
+        self.extend_parent(span);
+    }
+
+    /// Aliases are indexed as a whole; the nodes they refer to keep their
+    /// original spans.
+    fn alias(&mut self, mark: Marker) {
+        let name: String = self
+            .chars
+            .iter()
+            .skip(mark.index())
+            .take_while(|c| !c.is_whitespace() && !matches!(c, ',' | ']' | '}'))
+            .collect();
+        self.scalar(&name, TScalarStyle::Plain, mark);
+    }
+
+    /// Find where a scalar ends by walking the source from its start.
+    fn scalar_end(&self, value: &str, style: TScalarStyle, mark: Marker) -> Position {
+        let mut idx = mark.index();
+        let mut line = mark.line();
+        let mut col = mark.col();
+        let mut advance = |idx: &mut usize, chars: &[char]| {
+            if chars.get(*idx) == Some(&'\n') {
+                line += 1;
+                col = 0;
+            } else {
+                col += 1;
+            }
+            *idx += 1;
+        };
+
+        match style {
+            TScalarStyle::SingleQuoted | TScalarStyle::DoubleQuoted => {
+                let quote = self.chars.get(idx).copied().unwrap_or('"');
+                advance(&mut idx, &self.chars);
+                while let Some(&c) = self.chars.get(idx) {
+                    if quote == '"' && c == '\\' {
+                        advance(&mut idx, &self.chars);
+                    } else if c == quote {
+                        // '' is an escaped quote inside single quotes
+                        if quote == '\'' && self.chars.get(idx + 1) == Some(&'\'') {
+                            advance(&mut idx, &self.chars);
+                        } else {
+                            advance(&mut idx, &self.chars);
+                            break;
+                        }
+                    }
+                    advance(&mut idx, &self.chars);
+                }
+            }
+            TScalarStyle::Plain => {
+                // Plain scalars may be folded over several lines; whitespace
+                // in the value matches any run of whitespace in the source.
+                let mut value_chars = value.chars().peekable();
+                while let Some(c) = value_chars.next() {
+                    if c.is_whitespace() {
+                        while value_chars.peek().is_some_and(|c| c.is_whitespace()) {
+                            value_chars.next();
+                        }
+                        while self.chars.get(idx).is_some_and(|c| c.is_whitespace()) {
+                            advance(&mut idx, &self.chars);
+                        }
+                    } else if self.chars.get(idx) == Some(&c) {
+                        advance(&mut idx, &self.chars);
+                    } else {
+                        break;
+                    }
+                }
+            }
+            TScalarStyle::Literal | TScalarStyle::Folded => {
+                return self.block_scalar_end(mark, value.is_empty());
+            }
+        }
+
+        Position {
+            line,
+            column: col + 1,
+        }
+    }
+
+    /// Block scalars are reported from their first content line and run
+    /// over every following line indented at least as deep.
+    fn block_scalar_end(&self, mark: Marker, empty: bool) -> Position {
+        let source: String = self.chars[mark.index().min(self.chars.len())..]
+            .iter()
+            .collect();
+        let mut lines = source.lines();
+        let first = lines.next().unwrap_or_default();
+        let mut end = Position {
+            line: mark.line(),
+            column: mark.col() + first.trim_end().chars().count() + 1,
+        };
+        if empty {
+            return end;
+        }
+
+        for (offset, line) in lines.enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if line.chars().take_while(|c| *c == ' ').count() < mark.col() {
+                break;
+            }
+            end = Position {
+                line: mark.line() + offset + 1,
+                column: line.trim_end().chars().count() + 1,
+            };
+        }
+
+        end
+    }
+}
+
+impl MarkedEventReceiver for SpanBuilder {
+    fn on_event(&mut self, ev: Event, mark: Marker) {
+        match ev {
+            Event::DocumentStart => {
+                self.stack.clear();
+                self.done = false;
+                return;
+            }
+            Event::DocumentEnd => {
+                self.documents.push(std::mem::take(&mut self.index));
+                return;
+            }
+            _ if self.done => return,
+            _ => {}
+        }
+
+        match ev {
+            Event::Scalar(value, style, _, _) => self.scalar(&value, style, mark),
+            Event::Alias(_) => self.alias(mark),
+            Event::MappingStart(..) => self.start_container(mark, true),
+            Event::SequenceStart(..) => self.start_container(mark, false),
+            Event::MappingEnd | Event::SequenceEnd => self.end_container(),
+            _ => {}
+        }
+    }
+}
+
+fn position(mark: Marker) -> Position {
+    Position {
+        line: mark.line(),
+        column: mark.col() + 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_expr_select() {
+        let value: serde_yaml::Value = serde_yaml::from_str(
+            "- hosts: all\n  tasks:\n    - name: a\n    - name: b\n- hosts: db\n  gather_facts: false\n",
+        )
+        .unwrap();
+
+        let selected = |expr: &str| -> Vec<NodePath> {
+            PathExpr::parse(expr)
+                .unwrap()
+                .select(&value)
+                .into_iter()
+                .map(|(path, _)| path)
+                .collect()
+        };
+
+        assert_eq!(
+            selected("[*].hosts"),
+            [path(&["0", "hosts"]), path(&["1", "hosts"])]
+        );
+        assert_eq!(
+            selected("0.tasks[1].name"),
+            [path(&["0", "tasks", "1", "name"])]
+        );
+        assert_eq!(selected("*.tasks.*.name").len(), 2);
+        assert!(selected("[*].gather_facts").len() == 1);
+        assert!(selected("[2].hosts").is_empty());
+
+        assert!(PathExpr::parse("tasks..name").is_err());
+        assert!(PathExpr::parse("tasks[x]").is_err());
+        assert!(PathExpr::parse("tasks[0").is_err());
+    }
+
+    fn path(segments: &[&str]) -> NodePath {
+        segments
+            .iter()
+            .map(|s| match s.parse::<usize>() {
+                Ok(idx) => PathSegment::Index(idx),
+                Err(_) => PathSegment::Key(s.to_string()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_key_and_value_spans() {
+        let content = "- hosts: all\n  tasks:\n    - name: \"Install\"\n      apt: name=nginx\n";
+        let index = SpanIndex::build(content);
+
+        let name = path(&["0", "tasks", "0", "name"]);
+        assert_eq!(index.key(&name), Some(Span::on_line(3, 7, 11)));
+        assert_eq!(index.value(&name), Some(Span::on_line(3, 13, 22)));
+
+        let apt = path(&["0", "tasks", "0", "apt"]);
+        assert_eq!(index.value(&apt), Some(Span::on_line(4, 12, 22)));
+
+        let task = index.value(&path(&["0", "tasks", "0"])).unwrap();
+        assert_eq!(task.start, Position { line: 3, column: 7 });
+        assert_eq!(
+            task.end,
+            Position {
+                line: 4,
+                column: 22
+            }
+        );
+    }
+
+    #[test]
+    fn test_multiline_scalars() {
+        let content = "cmd: >\n  echo one\n  echo two\nnext: a\n  b\n";
+        let index = SpanIndex::build(content);
+
+        assert_eq!(
+            index.value(&path(&["cmd"])),
+            Some(Span {
+                start: Position { line: 2, column: 3 },
+                end: Position {
+                    line: 3,
+                    column: 11
+                },
+            })
+        );
+        assert_eq!(
+            index.value(&path(&["next"])).unwrap().end,
+            Position { line: 5, column: 4 }
+        );
+    }
+
+    #[test]
+    fn test_unknown_path_falls_back_to_ancestor() {
+        let content = "base: &b\n  x: 1\nother: *b\n";
+        let index = SpanIndex::build(content);
+
+        let aliased = path(&["other", "x"]);
+        assert_eq!(index.value(&aliased), Some(Span::on_line(3, 8, 10)));
+    }
+
+    #[test]
+    fn test_stream_spans() {
+        let content = "a: 1\n---\n# second\nb:\n  c: 2\n...\n---\n- x\n";
+        let documents = SpanIndex::build_stream(content);
+
+        assert_eq!(documents.len(), 3);
+        assert_eq!(
+            documents[0].value(&path(&["a"])),
+            Some(Span::on_line(1, 4, 5))
+        );
+        assert_eq!(
+            documents[1].key(&path(&["b"])),
+            Some(Span::on_line(4, 1, 2))
+        );
+        assert_eq!(
+            documents[1].value(&path(&["b", "c"])),
+            Some(Span::on_line(5, 6, 7))
+        );
+        assert_eq!(
+            documents[2].value(&path(&["0"])),
+            Some(Span::on_line(8, 3, 4))
+        );
+        assert_eq!(
+            SpanIndex::build(content).value(&[]),
+            documents[0].value(&[])
+        );
+        assert!(SpanIndex::build_stream("a: [1\n---\nb: 2\n").is_empty());
+
+        let values: Vec<serde_yaml::Value> = serde_yaml::Deserializer::from_str(content)
+            .map(serde::Deserialize::deserialize)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(values.len(), documents.len());
+    }
+}